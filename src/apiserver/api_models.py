@@ -20,11 +20,21 @@ class Run(pydantic.BaseModel):
     user_id: uuid.UUID
     contest_name: str
     status: typing.Mapping[str, str] = pydantic.Field(default_factory=dict)
+    score: typing.Mapping[str, int] = pydantic.Field(default_factory=dict)
+    """
+    Maps protocol kind to run score, as seen in this protocol.
+    """
+    subtasks: typing.Mapping[str, typing.Mapping[str, int]] = pydantic.Field(
+        default_factory=dict)
+    """
+    Maps protocol kind to scores of subtasks, visible in this protocol.
+    """
 
     @staticmethod
     def from_db(doc: db_models.RunMainProj) -> Run:
         return Run(id=doc['id'], toolchain_name=doc['toolchain_name'],
-                   user_id=doc['user_id'], contest_name=doc['contest_name'], problem_name=doc['problem_name'], status=doc['status'])
+                   user_id=doc['user_id'], contest_name=doc['contest_name'], problem_name=doc['problem_name'], status=doc['status'],
+                   score=doc.get('score', {}), subtasks=doc.get('subtasks', {}))
 
 
 class Toolchain(pydantic.BaseModel):
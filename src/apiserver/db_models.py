@@ -35,10 +35,20 @@ class RunMainProj(BaseModel):
     """
     Each item is (protocol_kind, f"{status_kind}:{status_code}" as in invoker_api::Status).
     """
+    score: typing.Mapping[str, int] = Field(default_factory=dict)
+    """
+    Each item is (protocol_kind, score of run as seen in this protocol).
+    """
+    subtasks: typing.Mapping[str, typing.Mapping[str, int]] = Field(
+        default_factory=dict)
+    """
+    Each item is (protocol_kind, {subtask_id: score}). Subtasks which are
+    hidden in protocol are omitted.
+    """
 
 
 RunMainProj.FIELDS = ['id', 'toolchain_name',
-                      'problem_name', 'user_id', 'contest_name', 'status']
+                      'problem_name', 'user_id', 'contest_name', 'status', 'score', 'subtasks']
 
 
 class RunSourceProj(BaseModel):
@@ -6,6 +6,7 @@ import uuid
 import bcrypt
 import typing
 import base64
+import hashlib
 import pymongo
 import pydantic
 import os
@@ -55,6 +56,20 @@ class RunPatch(pydantic.BaseModel):
     Base64-encoded build artifact.
     Can be binary, can be archive.
     """
+    score: typing.Optional[typing.Mapping[str, int]] = None
+    """
+    behavior: merge
+    merge key: protocol kind
+    run scores to set, e.g. `{"Full": 50}`
+    """
+    subtasks: typing.Optional[typing.Mapping[str,
+                                             typing.Mapping[str, int]]] = None
+    """
+    behavior: merge
+    merge key: protocol kind
+    subtask scores to set, e.g. `{"Full": {"1": 20, "2": 30}}`.
+    Subtasks which are hidden in protocol are omitted.
+    """
 
 
 class UserCreationParams(pydantic.BaseModel):
@@ -334,6 +349,12 @@ def create_app(db_connect: typing.Callable[[], pymongo.database.Database]) -> fa
                     raise ValueError(
                         "RunPatch.status[*] must have length exactly 2")
                 p['$set'][f"status.{status_to_add[0]}"] = status_to_add[1]
+        if patch.score is not None:
+            for kind, score in patch.score.items():
+                p['$set'][f"score.{kind}"] = score
+        if patch.subtasks is not None:
+            for kind, subtasks in patch.subtasks.items():
+                p['$set'][f"subtasks.{kind}"] = subtasks
         updated_run = db.runs.find_one_and_update(
             {'id': run_id}, p, projection=db_models.RunMainProj.FIELDS, return_document=pymongo.ReturnDocument.AFTER)
         if updated_run is None:
@@ -398,12 +419,15 @@ def create_app(db_connect: typing.Callable[[], pymongo.database.Database]) -> fa
     def route_put_problem(problem_id: str, problem_manifest: bytes = fastapi.Form(...), problem_assets: str = fastapi.File(...), db: pymongo.database.Database = fastapi.Depends(get_db)):
         # TODO docs
         # TODO auth
+        assets = base64.b64decode(problem_assets)
         doc = {
             'problem-name': problem_id,
             'manifest': problem_manifest,
-            'assets': base64.b64decode(problem_assets)
+            'assets': assets,
+            # invokers compare it with revision of cached problem
+            'revision': hashlib.sha256(problem_manifest + assets).hexdigest()
         }
-        db.problems.insert_one(doc)
+        db.problems.replace_one({'problem-name': problem_id}, doc, upsert=True)
         # TODO better return value
         return True
 
@@ -148,7 +148,9 @@ class TestPatches:
         judge_status = ('Full', 'Accepted:FULL_SOLUTION')
         patch = {
             'binary': base64.b64encode(b"Compiled run").decode(),
-            'status': [judge_status]
+            'status': [judge_status],
+            'score': {'Full': 50},
+            'subtasks': {'Full': {'1': 20, '2': 30}}
         }
 
         patch_run = client.patch(
@@ -157,6 +159,8 @@ class TestPatches:
 
         expected_patched_run = create_run.json()
         expected_patched_run['status'][judge_status[0]] = judge_status[1]
+        expected_patched_run['score'] = patch['score']
+        expected_patched_run['subtasks'] = patch['subtasks']
 
         # TODO verify `binary` field is updated too
         # to check this, we must call /runs/<id>/binary
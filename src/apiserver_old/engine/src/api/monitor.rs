@@ -3,18 +3,27 @@ use std::{collections::BTreeMap, convert::TryInto, num::NonZeroU32};
 
 fn lower_run(_run: &db::schema::Run, inv: &invoker_api::InvokeOutcomeHeader) -> ranker::Run {
     let mut subtasks = BTreeMap::new();
-    //let outcome_header = inv.o
-    subtasks.insert(
-        ranker::SubtaskId(NonZeroU32::new(1).unwrap()),
-        inv.score.unwrap_or(0) as i32,
-    );
-    // TODO: properly support subtasks
+    for row in &inv.subtasks {
+        // subtasks with hidden score are not taken into account
+        if let Some(score) = row.score {
+            subtasks.insert(ranker::SubtaskId(row.subtask_id.0), score as i32);
+        }
+    }
+    if inv.subtasks.is_empty() {
+        // problem has no subtasks, or header was created by older invoker
+        subtasks.insert(
+            ranker::SubtaskId(NonZeroU32::new(1).unwrap()),
+            inv.score.unwrap_or(0) as i32,
+        );
+    }
     // TODO: keep party info for runs
     // TODO: keep problem_id for runs
+    // TODO: keep submission time for runs
     ranker::Run {
         subtasks,
         party: ranker::PartyId(NonZeroU32::new(1).unwrap()),
         problem: ranker::ProblemId(NonZeroU32::new(1).unwrap()),
+        time: std::time::Duration::from_secs(0),
     }
 }
 
@@ -70,7 +79,13 @@ pub(crate) async fn route_get(
 
     let ranker_config = ranker::Config {
         penalty_aggregation: ranker::PenaltyAggregation::Sum,
+        attempt_penalty: 20,
         score_problems: ranker::ProblemScoreAggregationTarget::All,
+        ranking: ranker::Ranking::Score,
+        freeze_time: None,
+        coloring: ranker::Coloring::ScoreBuckets(100),
+        start_offsets: Default::default(),
+        elapsed: None,
     };
 
     let monitor = ranker::build_monitor(
@@ -3,7 +3,9 @@ pub struct Run {
     pub contest_name: String,
     pub id: String,
     pub problem_name: String,
+    pub score: Option<std::collections::BTreeMap<String, i64>>,
     pub status: Option<std::collections::BTreeMap<String, String>>,
+    pub subtasks: Option<std::collections::BTreeMap<String, std::collections::BTreeMap<String, i64>>>,
     pub toolchain_name: String,
     pub user_id: String,
 }
@@ -92,12 +94,24 @@ impl<ContestName, Id, ProblemName, ToolchainName, UserId> RunBuilder<ContestName
         unsafe { std::mem::transmute(self) }
     }
 
+    #[inline]
+    pub fn score(mut self, value: impl Iterator<Item = (String, impl Into<i64>)>) -> Self {
+        self.body.score = Some(value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into());
+        self
+    }
+
     #[inline]
     pub fn status(mut self, value: impl Iterator<Item = (String, impl Into<String>)>) -> Self {
         self.body.status = Some(value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into());
         self
     }
 
+    #[inline]
+    pub fn subtasks(mut self, value: impl Iterator<Item = (String, impl Iterator<Item = (String, impl Into<i64>)>)>) -> Self {
+        self.body.subtasks = Some(value.map(|(key, value)| (key, value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into())).collect::<std::collections::BTreeMap<_, _>>().into());
+        self
+    }
+
     #[inline]
     pub fn toolchain_name(mut self, value: impl Into<String>) -> RunBuilder<ContestName, Id, ProblemName, crate::generics::ToolchainNameExists, UserId> {
         self.body.toolchain_name = value.into();
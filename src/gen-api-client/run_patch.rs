@@ -3,7 +3,9 @@
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RunPatch {
     pub binary: Option<String>,
+    pub score: Option<std::collections::BTreeMap<String, i64>>,
     pub status: Option<Vec<Vec<String>>>,
+    pub subtasks: Option<std::collections::BTreeMap<String, std::collections::BTreeMap<String, i64>>>,
 }
 
 impl RunPatch {
@@ -52,11 +54,23 @@ impl RunPatchBuilder {
         self
     }
 
+    #[inline]
+    pub fn score(mut self, value: impl Iterator<Item = (String, impl Into<i64>)>) -> Self {
+        self.body.score = Some(value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into());
+        self
+    }
+
     #[inline]
     pub fn status(mut self, value: impl Iterator<Item = impl Iterator<Item = impl Into<String>>>) -> Self {
         self.body.status = Some(value.map(|value| value.map(|value| value.into()).collect::<Vec<_>>().into()).collect::<Vec<_>>().into());
         self
     }
+
+    #[inline]
+    pub fn subtasks(mut self, value: impl Iterator<Item = (String, impl Iterator<Item = (String, impl Into<i64>)>)>) -> Self {
+        self.body.subtasks = Some(value.map(|(key, value)| (key, value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into())).collect::<std::collections::BTreeMap<_, _>>().into());
+        self
+    }
 }
 
 /// Builder created by [`RunPatch::patch_run`](./struct.RunPatch.html#method.patch_run) method for a `PATCH` operation associated with `RunPatch`.
@@ -86,11 +100,23 @@ impl<RunId> RunPatchPatchBuilder<RunId> {
         self
     }
 
+    #[inline]
+    pub fn score(mut self, value: impl Iterator<Item = (String, impl Into<i64>)>) -> Self {
+        self.inner.body.score = Some(value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into());
+        self
+    }
+
     #[inline]
     pub fn status(mut self, value: impl Iterator<Item = impl Iterator<Item = impl Into<String>>>) -> Self {
         self.inner.body.status = Some(value.map(|value| value.map(|value| value.into()).collect::<Vec<_>>().into()).collect::<Vec<_>>().into());
         self
     }
+
+    #[inline]
+    pub fn subtasks(mut self, value: impl Iterator<Item = (String, impl Iterator<Item = (String, impl Into<i64>)>)>) -> Self {
+        self.inner.body.subtasks = Some(value.map(|(key, value)| (key, value.map(|(key, value)| (key, value.into())).collect::<std::collections::BTreeMap<_, _>>().into())).collect::<std::collections::BTreeMap<_, _>>().into());
+        self
+    }
 }
 
 impl<Client: crate::client::ApiClient + Sync + 'static> crate::client::Sendable<Client> for RunPatchPatchBuilder<crate::generics::RunIdExists> {
@@ -12,6 +12,16 @@ pub struct JudgeLogTestRow {
     pub test_answer: Option<String>,
     pub time_usage: Option<u64>,
     pub memory_usage: Option<u64>,
+    /// Checker stdout, stderr and comments, and description of checker
+    /// failure if any. Only included into full judge log.
+    #[serde(default)]
+    pub checker_log: Option<String>,
+    /// Effective time limit (i.e. scaled for toolchain) in milliseconds
+    #[serde(default)]
+    pub time_limit: Option<u64>,
+    /// Effective memory limit in bytes
+    #[serde(default)]
+    pub memory_limit: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,6 +40,10 @@ pub struct JudgeLog {
     pub score: u32,
     pub is_full: bool,
     pub status: Status,
+    /// Valuer stderr. Only included into full judge log of runs which were
+    /// not judged because of judge fault.
+    #[serde(default)]
+    pub valuer_log: Option<String>,
 }
 
 impl Default for JudgeLog {
@@ -46,6 +60,7 @@ impl Default for JudgeLog {
                 code: "".to_string(),
                 kind: StatusKind::NotSet,
             },
+            valuer_log: None,
         }
     }
 }
@@ -55,22 +55,29 @@ pub mod status_codes {
         TOOLCHAIN_SEARCH_ERROR,
         BUILT,
         COMPILATION_TIMED_OUT,
-        COMPILER_FAILED
+        COMPILER_FAILED,
+        INVALID_ARCHIVE
     );
 
     // per-test status codes
     declare_code!(
         TIME_LIMIT_EXCEEDED,
+        OUTPUT_LIMIT_EXCEEDED,
+        SECURITY_VIOLATION,
         RUNTIME_ERROR,
         TEST_PASSED,
         JUDGE_FAULT,
         WRONG_ANSWER,
         PRESENTATION_ERROR,
-        LAUNCH_ERROR
+        LAUNCH_ERROR,
+        ANSWER_MISSING
     );
 
     // aggregated status codes
     declare_code!(ACCEPTED, PARTIAL_SOLUTION, BUILD_ERROR);
+
+    // custom run status codes
+    declare_code!(RUN_FINISHED);
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Ord, PartialOrd, PartialEq, Eq, Hash)]
@@ -91,6 +98,25 @@ pub struct JudgeRequest {
     pub request_id: uuid::Uuid,
     /// Run source
     pub run_source: Vec<u8>,
+    /// If set, invoker re-values results of previous judging instead of
+    /// judging run from scratch.
+    /// Only CLI source sets it: JJS API does not store full judge logs, so
+    /// runs fetched from API are always judged from scratch.
+    #[serde(default)]
+    pub revalue: Option<RevalueRequest>,
+    /// If set, run is executed once on custom input instead of judging.
+    #[serde(default)]
+    pub custom_run: Option<CustomRunRequest>,
+}
+
+/// Results of previous judging, which should be passed to fresh valuer.
+///
+/// Test statuses are replayed to valuer as if tests were executed again.
+/// Only tests which are missing in `previous_judge_log` are executed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevalueRequest {
+    /// Full judge log, produced by previous judging of this run
+    pub previous_judge_log: judge_log::JudgeLog,
 }
 
 impl std::fmt::Debug for JudgeRequest {
@@ -104,10 +130,52 @@ impl std::fmt::Debug for JudgeRequest {
                 "run_source",
                 &format_args!("{} bytes", self.run_source.len()),
             )
+            .field("revalue", &self.revalue.is_some())
+            .field("custom_run", &self.custom_run)
+            .finish()
+    }
+}
+
+/// Request to run solution on arbitrary input (i.e. "run on my test").
+///
+/// Run is compiled as usual and executed once, in the same sandbox as
+/// problem tests. Neither checker nor valuer is used.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CustomRunRequest {
+    /// Data passed to solution stdin
+    pub stdin: Vec<u8>,
+    /// Limits for solution. By default, limits of first problem test are used.
+    #[serde(default)]
+    pub limits: Option<pom::Limits>,
+}
+
+impl std::fmt::Debug for CustomRunRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomRunRequest")
+            .field("stdin", &format_args!("{} bytes", self.stdin.len()))
+            .field("limits", &self.limits)
             .finish()
     }
 }
 
+/// Result of custom run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomRunResult {
+    /// `RUN_FINISHED` if solution exited with zero code, otherwise per-test
+    /// status code, e.g. `RUNTIME_ERROR` or `TIME_LIMIT_EXCEEDED`.
+    pub status: Status,
+    /// Exit code, if solution exited
+    pub exit_code: Option<i64>,
+    /// Base64-encoded solution stdout, truncated to output limit
+    pub stdout: String,
+    /// Base64-encoded solution stderr, truncated to output limit
+    pub stderr: String,
+    /// Time usage in milliseconds
+    pub time_usage: Option<u64>,
+    /// Memory usage in bytes
+    pub memory_usage: Option<u64>,
+}
+
 /// Pass this to invoker running in CLI mode
 ///
 /// See fields' description in [JudgeRequest](JudgeRequest)
@@ -118,6 +186,17 @@ pub struct CliJudgeRequest {
     pub problem_id: String,
     pub request_id: uuid::Uuid,
     pub run_source: PathBuf,
+    /// Path to full judge log (`protocol-full.json`) of previous judging.
+    /// If set, run is re-valued.
+    #[serde(default)]
+    pub revalue: Option<PathBuf>,
+    /// Path to file with custom input. If set, run is executed on this input
+    /// only.
+    #[serde(default)]
+    pub custom_run_stdin: Option<PathBuf>,
+    /// Limits for custom run
+    #[serde(default)]
+    pub custom_run_limits: Option<pom::Limits>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -125,6 +204,9 @@ pub struct JudgeOutcomeHeader {
     pub score: Option<u32>,
     pub status: Status,
     pub kind: valuer_proto::JudgeLogKind,
+    /// Scores for subtasks, as seen in judge log of this kind
+    #[serde(default)]
+    pub subtasks: Vec<judge_log::JudgeLogSubtaskRow>,
 }
 
 /// Represents Live Status Update. Some fields can be None always, or only in some updates.
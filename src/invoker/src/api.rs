@@ -4,15 +4,17 @@
 //! This API is desired for advanced use cases, such as integrating invoker
 //! in custom system.
 
-use crate::controller::JudgeRequestAndCallbacks;
+use crate::{controller::JudgeRequestAndCallbacks, metrics::METRICS};
 use actix_web::{web, App, HttpResponse, HttpServer, Responder};
 use anyhow::Context as _;
+use std::sync::Arc;
 use tracing::instrument;
 
 #[derive(Clone)]
 struct State {
     task_tx: async_mpmc::Sender<JudgeRequestAndCallbacks>,
     cancel_token: tokio::sync::CancellationToken,
+    problem_loader: Arc<problem_loader::Loader>,
 }
 
 async fn route_ping() -> impl Responder {
@@ -25,22 +27,46 @@ async fn route_ready() -> impl Responder {
     ""
 }
 
+async fn route_metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(METRICS.render())
+}
+
 async fn route_shutdown(state: web::Data<State>) -> impl Responder {
     tracing::info!("invoker api: got shutdown request");
     state.cancel_token.cancel();
     "cancellation triggered"
 }
 
+/// Makes invoker reload problem on next use, e.g. after problem was updated
+async fn route_invalidate_problem(
+    state: web::Data<State>,
+    problem_name: web::Path<String>,
+) -> impl Responder {
+    let count = state.problem_loader.invalidate(Some(problem_name.as_str()));
+    tracing::info!(problem_name=%problem_name, "invoker api: invalidated problem");
+    format!("invalidated {} problems", count)
+}
+
+async fn route_invalidate_all_problems(state: web::Data<State>) -> impl Responder {
+    let count = state.problem_loader.invalidate(None);
+    tracing::info!("invoker api: invalidated all problems");
+    format!("invalidated {} problems", count)
+}
+
 #[actix_rt::main]
-#[instrument(skip(task_tx))]
+#[instrument(skip(task_tx, problem_loader))]
 async fn exec(
     cancel_token: tokio::sync::CancellationToken,
     bind_addr: std::net::SocketAddr,
     task_tx: async_mpmc::Sender<JudgeRequestAndCallbacks>,
+    problem_loader: Arc<problem_loader::Loader>,
 ) -> anyhow::Result<()> {
     let state = State {
         task_tx,
         cancel_token: cancel_token.clone(),
+        problem_loader,
     };
 
     let srv = HttpServer::new(move || {
@@ -49,7 +75,16 @@ async fn exec(
             .wrap(actix_web::middleware::Logger::default())
             .route("/", web::get().to(route_ping))
             .route("/ready", web::get().to(route_ready))
+            .route("/metrics", web::get().to(route_metrics))
             .route("/state/shutdown", web::post().to(route_shutdown))
+            .route(
+                "/problems/invalidate",
+                web::post().to(route_invalidate_all_problems),
+            )
+            .route(
+                "/problems/{problem_name}/invalidate",
+                web::post().to(route_invalidate_problem),
+            )
     })
     .workers(1)
     .disable_signals()
@@ -66,9 +101,10 @@ pub async fn start(
     cancel_token: tokio::sync::CancellationToken,
     bind_addr: std::net::SocketAddr,
     task_tx: async_mpmc::Sender<JudgeRequestAndCallbacks>,
+    problem_loader: Arc<problem_loader::Loader>,
 ) -> Result<(), anyhow::Error> {
     tokio::task::spawn_blocking(move || {
-        if let Err(err) = exec(cancel_token, bind_addr, task_tx) {
+        if let Err(err) = exec(cancel_token, bind_addr, task_tx, problem_loader) {
             eprintln!("Invoker api service: serve error: {:#}", err);
         }
     });
@@ -25,6 +25,24 @@ pub struct InvokerConfig {
     pub expose_host_dirs: Option<Vec<String>>,
     /// Configures how invoker should resolve problems
     pub problems: problem_loader::LoaderConfig,
+    /// If set, invoker accepts remote workers
+    #[serde(default)]
+    pub remote_workers: Option<RemoteWorkersConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemoteWorkersConfig {
+    /// Endpoint to listen on: `tcp://<address>:<port>` or `unix://<path>`
+    pub listen: String,
+    /// Remote workers must present this token (`JJS_WORKER_TOKEN`)
+    pub token: String,
+    /// Connection with remote workers is not encrypted, so by default TCP
+    /// endpoint must be a loopback address. Set this only if network between
+    /// controller and workers is trusted (e.g. it is VPN).
+    #[serde(default)]
+    pub trusted_network: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
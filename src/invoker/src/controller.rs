@@ -8,8 +8,9 @@ mod task_loading;
 mod toolchains;
 
 use crate::{
+    metrics::METRICS,
     scheduler::Scheduler,
-    worker::{JudgeOutcome, Request, Response},
+    worker::{JudgeOutcome, JudgeStats, Request, Response},
 };
 use anyhow::Context;
 use notify::Notifier;
@@ -25,6 +26,8 @@ use uuid::Uuid;
 struct LoweredJudgeRequestExtensions {
     notifier: Notifier,
     invocation_dir: PathBuf,
+    /// Keeps problem revision used by this request
+    _problem: problem_loader::ProblemHandle,
 }
 
 pub enum InvocationFinishReason {
@@ -33,6 +36,16 @@ pub enum InvocationFinishReason {
     TestingDone,
 }
 
+impl InvocationFinishReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            InvocationFinishReason::Fault => "fault",
+            InvocationFinishReason::CompileError => "compile_error",
+            InvocationFinishReason::TestingDone => "testing_done",
+        }
+    }
+}
+
 /// Contains both judging task and back address.
 /// Each task source is represented as mpsc channel of `TaskInfo`s
 pub struct JudgeRequestAndCallbacks {
@@ -70,6 +83,13 @@ pub trait JudgeResponseCallbacks: Send + Sync {
         invocation_id: Uuid,
         lsu: invoker_api::LiveStatusUpdate,
     ) -> anyhow::Result<()>;
+
+    /// Called when custom run is finished, before `set_finished`.
+    async fn deliver_custom_run_result(
+        &self,
+        invocation_id: Uuid,
+        result: invoker_api::CustomRunResult,
+    ) -> anyhow::Result<()>;
 }
 
 #[derive(Clone)]
@@ -83,6 +103,22 @@ pub struct Controller {
     toolchain_loader: Arc<toolchains::ToolchainLoader>,
 }
 
+fn record_judge_stats(stats: &JudgeStats) {
+    let phases = [
+        ("compile", stats.compile_time),
+        ("tests", stats.tests_time),
+        ("valuer", stats.valuer_time),
+    ];
+    for (phase, duration) in phases.iter() {
+        if let Some(duration) = duration {
+            METRICS.phase_duration.observe(phase, *duration);
+        }
+    }
+    for status in &stats.test_statuses {
+        METRICS.test_verdicts.inc(status);
+    }
+}
+
 fn get_num_cpus() -> usize {
     static CACHE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
     let old = CACHE.load(std::sync::atomic::Ordering::Relaxed);
@@ -105,14 +141,23 @@ impl Controller {
             None => get_num_cpus(),
         };
         info!("Using {} workers", worker_count);
-        let mut scheduler = Scheduler::new(&config).context("failed to initialize Scheduler")?;
+        let scheduler = Scheduler::new(&config).context("failed to initialize Scheduler")?;
+        let scheduler = Arc::new(scheduler);
         for _ in 0..worker_count {
             scheduler
                 .add_worker()
                 .await
                 .context("failed to start a worker")?;
         }
-        let scheduler = Arc::new(scheduler);
+        if let Some(remote_config) = &config.remote_workers {
+            let scheduler = scheduler.clone();
+            let remote_config = remote_config.clone();
+            tokio::task::spawn(async move {
+                if let Err(err) = crate::remote::serve(scheduler, remote_config).await {
+                    tracing::error!(error = %format_args!("{:#}", err), "Remote workers endpoint failed");
+                }
+            });
+        }
 
         let temp_dir = tempfile::TempDir::new().context("can not find temporary dir")?;
 
@@ -135,6 +180,10 @@ impl Controller {
         })
     }
 
+    pub fn problem_loader(&self) -> Arc<problem_loader::Loader> {
+        self.problem_loader.clone()
+    }
+
     #[instrument(skip(self, chan))]
     pub fn exec_on(self, chan: async_mpmc::Receiver<JudgeRequestAndCallbacks>) {
         chan.process_all(move |req| {
@@ -142,11 +191,18 @@ impl Controller {
 
             async move {
                 let request_id = req.request.request_id;
+                METRICS
+                    .requests_in_progress
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 if let Err(err) = this.process_request(req).await {
+                    METRICS.requests_finished.inc("error");
                     tracing::warn!(request_id = %request_id,
                     err = %format_args!("{:#}", err), 
                     "Failed to process a judge request");
                 }
+                METRICS
+                    .requests_in_progress
+                    .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
             }
         });
     }
@@ -183,18 +239,33 @@ impl Controller {
                         JudgeOutcome::TestingDone => InvocationFinishReason::TestingDone,
                         JudgeOutcome::CompileError(_) => InvocationFinishReason::CompileError,
                     };
+                    let reason_name = reason.as_str();
                     req.callbacks
                         .set_finished(req.request.request_id, reason)
                         .await
                         .context("failed to set run outcome in DB")?;
+                    // failed requests are counted as errors by caller
+                    METRICS.requests_finished.inc(reason_name);
                     break;
                 }
+                Response::Heartbeat | Response::AssetSaved | Response::AssetFailed(_) => {
+                    // filtered or consumed by scheduler
+                }
+                Response::Stats(stats) => {
+                    record_judge_stats(&stats);
+                }
                 Response::LiveScore(score) => {
                     exts.notifier.set_score(score).await;
                 }
                 Response::LiveTest(test) => {
                     exts.notifier.set_test(test).await;
                 }
+                Response::CustomRun(result) => {
+                    req.callbacks
+                        .deliver_custom_run_result(req.request.request_id, result)
+                        .await
+                        .context("failed to deliver custom run result")?;
+                }
                 Response::OutcomeHeader(header) => {
                     req.callbacks
                         .add_outcome_header(req.request.request_id, header)
@@ -84,6 +84,9 @@ impl Notifier {
         {
             warn!(error=%format_args!("{:#}", err), "Failed to send live status update");
             warn!("Disabling live status updates for this run");
+            crate::metrics::METRICS
+                .live_status_update_failures
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             self.errored = true;
         }
         self.throttled_until = Instant::now() + LIVE_STATUS_UPDATE_THROTTLE;
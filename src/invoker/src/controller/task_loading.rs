@@ -2,7 +2,10 @@
 use super::{
     notify::Notifier, Controller, JudgeRequestAndCallbacks, LoweredJudgeRequestExtensions,
 };
-use crate::worker::{self, LoweredJudgeRequest};
+use crate::{
+    metrics::METRICS,
+    worker::{self, LoweredJudgeRequest},
+};
 use anyhow::Context;
 use std::{
     collections::{HashMap, HashSet},
@@ -11,6 +14,10 @@ use std::{
 use tracing::instrument;
 use uuid::Uuid;
 
+/// Maximal size of custom run stdout (and, separately, stderr) in bytes.
+/// Custom run output is delivered through callbacks, so it should be small.
+const CUSTOM_RUN_OUTPUT_LIMIT: u64 = 64 * 1024;
+
 #[derive(Debug, Clone, thiserror::Error)]
 pub(crate) enum InterpolateError {
     #[error("template syntax violation: {message}")]
@@ -138,12 +145,16 @@ impl Controller {
             run_metadata.insert("InvokeRequestId".to_string(), s.to_owned());
         }
         let problem_name = &judge_request_and_cbs.request.problem_id;
-        let (problem, problem_dir) = self
+        let problem_handle = self
             .problem_loader
             .find(problem_name)
             .await
             .and_then(|opt| opt.ok_or_else(|| anyhow::anyhow!("unknown problem")))
             .with_context(|| format!("can not find problem `{}`", problem_name))?;
+        let cache_stats = self.problem_loader.cache_stats();
+        METRICS
+            .problem_cache
+            .set(cache_stats.hits, cache_stats.misses);
 
         let toolchain_info = self
             .toolchain_loader
@@ -184,18 +195,67 @@ impl Controller {
         let execute_command =
             interpolate_command(&toolchain_spec.run_command, &interp_dict, &toolchain_spec)
                 .context("invalid run command template")?;
+        let previous_judge_log = match &judge_request_and_cbs.request.revalue {
+            Some(revalue) => {
+                if revalue.previous_judge_log.kind != invoker_api::judge_log::JudgeLogKind::Full {
+                    anyhow::bail!("only full judge log can be re-valued");
+                }
+                Some(revalue.previous_judge_log.clone())
+            }
+            None => None,
+        };
+        let mut problem = problem_handle.manifest.clone();
+        let custom_run = match &judge_request_and_cbs.request.custom_run {
+            Some(custom_run) => {
+                if previous_judge_log.is_some() {
+                    anyhow::bail!("custom run can not be re-valued");
+                }
+                if problem.kind == pom::ProblemKind::OutputOnly {
+                    anyhow::bail!("custom run is not supported for output-only problems");
+                }
+                let stdin_path = temp_invocation_dir.join("custom-stdin");
+                tokio::fs::write(&stdin_path, &custom_run.stdin)
+                    .await
+                    .context("unable to save custom run input in FS")?;
+                let mut limits = custom_run
+                    .limits
+                    .or_else(|| problem.tests.first().map(|test| test.limits))
+                    .unwrap_or_default();
+                limits.output_limit = Some(limits.output_limit().min(CUSTOM_RUN_OUTPUT_LIMIT));
+                // custom input replaces problem tests, so that solution is
+                // executed in the usual test sandbox
+                problem.tests = vec![pom::Test {
+                    path: pom::FileRef {
+                        root: pom::FileRefRoot::Root,
+                        path: stdin_path.display().to_string(),
+                    },
+                    correct: None,
+                    limits,
+                    group: String::new(),
+                }];
+                true
+            }
+            None => false,
+        };
+        for test in &mut problem.tests {
+            test.limits = toolchain_spec.limits_scaling.apply(test.limits);
+        }
         let low_judge_request = LoweredJudgeRequest {
             compile_commands,
             execute_command,
 
             compile_limits: toolchain_spec.limits,
-            problem_dir: problem_dir.to_path_buf(),
+            problem_dir: problem_handle.problem_dir().to_path_buf(),
+            problem_revision: problem_handle.revision.0.clone(),
             source_file_name: toolchain_spec.filename.clone(),
-            problem: problem.clone(),
+            problem,
             run_source: run_source_temp_file,
             out_dir: temp_invocation_dir.clone(),
             judge_request_id: judge_request_and_cbs.request.request_id,
             toolchain_dir: toolchain_info.path,
+            sandbox_settings: toolchain_spec.sandbox,
+            previous_judge_log,
+            custom_run,
         };
         let exts = LoweredJudgeRequestExtensions {
             notifier: Notifier::new(
@@ -203,6 +263,7 @@ impl Controller {
                 judge_request_and_cbs.callbacks.clone(),
             ),
             invocation_dir: temp_invocation_dir,
+            _problem: problem_handle,
         };
 
         Ok((low_judge_request, exts))
@@ -1,9 +1,11 @@
 //! This module is responsible for toolchain loading
+use crate::metrics::METRICS;
 use anyhow::Context as _;
 use dkregistry::v2::manifest::{Manifest, RuntimeConfig};
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tracing::{debug, instrument};
 
@@ -34,6 +36,67 @@ pub struct ToolchainSpec {
 
     #[serde(rename = "env-blacklist", default)]
     pub env_blacklist: Vec<String>,
+
+    /// Hardening applied to build and run sandboxes
+    #[serde(default)]
+    pub sandbox: crate::worker::SandboxSettings,
+
+    /// Scaling applied to problem test limits
+    #[serde(rename = "limits-scaling", default)]
+    pub limits_scaling: LimitsScaling,
+}
+
+/// Allows slower toolchains (e.g. Java or Python) to be judged with the same
+/// problem limits
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub struct LimitsScaling {
+    /// Time limit is multiplied by this value
+    #[serde(default = "LimitsScaling::default_time_multiplier")]
+    pub time_multiplier: f64,
+    /// Added to time limit after multiplication, in milliseconds
+    #[serde(default)]
+    pub time_offset: u64,
+    /// Added to memory limit, in bytes
+    #[serde(default)]
+    pub extra_memory: u64,
+}
+
+impl LimitsScaling {
+    fn default_time_multiplier() -> f64 {
+        1.0
+    }
+
+    /// Checks that scaling produces meaningful limits
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(self.time_multiplier.is_finite() && self.time_multiplier > 0.0) {
+            anyhow::bail!(
+                "time-multiplier must be finite positive number, got {}",
+                self.time_multiplier
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns effective limits for this toolchain
+    pub fn apply(&self, limits: pom::Limits) -> pom::Limits {
+        let time = (limits.time() as f64 * self.time_multiplier).round() as u64;
+        pom::Limits {
+            time: Some(time + self.time_offset),
+            memory: Some(limits.memory() + self.extra_memory),
+            ..limits
+        }
+    }
+}
+
+impl Default for LimitsScaling {
+    fn default() -> Self {
+        LimitsScaling {
+            time_multiplier: LimitsScaling::default_time_multiplier(),
+            time_offset: 0,
+            extra_memory: 0,
+        }
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Default, Debug, Clone)]
@@ -98,12 +161,17 @@ impl ImageConfig {
     }
 }
 
+/// Toolchain, which is pulled or is being pulled
+type CacheEntry = Arc<tokio::sync::Mutex<Option<ResolvedToolchainInfo>>>;
+
 /// Responsible for fetching toolchains
 pub struct ToolchainLoader {
     puller: puller::Puller,
     toolchains_dir: tempfile::TempDir,
-    /// Cache for already pulled toolchains.
-    cache: HashMap<String, ResolvedToolchainInfo>,
+    /// Cache for already pulled toolchains. Each toolchain has its own lock,
+    /// which is held while it is pulled, so that it is pulled only once
+    /// and other toolchains are not blocked.
+    cache: std::sync::Mutex<HashMap<String, CacheEntry>>,
 }
 
 impl ToolchainLoader {
@@ -113,7 +181,7 @@ impl ToolchainLoader {
         Ok(ToolchainLoader {
             toolchains_dir,
             puller,
-            cache: HashMap::new(),
+            cache: std::sync::Mutex::new(HashMap::new()),
         })
     }
 
@@ -156,9 +224,19 @@ impl ToolchainLoader {
 
     #[instrument(skip(self))]
     pub async fn resolve(&self, toolchain_url: &str) -> anyhow::Result<ResolvedToolchainInfo> {
-        if let Some(info) = self.cache.get(toolchain_url) {
+        let entry = self
+            .cache
+            .lock()
+            .unwrap()
+            .entry(toolchain_url.to_string())
+            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(None)))
+            .clone();
+        let mut entry = entry.lock().await;
+        if let Some(info) = &*entry {
+            METRICS.toolchain_cache.hit();
             return Ok(info.clone());
         }
+        METRICS.toolchain_cache.miss();
         let toolchain_dir = self
             .toolchains_dir
             .path()
@@ -175,10 +253,16 @@ impl ToolchainLoader {
             .context("toolchain config file (manifest.yaml in image root) missing")?;
         let toolchain_spec: ToolchainSpec =
             serde_yaml::from_slice(&toolchain_spec).context("invalid toolchain spec")?;
-        Ok(ResolvedToolchainInfo {
+        toolchain_spec
+            .limits_scaling
+            .validate()
+            .context("invalid limits-scaling in toolchain spec")?;
+        let info = ResolvedToolchainInfo {
             path: toolchain_dir,
             spec: toolchain_spec,
             image_config,
-        })
+        };
+        *entry = Some(info.clone());
+        Ok(info)
     }
 }
@@ -3,6 +3,8 @@ pub mod api;
 pub mod config;
 pub mod controller;
 pub mod init;
+mod metrics;
+mod remote;
 mod scheduler;
 pub mod sources;
 pub mod worker;
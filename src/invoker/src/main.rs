@@ -29,14 +29,19 @@ async fn start_request_providers(
 fn worker_self_isolate() -> anyhow::Result<()> {
     #[cfg(target_os = "linux")]
     {
-        // TODO: unshare NEWNET too. To achieve it, we have to switch to multiprocessing instead of multithreading
+        // network is isolated for each sandbox separately, see `worker::hardening`
         nix::sched::unshare(nix::sched::CloneFlags::CLONE_FILES).context("failed to unshare")?;
     }
     Ok(())
 }
 
 fn is_worker() -> bool {
-    std::env::var("__JJS_WORKER").is_ok()
+    std::env::var("__JJS_WORKER").is_ok() || is_remote_worker()
+}
+
+/// Remote worker connects to controller instead of being spawned by it
+fn is_remote_worker() -> bool {
+    std::env::var("JJS_WORKER_CONNECT").is_ok()
 }
 
 fn main() -> anyhow::Result<()> {
@@ -61,21 +66,24 @@ fn main() -> anyhow::Result<()> {
         res
     })
 }
-#[instrument(skip(judge_requests))]
-async fn start_controller(
+#[instrument]
+async fn create_controller(
     config: Arc<invoker::config::InvokerConfig>,
     system_config_data: util::cfg::CfgData,
-    judge_requests: async_mpmc::Receiver<JudgeRequestAndCallbacks>,
-) -> anyhow::Result<()> {
-    info!("Starting controller");
-    let controller = invoker::controller::Controller::new(system_config_data, config)
+) -> anyhow::Result<invoker::controller::Controller> {
+    info!("Creating controller");
+    invoker::controller::Controller::new(system_config_data, config)
         .await
-        .context("failed to start controller")?;
-    controller.exec_on(judge_requests);
-    Ok(())
+        .context("failed to create controller")
 }
 
 async fn real_main(cancel_token: tokio::sync::CancellationToken) -> anyhow::Result<()> {
+    if is_remote_worker() {
+        let endpoint = std::env::var("JJS_WORKER_CONNECT")?;
+        let token = std::env::var("JJS_WORKER_TOKEN").context("JJS_WORKER_TOKEN missing")?;
+        let trusted_network = std::env::var_os("JJS_WORKER_TRUSTED_NETWORK").is_some();
+        return invoker::worker::remote_main(&endpoint, &token, trusted_network).await;
+    }
     if is_worker() {
         return invoker::worker::main().await;
     }
@@ -101,21 +109,25 @@ async fn real_main(cancel_token: tokio::sync::CancellationToken) -> anyhow::Resu
 
     let (judge_request_tx, judge_request_rx) = async_mpmc::channel();
 
-    invoker::api::start(cancel_token.clone(), bind_address, judge_request_tx.clone())
+    let controller = create_controller(Arc::new(invoker_config), system_config_data)
         .await
-        .context("failed to start api")?;
+        .context("can not start controller")?;
+
+    invoker::api::start(
+        cancel_token.clone(),
+        bind_address,
+        judge_request_tx.clone(),
+        controller.problem_loader(),
+    )
+    .await
+    .context("failed to start api")?;
 
     info!("API service started");
     start_request_providers(cancel_token.clone(), judge_request_tx)
         .await
         .context("failed to initialize request providers")?;
-    start_controller(
-        Arc::new(invoker_config),
-        system_config_data,
-        judge_request_rx,
-    )
-    .await
-    .context("can not start controller")?;
+    info!("Starting controller");
+    controller.exec_on(judge_request_rx);
     {
         let cancel_token = cancel_token.clone();
         tokio::task::spawn(async move {
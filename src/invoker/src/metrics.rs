@@ -0,0 +1,265 @@
+//! Invoker metrics, exported in Prometheus text format.
+//!
+//! Metrics are stored in global registry, because they are updated from
+//! many places (scheduler, controller, notifier).
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering::Relaxed},
+        Mutex,
+    },
+    time::Duration,
+};
+
+pub(crate) static METRICS: once_cell::sync::Lazy<Metrics> =
+    once_cell::sync::Lazy::new(Metrics::default);
+
+/// Upper bounds of latency histogram buckets, in seconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Set of counters or gauges, distinguished by single label value
+#[derive(Default)]
+pub(crate) struct Family(Mutex<BTreeMap<String, i64>>);
+
+impl Family {
+    pub(crate) fn add(&self, label: &str, delta: i64) {
+        let mut values = self.0.lock().unwrap();
+        *values.entry(label.to_string()).or_insert(0) += delta;
+    }
+
+    pub(crate) fn inc(&self, label: &str) {
+        self.add(label, 1);
+    }
+
+    pub(crate) fn dec(&self, label: &str) {
+        self.add(label, -1);
+    }
+
+    fn render(&self, out: &mut String, name: &str, label_name: &str) {
+        for (label, value) in self.0.lock().unwrap().iter() {
+            writeln!(
+                out,
+                "{}{{{}=\"{}\"}} {}",
+                name,
+                label_name,
+                escape(label),
+                value
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[derive(Clone)]
+struct HistogramData {
+    /// Non-cumulative bucket counts
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// Set of latency histograms, distinguished by single label value
+#[derive(Default)]
+pub(crate) struct HistogramFamily(Mutex<BTreeMap<String, HistogramData>>);
+
+impl HistogramFamily {
+    pub(crate) fn observe(&self, label: &str, value: Duration) {
+        let value = value.as_secs_f64();
+        let mut histograms = self.0.lock().unwrap();
+        let data = histograms
+            .entry(label.to_string())
+            .or_insert_with(|| HistogramData {
+                buckets: vec![0; LATENCY_BUCKETS.len()],
+                sum: 0.0,
+                count: 0,
+            });
+        if let Some(pos) = LATENCY_BUCKETS.iter().position(|&bound| value <= bound) {
+            data.buckets[pos] += 1;
+        }
+        data.sum += value;
+        data.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, label_name: &str) {
+        for (label, data) in self.0.lock().unwrap().iter() {
+            let label = escape(label);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&data.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "{}_bucket{{{}=\"{}\",le=\"{}\"}} {}",
+                    name, label_name, label, bound, cumulative
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "{}_bucket{{{}=\"{}\",le=\"+Inf\"}} {}",
+                name, label_name, label, data.count
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "{}_sum{{{}=\"{}\"}} {}",
+                name, label_name, label, data.sum
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "{}_count{{{}=\"{}\"}} {}",
+                name, label_name, label, data.count
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Hit and miss counters of some cache
+#[derive(Default)]
+pub(crate) struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounters {
+    pub(crate) fn hit(&self) {
+        self.hits.fetch_add(1, Relaxed);
+    }
+
+    pub(crate) fn miss(&self) {
+        self.misses.fetch_add(1, Relaxed);
+    }
+
+    /// Overwrites counters, for caches which count requests themselves
+    pub(crate) fn set(&self, hits: u64, misses: u64) {
+        self.hits.store(hits, Relaxed);
+        self.misses.store(misses, Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, cache: &str) {
+        writeln!(
+            out,
+            "{}{{cache=\"{}\",result=\"hit\"}} {}",
+            name,
+            cache,
+            self.hits.load(Relaxed)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "{}{{cache=\"{}\",result=\"miss\"}} {}",
+            name,
+            cache,
+            self.misses.load(Relaxed)
+        )
+        .unwrap();
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Metrics {
+    /// Worker count by `WorkerStateKind`
+    pub(crate) workers: Family,
+    /// Judge requests, which are being processed (including ones waiting
+    /// for free worker)
+    pub(crate) requests_in_progress: AtomicI64,
+    /// Finished judge requests by `InvocationFinishReason`
+    pub(crate) requests_finished: Family,
+    /// Judging phase durations (compile, tests, valuer)
+    pub(crate) phase_duration: HistogramFamily,
+    /// Test verdicts by status code
+    pub(crate) test_verdicts: Family,
+    pub(crate) toolchain_cache: CacheCounters,
+    pub(crate) problem_cache: CacheCounters,
+    /// Live status updates which were not delivered
+    pub(crate) live_status_update_failures: AtomicU64,
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+impl Metrics {
+    /// Renders all metrics in Prometheus text exposition format
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+        header(
+            &mut out,
+            "invoker_workers",
+            "gauge",
+            "Number of workers by state",
+        );
+        self.workers.render(&mut out, "invoker_workers", "state");
+        header(
+            &mut out,
+            "invoker_judge_requests_in_progress",
+            "gauge",
+            "Number of judge requests being processed",
+        );
+        writeln!(
+            out,
+            "invoker_judge_requests_in_progress {}",
+            self.requests_in_progress.load(Relaxed)
+        )
+        .unwrap();
+        header(
+            &mut out,
+            "invoker_judge_requests_total",
+            "counter",
+            "Number of finished judge requests by finish reason",
+        );
+        self.requests_finished
+            .render(&mut out, "invoker_judge_requests_total", "reason");
+        header(
+            &mut out,
+            "invoker_phase_duration_seconds",
+            "histogram",
+            "Duration of judging phases",
+        );
+        self.phase_duration
+            .render(&mut out, "invoker_phase_duration_seconds", "phase");
+        header(
+            &mut out,
+            "invoker_test_verdicts_total",
+            "counter",
+            "Number of judged tests by status code",
+        );
+        self.test_verdicts
+            .render(&mut out, "invoker_test_verdicts_total", "status");
+        header(
+            &mut out,
+            "invoker_cache_requests_total",
+            "counter",
+            "Number of cache lookups by result",
+        );
+        self.toolchain_cache
+            .render(&mut out, "invoker_cache_requests_total", "toolchain");
+        self.problem_cache
+            .render(&mut out, "invoker_cache_requests_total", "problem");
+        header(
+            &mut out,
+            "invoker_live_status_update_failures_total",
+            "counter",
+            "Number of live status updates which were not delivered",
+        );
+        writeln!(
+            out,
+            "invoker_live_status_update_failures_total {}",
+            self.live_status_update_failures.load(Relaxed)
+        )
+        .unwrap();
+        out
+    }
+}
@@ -0,0 +1,302 @@
+//! Implements remote workers.
+//!
+//! Remote worker is invoker process, started with `JJS_WORKER_CONNECT`
+//! environment variable. It connects to controller, authenticates with
+//! `JJS_WORKER_TOKEN` and then speaks usual worker protocol (JSON lines
+//! with `Request`s and `Response`s). Remote worker does not share filesystem
+//! with controller, so request assets are transferred as `PutAsset` requests,
+//! followed by data in `AssetChunk`s.
+//!
+//! Connection is not encrypted, so TCP endpoint must be a loopback address
+//! (remote workers should reach it through SSH or stunnel tunnel), unless
+//! network is explicitly marked as trusted.
+use crate::{config::RemoteWorkersConfig, scheduler::Scheduler};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::Semaphore,
+};
+use tracing::{info, warn};
+
+/// Maximal time between connection and successful authentication
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximal length of `Hello` line, including newline
+const MAX_HELLO_LEN: u64 = 4096;
+
+/// Maximal number of connections that are not authenticated yet
+const MAX_CONCURRENT_HANDSHAKES: usize = 16;
+
+/// Maximal time remote worker waits for `Welcome` after sending `Hello`
+pub(crate) const WELCOME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximal size of asset data in one `AssetChunk` (before base64 encoding)
+pub(crate) const ASSET_CHUNK_SIZE: usize = 1 << 20;
+
+/// Address of controller endpoint for remote workers
+pub(crate) enum Endpoint {
+    /// `tcp://<host>:<port>`
+    Tcp(String),
+    /// `unix://<path>`, mostly useful for testing
+    Unix(PathBuf),
+}
+
+impl std::str::FromStr for Endpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Endpoint> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            Ok(Endpoint::Tcp(addr.to_string()))
+        } else if let Some(path) = s.strip_prefix("unix://") {
+            Ok(Endpoint::Unix(path.into()))
+        } else {
+            bail!(
+                "endpoint {} has unknown scheme (tcp:// or unix:// expected)",
+                s
+            )
+        }
+    }
+}
+
+/// First message sent by remote worker
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Hello {
+    /// Used in logs
+    pub(crate) name: String,
+    pub(crate) token: String,
+}
+
+/// Sent by controller when remote worker is authenticated
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Welcome {
+    /// Serialized `InvokerConfig`
+    pub(crate) config: String,
+}
+
+/// Identifies asset cached by remote worker
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) struct AssetKey {
+    /// Path on controller side
+    pub(crate) path: PathBuf,
+    /// Problem revision, if asset is problem directory
+    pub(crate) revision: Option<String>,
+}
+
+/// Describes file or directory, transferred to remote worker
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AssetHeader {
+    pub(crate) key: AssetKey,
+    /// If true, data is gzipped tar archive of directory, otherwise it is
+    /// file contents
+    pub(crate) is_dir: bool,
+}
+
+/// Asset, prepared for transfer
+pub(crate) struct PackedAsset {
+    pub(crate) is_dir: bool,
+    /// Contains data of directory asset
+    archive: Option<tempfile::NamedTempFile>,
+    path: PathBuf,
+}
+
+impl PackedAsset {
+    /// Returns path to file which should be sent in `AssetChunk`s
+    pub(crate) fn data_path(&self) -> &Path {
+        match &self.archive {
+            Some(archive) => archive.path(),
+            None => &self.path,
+        }
+    }
+}
+
+/// Packs file or directory at `path`. Directories are archived to temporary
+/// file, so that they are not kept in memory.
+pub(crate) async fn pack_asset(path: &Path) -> anyhow::Result<PackedAsset> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let is_dir = path.is_dir();
+        let archive = if is_dir {
+            let file = tempfile::NamedTempFile::new().context("failed to create archive file")?;
+            let encoder = flate2::write::GzEncoder::new(
+                std::io::BufWriter::new(file),
+                flate2::Compression::default(),
+            );
+            let mut builder = tar::Builder::new(encoder);
+            builder.follow_symlinks(false);
+            builder
+                .append_dir_all(".", &path)
+                .context("failed to create archive")?;
+            let file = builder
+                .into_inner()
+                .and_then(|encoder| encoder.finish())
+                .context("failed to finish archive")?
+                .into_inner()
+                .context("failed to write archive")?;
+            Some(file)
+        } else {
+            None
+        };
+        Ok(PackedAsset {
+            is_dir,
+            archive,
+            path,
+        })
+    })
+    .await
+    .unwrap()
+}
+
+/// Unpacks received asset data from `data` to `target`. File assets are
+/// moved, and archives are left in place.
+pub(crate) fn unpack_asset(header: &AssetHeader, data: &Path, target: &Path) -> anyhow::Result<()> {
+    if header.is_dir {
+        let file = std::fs::File::open(data).context("failed to open archive")?;
+        let decoder = flate2::read::GzDecoder::new(std::io::BufReader::new(file));
+        let mut archive = tar::Archive::new(decoder);
+        archive.set_preserve_permissions(true);
+        archive.unpack(target).context("failed to unpack archive")?;
+    } else {
+        std::fs::rename(data, target).context("failed to move file")?;
+    }
+    Ok(())
+}
+
+/// Tokens and judging data are sent in plain text, so they must not leave
+/// the machine unless network is trusted
+pub(crate) fn check_address_trusted(addr: SocketAddr, trusted_network: bool) -> anyhow::Result<()> {
+    if !trusted_network && !addr.ip().is_loopback() {
+        bail!(
+            "{} is not a loopback address, but connection is not encrypted; use a tunnel (e.g. SSH or stunnel) or mark network as trusted",
+            addr
+        );
+    }
+    Ok(())
+}
+
+/// Compares tokens in constant time
+fn token_matches(provided: &str, expected: &str) -> bool {
+    let provided = provided.as_bytes();
+    let expected = expected.as_bytes();
+    if provided.len() != expected.len() {
+        return false;
+    }
+    provided
+        .iter()
+        .zip(expected)
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// Accepts remote workers and adds them to `scheduler`
+pub(crate) async fn serve(
+    scheduler: Arc<Scheduler>,
+    config: RemoteWorkersConfig,
+) -> anyhow::Result<()> {
+    let endpoint: Endpoint = config.listen.parse()?;
+    let token: Arc<str> = config.token.into();
+    let handshakes = Arc::new(Semaphore::new(MAX_CONCURRENT_HANDSHAKES));
+    match endpoint {
+        Endpoint::Tcp(addr) => {
+            let mut listener = tokio::net::TcpListener::bind(&addr)
+                .await
+                .with_context(|| format!("failed to bind {}", addr))?;
+            check_address_trusted(listener.local_addr()?, config.trusted_network)?;
+            info!(address = %addr, "Listening for remote workers");
+            loop {
+                let (stream, peer) = listener.accept().await.context("accept failed")?;
+                tokio::task::spawn(accept(
+                    scheduler.clone(),
+                    handshakes.clone(),
+                    stream,
+                    token.clone(),
+                    peer.to_string(),
+                ));
+            }
+        }
+        Endpoint::Unix(path) => {
+            let mut listener = tokio::net::UnixListener::bind(&path)
+                .with_context(|| format!("failed to bind {}", path.display()))?;
+            info!(path = %path.display(), "Listening for remote workers");
+            loop {
+                let (stream, _) = listener.accept().await.context("accept failed")?;
+                tokio::task::spawn(accept(
+                    scheduler.clone(),
+                    handshakes.clone(),
+                    stream,
+                    token.clone(),
+                    path.display().to_string(),
+                ));
+            }
+        }
+    }
+}
+
+async fn accept<S>(
+    scheduler: Arc<Scheduler>,
+    handshakes: Arc<Semaphore>,
+    stream: S,
+    token: Arc<str>,
+    peer: String,
+) where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let _permit = match handshakes.try_acquire() {
+        Ok(permit) => permit,
+        Err(_) => {
+            warn!(peer = %peer, "Rejected remote worker: too many pending handshakes");
+            return;
+        }
+    };
+    if let Err(err) = handshake(&scheduler, stream, &token, &peer).await {
+        warn!(peer = %peer, error = %format_args!("{:#}", err), "Rejected remote worker");
+    }
+}
+
+async fn handshake<S>(
+    scheduler: &Scheduler,
+    stream: S,
+    token: &str,
+    peer: &str,
+) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = tokio::io::BufReader::new(reader);
+    let mut line = String::new();
+    let mut limited_reader = (&mut reader).take(MAX_HELLO_LEN);
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, limited_reader.read_line(&mut line))
+        .await
+        .context("handshake timed out")?
+        .context("failed to read hello")?;
+    if !line.ends_with('\n') {
+        bail!("hello is truncated or longer than {} bytes", MAX_HELLO_LEN);
+    }
+    let hello: Hello = serde_json::from_str(&line).context("invalid hello")?;
+    if !token_matches(&hello.token, token) {
+        bail!("invalid token");
+    }
+    let welcome = Welcome {
+        config: scheduler.worker_config().to_string(),
+    };
+    let mut data = serde_json::to_vec(&welcome)?;
+    data.push(b'\n');
+    writer
+        .write_all(&data)
+        .await
+        .context("failed to send welcome")?;
+    info!(peer = %peer, name = %hello.name, "Remote worker connected");
+    scheduler.add_remote_worker(
+        format!("{}@{}", hello.name, peer),
+        Box::new(reader),
+        Box::new(writer),
+    );
+    Ok(())
+}
@@ -2,18 +2,38 @@
 
 use crate::{
     config::InvokerConfig,
-    worker::{Request, Response},
+    metrics::METRICS,
+    remote,
+    worker::{LoweredJudgeRequest, Request, Response},
+};
+use anyhow::{bail, Context as _};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU8, Ordering::SeqCst},
+        Arc, RwLock,
+    },
+    time::Duration,
 };
-use anyhow::Context as _;
-use std::sync::atomic::{AtomicU8, Ordering::SeqCst};
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt},
-    sync::Mutex,
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::{mpsc, Mutex},
 };
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
+
+/// Worker is considered dead if it does not send anything (including
+/// heartbeats) during this interval
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many problems and toolchains remote worker keeps. Least recently
+/// used assets are removed when this limit is exceeded.
+const MAX_CACHED_ASSETS: usize = 8;
+
+type WorkerList = Arc<RwLock<Vec<Arc<WorkerInfo>>>>;
+
 /// Scheduler is responsible for finding a suitable worker for a task
 pub struct Scheduler {
-    workers: Vec<WorkerInfo>,
+    workers: WorkerList,
     /// We need it because we must pass it to a worker.
     // TODO: ideally we do not want to pass any configs to a worker
     config: String,
@@ -26,15 +46,20 @@ impl Scheduler {
     pub fn new(config: &InvokerConfig) -> anyhow::Result<Self> {
         let config = serde_json::to_string(&config).context("failed to serialize InvokerConfig")?;
         Ok(Scheduler {
-            workers: vec![],
+            workers: Arc::new(RwLock::new(Vec::new())),
             config,
             worker_reclamation: multiwake::multiwake(),
         })
     }
 
+    /// Returns serialized config, which should be passed to workers
+    pub(crate) fn worker_config(&self) -> &str {
+        &self.config
+    }
+
     /// Starts new worker process and adds it to this scheduler
     #[instrument(skip(self))]
-    pub async fn add_worker(&mut self) -> anyhow::Result<()> {
+    pub async fn add_worker(&self) -> anyhow::Result<()> {
         let mut child = tokio::process::Command::new(std::env::current_exe()?)
             .env("__JJS_WORKER", "1")
             .env("__JJS_WORKER_INVOKER_CONFIG", &self.config)
@@ -42,29 +67,71 @@ impl Scheduler {
             .stdout(std::process::Stdio::piped())
             .spawn()
             .context("failed to spawn worker")?;
-        let info = WorkerInfo {
-            state: WorkerState::new(WorkerStateKind::Idle),
-            child_stdin: Mutex::new(child.stdin.take().expect("child stdin was captured")),
-            child_stdout: Mutex::new(tokio::io::BufReader::new(
+        self.register(
+            "local".to_string(),
+            Box::new(tokio::io::BufReader::new(
                 child.stdout.take().expect("child stdout was captured"),
             )),
-        };
-        self.workers.push(info);
+            Box::new(child.stdin.take().expect("child stdin was captured")),
+            None,
+        );
         Ok(())
     }
 
+    /// Adds worker connected over network. Remote worker has separate
+    /// filesystem, so request assets are transferred to it.
+    pub(crate) fn add_remote_worker(
+        &self,
+        name: String,
+        reader: Box<dyn AsyncBufRead + Send + Unpin>,
+        writer: Box<dyn AsyncWrite + Send + Unpin>,
+    ) {
+        self.register(name, reader, writer, Some(Mutex::new(VecDeque::new())));
+        // someone may wait for a free worker
+        self.worker_reclamation.0.clone().wake();
+    }
+
+    fn register(
+        &self,
+        name: String,
+        reader: Box<dyn AsyncBufRead + Send + Unpin>,
+        writer: Box<dyn AsyncWrite + Send + Unpin>,
+        synced_assets: Option<Mutex<VecDeque<remote::AssetKey>>>,
+    ) {
+        let (responses_tx, responses_rx) = mpsc::unbounded_channel();
+        let info = Arc::new(WorkerInfo {
+            name,
+            state: WorkerState::new(WorkerStateKind::Idle),
+            responses: Mutex::new(responses_rx),
+            writer: Mutex::new(writer),
+            synced_assets,
+        });
+        tokio::task::spawn(read_responses(
+            info.clone(),
+            self.workers.clone(),
+            reader,
+            responses_tx,
+        ));
+        self.workers.write().unwrap().push(info);
+    }
+
     /// Tries to find a free worker. On success, returns `FreeWorkerHandle`,
     /// which can be used to send requests to that worker.
     #[instrument(skip(self))]
-    pub async fn find_free_worker(&self) -> FreeWorkerHandle<'_> {
+    pub async fn find_free_worker(&self) -> FreeWorkerHandle {
         let mut receiver = self.worker_reclamation.1.clone();
         let mut attempt_id = 0u32;
         loop {
             debug!(attempt_id, "scanning all workers");
             attempt_id += 1;
-            for worker in &self.workers {
-                if let Some(handle) = worker.try_lock(self.worker_reclamation.0.clone()) {
-                    return handle;
+            {
+                let workers = self.workers.read().unwrap();
+                for worker in workers.iter() {
+                    if let Some(handle) =
+                        WorkerInfo::try_lock(worker, self.worker_reclamation.0.clone())
+                    {
+                        return handle;
+                    }
                 }
             }
             receiver.wait().await;
@@ -72,15 +139,53 @@ impl Scheduler {
     }
 }
 
+/// Reads worker messages, filtering out heartbeats.
+/// If worker disconnects or stops sending heartbeats, it is marked as crashed,
+/// and remote worker is removed from scheduler.
+async fn read_responses(
+    worker: Arc<WorkerInfo>,
+    workers: WorkerList,
+    mut reader: Box<dyn AsyncBufRead + Send + Unpin>,
+    responses: mpsc::UnboundedSender<anyhow::Result<Response>>,
+) {
+    let err = loop {
+        let mut line = String::new();
+        match tokio::time::timeout(HEARTBEAT_TIMEOUT, reader.read_line(&mut line)).await {
+            Err(_elapsed) => break anyhow::anyhow!("worker missed heartbeats"),
+            Ok(Err(err)) => break anyhow::Error::new(err).context("failed to read from worker"),
+            Ok(Ok(0)) => break anyhow::anyhow!("worker disconnected"),
+            Ok(Ok(_)) => {}
+        }
+        match serde_json::from_str(&line).context("parse error") {
+            Ok(Response::Heartbeat) => continue,
+            Ok(response) => {
+                // receiver is never dropped before worker
+                responses.send(Ok(response)).ok();
+            }
+            Err(err) => break err,
+        }
+    };
+    warn!(worker = %worker.name, error = %format_args!("{:#}", err), "Worker lost");
+    worker.state.store(WorkerStateKind::Crash);
+    responses.send(Err(err)).ok();
+    if worker.is_remote() {
+        workers
+            .write()
+            .unwrap()
+            .retain(|item| !Arc::ptr_eq(item, &worker));
+        worker.state.retire();
+    }
+}
+
 /// This handle logically owns free worker and can be used to send it requests.
-pub struct FreeWorkerHandle<'a> {
+pub struct FreeWorkerHandle {
     /// reference to the worker
-    worker: &'a WorkerInfo,
+    worker: Arc<WorkerInfo>,
     /// Used to notify that worker is reclaimed
     notify: multiwake::Sender,
 }
 
-impl Drop for FreeWorkerHandle<'_> {
+impl Drop for FreeWorkerHandle {
     fn drop(&mut self) {
         // true if worker can be reused later.
         // Theoretically, it should always be the case when handle is dropped.
@@ -103,29 +208,34 @@ impl Drop for FreeWorkerHandle<'_> {
     }
 }
 
-impl<'a> FreeWorkerHandle<'a> {
+impl FreeWorkerHandle {
     /// Sends request to worker, returning "stream" of responses
-    pub(crate) async fn send(self, req: Request) -> anyhow::Result<WorkerResponses<'a>> {
+    pub(crate) async fn send(self, req: Request) -> anyhow::Result<WorkerResponses> {
         self.worker.state.store(WorkerStateKind::Judge);
-        self.worker
-            .send(req)
-            .await
-            .map_err(|err| {
-                tracing::warn!("request not delivered, marking worker as crashed");
-                self.worker.state.store(WorkerStateKind::Crash);
-                err
-            })
-            .context("failed to send request")?;
+        let res = match &req {
+            Request::Judge(judge_req) => self.worker.sync_assets(judge_req).await,
+            _ => Ok(()),
+        };
+        let res = match res {
+            Ok(()) => self.worker.send(&req).await,
+            Err(err) => Err(err),
+        };
+        res.map_err(|err| {
+            tracing::warn!("request not delivered, marking worker as crashed");
+            self.worker.state.store(WorkerStateKind::Crash);
+            err
+        })
+        .context("failed to send request")?;
         Ok(WorkerResponses { handle: Some(self) })
     }
 }
 
 /// Provides access to worker responses
-pub struct WorkerResponses<'a> {
-    handle: Option<FreeWorkerHandle<'a>>,
+pub struct WorkerResponses {
+    handle: Option<FreeWorkerHandle>,
 }
 
-impl WorkerResponses<'_> {
+impl WorkerResponses {
     /// Returns next response.
     /// If returned response is JudgeDone or error, must not be polled again.
     pub(crate) async fn next(&mut self) -> anyhow::Result<Response> {
@@ -158,23 +268,20 @@ const WORKER_STATE_CRASH: u8 = 2;
 const WORKER_STATE_JUDGE: u8 = 3;
 impl WorkerState {
     fn new(kind: WorkerStateKind) -> Self {
-        let this = WorkerState(AtomicU8::new(0));
-        this.store(kind);
-        this
+        METRICS.workers.inc(kind.as_str());
+        WorkerState(AtomicU8::new(Self::encode(kind)))
     }
 
-    fn store(&self, kind: WorkerStateKind) {
-        let value = match kind {
+    fn encode(kind: WorkerStateKind) -> u8 {
+        match kind {
             WorkerStateKind::Idle => WORKER_STATE_IDLE,
             WorkerStateKind::Locked => WORKER_STATE_LOCKED,
             WorkerStateKind::Crash => WORKER_STATE_CRASH,
             WorkerStateKind::Judge => WORKER_STATE_JUDGE,
-        };
-        self.0.store(value, SeqCst);
+        }
     }
 
-    fn load(&self) -> WorkerStateKind {
-        let value = self.0.load(SeqCst);
+    fn decode(value: u8) -> WorkerStateKind {
         match value {
             WORKER_STATE_IDLE => WorkerStateKind::Idle,
             WORKER_STATE_LOCKED => WorkerStateKind::Locked,
@@ -184,16 +291,42 @@ impl WorkerState {
         }
     }
 
+    /// Updates worker state metrics
+    fn record_transition(prev: WorkerStateKind, next: WorkerStateKind) {
+        METRICS.workers.dec(prev.as_str());
+        METRICS.workers.inc(next.as_str());
+    }
+
+    fn store(&self, kind: WorkerStateKind) {
+        let prev = self.0.swap(Self::encode(kind), SeqCst);
+        Self::record_transition(Self::decode(prev), kind);
+    }
+
+    fn load(&self) -> WorkerStateKind {
+        Self::decode(self.0.load(SeqCst))
+    }
+
+    /// Removes worker from metrics, when it is removed from scheduler
+    fn retire(&self) {
+        METRICS.workers.dec(self.load().as_str());
+    }
+
     /// Tries to atomically lock this worker state.
     /// I.e., this functions succeeds if state was `Idle` and it was
     /// successfully CASed to `Locked`.
     fn lock(&self) -> bool {
-        self.0
+        let locked = self
+            .0
             .compare_and_swap(WORKER_STATE_IDLE, WORKER_STATE_LOCKED, SeqCst)
-            == WORKER_STATE_IDLE
+            == WORKER_STATE_IDLE;
+        if locked {
+            Self::record_transition(WorkerStateKind::Idle, WorkerStateKind::Locked);
+        }
+        locked
     }
 }
 
+#[derive(Clone, Copy)]
 enum WorkerStateKind {
     /// Worker is ready for new tasks
     Idle,
@@ -205,34 +338,143 @@ enum WorkerStateKind {
     Crash,
 }
 
+impl WorkerStateKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            WorkerStateKind::Idle => "idle",
+            WorkerStateKind::Locked => "locked",
+            WorkerStateKind::Judge => "judge",
+            WorkerStateKind::Crash => "crash",
+        }
+    }
+}
+
 struct WorkerInfo {
+    /// Used in logs
+    name: String,
     state: WorkerState,
-    child_stdout: Mutex<tokio::io::BufReader<tokio::process::ChildStdout>>,
-    child_stdin: Mutex<tokio::process::ChildStdin>,
+    /// Worker responses, excluding heartbeats
+    responses: Mutex<mpsc::UnboundedReceiver<anyhow::Result<Response>>>,
+    writer: Mutex<Box<dyn AsyncWrite + Send + Unpin>>,
+    /// For remote workers, contains cached assets, least recently used first.
+    /// `None` for local workers, which share filesystem with controller.
+    synced_assets: Option<Mutex<VecDeque<remote::AssetKey>>>,
 }
 
 impl WorkerInfo {
-    pub async fn recv(&self) -> anyhow::Result<Response> {
-        let mut line = String::new();
-        let mut child_stdout = self.child_stdout.lock().await;
+    fn is_remote(&self) -> bool {
+        self.synced_assets.is_some()
+    }
 
-        child_stdout.read_line(&mut line).await?;
-        Ok(serde_json::from_str(&line).context("parse error")?)
+    pub async fn recv(&self) -> anyhow::Result<Response> {
+        match self.responses.lock().await.recv().await {
+            Some(res) => res,
+            None => anyhow::bail!("worker is lost"),
+        }
     }
 
-    pub async fn send(&self, req: Request) -> anyhow::Result<()> {
-        let mut data = serde_json::to_vec(&req)?;
+    pub async fn send(&self, req: &Request) -> anyhow::Result<()> {
+        let mut data = serde_json::to_vec(req)?;
         data.push(b'\n');
-        self.child_stdin.lock().await.write_all(&data).await?;
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&data).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Transfers assets of `req` to remote worker. Problem revision and
+    /// toolchain are transferred once and cached by worker, and run source
+    /// is transferred always.
+    async fn sync_assets(&self, req: &LoweredJudgeRequest) -> anyhow::Result<()> {
+        let synced_assets = match &self.synced_assets {
+            Some(synced) => synced,
+            None => return Ok(()),
+        };
+        let mut synced_assets = synced_assets.lock().await;
+        let cached = [
+            remote::AssetKey {
+                path: req.problem_dir.clone(),
+                revision: Some(req.problem_revision.clone()),
+            },
+            remote::AssetKey {
+                path: req.toolchain_dir.clone(),
+                revision: None,
+            },
+        ];
+        for key in &cached {
+            match synced_assets.iter().position(|item| item == key) {
+                Some(pos) => {
+                    synced_assets.remove(pos);
+                }
+                None => self
+                    .put_asset(key.clone())
+                    .await
+                    .with_context(|| format!("failed to transfer {}", key.path.display()))?,
+            }
+            synced_assets.push_back(key.clone());
+        }
+        // assets of current request are the most recently used ones
+        while synced_assets.len() > MAX_CACHED_ASSETS {
+            let key = synced_assets.pop_front().expect("queue is not empty");
+            self.send(&Request::DropAsset(key)).await?;
+        }
+        self.put_asset(remote::AssetKey {
+            path: req.run_source.clone(),
+            revision: None,
+        })
+        .await
+        .context("failed to transfer run source")?;
+        if let Some(stdin) = req.custom_run_stdin() {
+            self.put_asset(remote::AssetKey {
+                path: stdin,
+                revision: None,
+            })
+            .await
+            .context("failed to transfer custom run input")?;
+        }
         Ok(())
     }
 
+    /// Streams asset to remote worker in bounded chunks and waits until
+    /// worker saves it
+    async fn put_asset(&self, key: remote::AssetKey) -> anyhow::Result<()> {
+        let packed = remote::pack_asset(&key.path)
+            .await
+            .context("failed to pack asset")?;
+        let header = remote::AssetHeader {
+            key,
+            is_dir: packed.is_dir,
+        };
+        self.send(&Request::PutAsset(header)).await?;
+        let mut data = tokio::fs::File::open(packed.data_path())
+            .await
+            .context("failed to open asset data")?;
+        let mut buf = vec![0; remote::ASSET_CHUNK_SIZE];
+        loop {
+            let len = data
+                .read(&mut buf)
+                .await
+                .context("failed to read asset data")?;
+            if len == 0 {
+                break;
+            }
+            self.send(&Request::AssetChunk(base64::encode(&buf[..len])))
+                .await?;
+        }
+        self.send(&Request::AssetEnd).await?;
+        match self.recv().await? {
+            Response::AssetSaved => Ok(()),
+            Response::AssetFailed(err) => bail!("worker failed to save asset: {}", err),
+            other => bail!("unexpected response to asset transfer: {:?}", other),
+        }
+    }
+
     /// If this worker is idle, returns a handle to it.
     /// Otherwise, returns None
-    pub fn try_lock(&self, notify: multiwake::Sender) -> Option<FreeWorkerHandle> {
-        if self.state.lock() {
+    pub fn try_lock(this: &Arc<Self>, notify: multiwake::Sender) -> Option<FreeWorkerHandle> {
+        if this.state.lock() {
             Some(FreeWorkerHandle {
-                worker: self,
+                worker: this.clone(),
                 notify,
             })
         } else {
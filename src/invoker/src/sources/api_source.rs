@@ -64,18 +64,29 @@ impl JudgeResponseCallbacks for Callbacks {
             .get(&invocation_id)
             .context("corrupted run_id_mapping")?
             .clone();
-        client::models::RunPatch::patch_run()
+        let kind = header.kind.as_str();
+        // subtasks without score are hidden in this judge log
+        let subtasks = header
+            .subtasks
+            .iter()
+            .filter_map(|row| Some((row.subtask_id.0.to_string(), row.score?)));
+        let mut patch = client::models::RunPatch::patch_run()
             .run_id(run_id)
             .status(
                 vec![
                     vec![
-                        header.kind.as_str(),
+                        kind,
                         &format!("{}:{}", header.status.kind, header.status.code),
                     ]
                     .into_iter(),
                 ]
                 .into_iter(),
             )
+            .subtasks(std::iter::once((kind.to_string(), subtasks)));
+        if let Some(score) = header.score {
+            patch = patch.score(std::iter::once((kind.to_string(), score)));
+        }
+        patch
             .send(&self.inner.api)
             .await
             .context("failed to send outcome to API")?;
@@ -98,6 +109,14 @@ impl JudgeResponseCallbacks for Callbacks {
         eprintln!("TODO");
         Ok(())
     }
+
+    async fn deliver_custom_run_result(
+        &self,
+        _invocation_id: Uuid,
+        _result: invoker_api::CustomRunResult,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("custom runs are not requested from API")
+    }
 }
 
 impl ApiSource {
@@ -153,6 +172,8 @@ impl ApiSource {
                 revision: 0,
                 toolchain_id: toolchain.image.clone(),
                 run_source,
+                revalue: None,
+                custom_run: None,
             };
 
             tasks.push(task);
@@ -1,6 +1,6 @@
 use crate::controller::{InvocationFinishReason, JudgeRequestAndCallbacks, JudgeResponseCallbacks};
 use anyhow::Context as _;
-use invoker_api::{CliJudgeRequest, JudgeRequest};
+use invoker_api::{CliJudgeRequest, CustomRunRequest, JudgeRequest, RevalueRequest};
 use std::sync::Arc;
 use tokio::io::AsyncBufReadExt;
 use tracing::debug;
@@ -10,12 +10,34 @@ async fn convert_request(cli_judge_request: CliJudgeRequest) -> anyhow::Result<J
     let run_source = tokio::fs::read(&cli_judge_request.run_source)
         .await
         .context("run_source not readable")?;
+    let revalue = match &cli_judge_request.revalue {
+        Some(path) => {
+            let previous_judge_log = tokio::fs::read(path)
+                .await
+                .context("previous judge log not readable")?;
+            let previous_judge_log = serde_json::from_slice(&previous_judge_log)
+                .context("previous judge log is invalid")?;
+            Some(RevalueRequest { previous_judge_log })
+        }
+        None => None,
+    };
+    let custom_run = match &cli_judge_request.custom_run_stdin {
+        Some(path) => Some(CustomRunRequest {
+            stdin: tokio::fs::read(path)
+                .await
+                .context("custom run input not readable")?,
+            limits: cli_judge_request.custom_run_limits,
+        }),
+        None => None,
+    };
     Ok(JudgeRequest {
         revision: cli_judge_request.revision,
         toolchain_id: cli_judge_request.toolchain_id,
         problem_id: cli_judge_request.problem_id,
         request_id: cli_judge_request.request_id,
         run_source,
+        revalue,
+        custom_run,
     })
 }
 
@@ -24,6 +46,7 @@ pub enum Message {
     Finish(FinishedMessage),
     Progress(ProgressMessage),
     LiveStatusUpdate(LsuMessage),
+    CustomRun(CustomRunMessage),
 }
 #[derive(serde::Serialize)]
 pub struct FinishedMessage {
@@ -43,6 +66,12 @@ pub struct LsuMessage {
     update: invoker_api::LiveStatusUpdate,
 }
 
+#[derive(serde::Serialize)]
+pub struct CustomRunMessage {
+    invocation_id: Uuid,
+    result: invoker_api::CustomRunResult,
+}
+
 struct Callbacks;
 
 #[async_trait::async_trait]
@@ -87,6 +116,18 @@ impl JudgeResponseCallbacks for Callbacks {
         }))
         .await
     }
+
+    async fn deliver_custom_run_result(
+        &self,
+        invocation_id: Uuid,
+        result: invoker_api::CustomRunResult,
+    ) -> anyhow::Result<()> {
+        print_message(Message::CustomRun(CustomRunMessage {
+            invocation_id,
+            result,
+        }))
+        .await
+    }
 }
 
 async fn read_worker_iteration(
@@ -4,11 +4,13 @@
 
 mod compiler;
 mod exec_test;
+mod hardening;
 mod invoke_util;
-mod os_util;
+mod revalue;
 mod transform_judge_log;
 mod valuer;
 
+use crate::remote;
 use anyhow::Context;
 use compiler::{BuildOutcome, Compiler};
 use exec_test::{ExecRequest, TestExecutor};
@@ -19,12 +21,17 @@ use invoker_api::{
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    collections::HashMap,
+    io::{BufRead, Write},
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
 use tracing::{debug, error};
 use valuer::Valuer;
+
+pub(crate) use hardening::SandboxSettings;
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub(crate) struct Command {
     pub(crate) argv: Vec<String>,
@@ -41,6 +48,9 @@ pub(crate) struct LoweredJudgeRequest {
     pub(crate) problem: pom::Problem,
     /// Path to problem dir
     pub(crate) problem_dir: PathBuf,
+    /// Revision of problem, used to identify problem assets cached by remote
+    /// workers
+    pub(crate) problem_revision: String,
     /// Path to file containing run source
     pub(crate) run_source: PathBuf,
     /// Name of source file in sandbox. E.g., `source.cpp` for C++.
@@ -49,8 +59,15 @@ pub(crate) struct LoweredJudgeRequest {
     pub(crate) out_dir: PathBuf,
     /// Toolchain directory (i.e. sysroot for command execution)
     pub(crate) toolchain_dir: PathBuf,
+    /// Hardening for compilation and test sandboxes
+    pub(crate) sandbox_settings: SandboxSettings,
     /// UUID of request
     pub(crate) judge_request_id: uuid::Uuid,
+    /// Full judge log of previous judging, if this is re-valuation
+    pub(crate) previous_judge_log: Option<invoker_api::judge_log::JudgeLog>,
+    /// If set, `problem` contains single test with custom input, and run is
+    /// executed on it without checker and valuer
+    pub(crate) custom_run: bool,
 }
 
 impl LoweredJudgeRequest {
@@ -74,19 +91,137 @@ impl LoweredJudgeRequest {
             None => self.out_dir.join("compile"),
         }
     }
+
+    /// Directory with contestant's answers, for output-only problems
+    pub(crate) fn answers_dir(&self) -> PathBuf {
+        self.out_dir.join("answers")
+    }
+
+    pub(crate) fn answer_path(&self, test_id: u32) -> PathBuf {
+        self.answers_dir().join(format!("{}.txt", test_id))
+    }
+
+    /// Returns path to custom run input
+    pub(crate) fn custom_run_stdin(&self) -> Option<PathBuf> {
+        if self.custom_run {
+            Some(self.resolve_asset(&self.problem.tests[0].path))
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub(crate) enum Request {
     Judge(LoweredJudgeRequest),
+    /// Only sent to remote workers, before `Judge` request which uses asset.
+    /// Asset data follows in `AssetChunk`s, terminated by `AssetEnd`.
+    PutAsset(remote::AssetHeader),
+    /// Base64-encoded part of asset data
+    AssetChunk(String),
+    /// Worker answers with `AssetSaved` or `AssetFailed`
+    AssetEnd,
+    /// Asset is evicted from remote worker cache
+    DropAsset(remote::AssetKey),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) enum Response {
+    /// Sent just before `JudgeDone`
+    Stats(JudgeStats),
     JudgeDone(JudgeOutcome),
     OutcomeHeader(invoker_api::JudgeOutcomeHeader),
+    /// Sent before `JudgeDone` if custom run was executed
+    CustomRun(invoker_api::CustomRunResult),
     LiveTest(u32),
     LiveScore(u32),
+    /// Sent periodically, so that controller can detect lost workers
+    Heartbeat,
+    /// Sent after `AssetEnd` if asset was saved
+    AssetSaved,
+    /// Sent after `AssetEnd` if asset could not be saved
+    AssetFailed(String),
+}
+
+/// Statistics of single judging, used for metrics
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct JudgeStats {
+    /// Time spent in compilation
+    pub(crate) compile_time: Option<Duration>,
+    /// Total time spent running tests (including checkers)
+    pub(crate) tests_time: Option<Duration>,
+    /// Total time spent waiting for valuer responses
+    pub(crate) valuer_time: Option<Duration>,
+    /// Status codes of judged tests
+    pub(crate) test_statuses: Vec<String>,
+}
+
+/// Interval between heartbeats
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connection to controller. Shared with heartbeat thread.
+type Output = Arc<std::sync::Mutex<Box<dyn Write + Send>>>;
+
+fn write_response(output: &Output, resp: &Response) -> std::io::Result<()> {
+    let mut msg = serde_json::to_vec(resp).expect("failed to serialize Response");
+    msg.push(b'\n');
+    let mut output = output.lock().unwrap();
+    output.write_all(&msg)?;
+    output.flush()
+}
+
+/// Sends heartbeats from separate thread, because judging blocks worker thread
+fn start_heartbeats(output: Output) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(HEARTBEAT_INTERVAL);
+        if write_response(&output, &Response::Heartbeat).is_err() {
+            break;
+        }
+    });
+}
+
+/// Reads requests in helper thread, because controller connection is blocking
+fn start_reader(mut input: Box<dyn BufRead + Send>) -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || loop {
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// Assets transferred to remote worker
+struct RemoteAssets {
+    /// Contains assets and judging directories
+    work_dir: tempfile::TempDir,
+    /// Maps asset to local path
+    assets: HashMap<remote::AssetKey, PathBuf>,
+    next_asset_id: u64,
+    /// Asset which is being received
+    incoming: Option<IncomingAsset>,
+}
+
+impl RemoteAssets {
+    /// Returns local path of cached asset
+    fn cached(&self, key: &remote::AssetKey) -> anyhow::Result<PathBuf> {
+        self.assets
+            .get(key)
+            .cloned()
+            .with_context(|| format!("asset {} was not transferred", key.path.display()))
+    }
+}
+
+struct IncomingAsset {
+    header: remote::AssetHeader,
+    /// Received data, or error which occurred while receiving it
+    data: anyhow::Result<tempfile::NamedTempFile>,
 }
 
 pub(crate) struct Worker {
@@ -94,78 +229,255 @@ pub(crate) struct Worker {
     minion: Arc<dyn minion::erased::Backend>,
     /// Invoker configuration
     config: crate::config::InvokerConfig,
+    /// Statistics of current judging
+    stats: JudgeStats,
+    output: Output,
+    /// `None` if worker shares filesystem with controller
+    remote: Option<RemoteAssets>,
 }
 
 impl Worker {
-    pub(crate) fn new(config: crate::config::InvokerConfig) -> anyhow::Result<Worker> {
+    pub(crate) fn new(
+        config: crate::config::InvokerConfig,
+        output: Box<dyn Write + Send>,
+        is_remote: bool,
+    ) -> anyhow::Result<Worker> {
+        let remote = if is_remote {
+            Some(RemoteAssets {
+                work_dir: tempfile::TempDir::new().context("failed to create work dir")?,
+                assets: HashMap::new(),
+                next_asset_id: 0,
+                incoming: None,
+            })
+        } else {
+            None
+        };
         Ok(Worker {
             minion: minion::erased::setup()
                 .context("minion initialization failed")?
                 .into(),
             config,
+            stats: JudgeStats::default(),
+            output: Arc::new(std::sync::Mutex::new(output)),
+            remote,
         })
     }
 
-    async fn recv(&self, stdin: &mut (impl AsyncBufReadExt + Unpin)) -> Option<Request> {
-        let mut buf = String::new();
-        match stdin.read_line(&mut buf).await {
-            Ok(_) => {
-                if buf.trim().is_empty() {
-                    return None;
-                }
-
-                Some(serde_json::from_str(&buf).expect("parse error"))
-            }
-            Err(_) => None,
+    async fn recv(
+        &self,
+        input: &mut mpsc::UnboundedReceiver<String>,
+    ) -> anyhow::Result<Option<Request>> {
+        let buf = match input.recv().await {
+            Some(buf) => buf,
+            None => return Ok(None),
+        };
+        if buf.trim().is_empty() {
+            return Ok(None);
         }
+        serde_json::from_str(&buf)
+            .map(Some)
+            .context("failed to parse request")
     }
 
     async fn send(&self, resp: Response) {
-        let mut stdout = tokio::io::stdout();
-        let mut msg = serde_json::to_vec(&resp).expect("failed to serialize Response");
-        msg.push(b'\n');
-        stdout
-            .write_all(&msg)
-            .await
-            .expect("Failed to print Response");
+        if let Err(err) = write_response(&self.output, &resp) {
+            error!("Failed to send response: {}", err);
+        }
     }
 
-    pub(crate) async fn main_loop(mut self) {
-        let mut stdin = tokio::io::BufReader::new(tokio::io::stdin());
-        while let Some(req) = self.recv(&mut stdin).await {
+    /// Processes requests until controller disconnects. Returns error if
+    /// controller sent malformed request.
+    pub(crate) async fn main_loop(mut self, input: Box<dyn BufRead + Send>) -> anyhow::Result<()> {
+        let mut input = start_reader(input);
+        start_heartbeats(self.output.clone());
+        while let Some(req) = self.recv(&mut input).await? {
             match req {
                 Request::Judge(judge_req) => {
                     debug!("Got LoweredJudgeRequest: {:?}", &judge_req);
-                    let outcome = match self.judge(&judge_req).await {
-                        Ok(o) => o,
+                    let outcome = match self.localize_request(judge_req) {
+                        Ok(judge_req) => {
+                            let outcome = self.judge_and_report(&judge_req).await;
+                            if self.remote.is_some() {
+                                self.cleanup_remote_request(&judge_req);
+                            }
+                            outcome
+                        }
                         Err(err) => {
-                            error!("Invoke failed: {:#}", err);
-                            self.create_fake_protocols(
-                                &judge_req,
-                                &invoker_api::Status {
-                                    kind: invoker_api::StatusKind::InternalError,
-                                    code: invoker_api::status_codes::JUDGE_FAULT.to_string(),
-                                },
-                            )
-                            .await
-                            .ok();
+                            error!("Failed to prepare request: {:#}", err);
                             JudgeOutcome::Fault
                         }
                     };
                     debug!("JudgeOutcome: {:?}", &outcome);
+                    let stats = std::mem::take(&mut self.stats);
+                    self.send(Response::Stats(stats)).await;
                     self.send(Response::JudgeDone(outcome)).await;
                 }
+                Request::PutAsset(header) => self.begin_asset(header)?,
+                Request::AssetChunk(chunk) => self.put_asset_chunk(&chunk)?,
+                Request::AssetEnd => {
+                    let response = match self.finish_asset()? {
+                        Ok(()) => Response::AssetSaved,
+                        Err(err) => {
+                            error!("Failed to save asset: {:#}", err);
+                            Response::AssetFailed(format!("{:#}", err))
+                        }
+                    };
+                    self.send(response).await;
+                }
+                Request::DropAsset(key) => self.drop_asset(&key)?,
             }
         }
+        Ok(())
     }
 
-    async fn judge(&mut self, req: &LoweredJudgeRequest) -> anyhow::Result<JudgeOutcome> {
-        let compiler = Compiler {
-            req,
-            minion: &*self.minion,
-            config: &self.config,
+    async fn judge_and_report(&mut self, req: &LoweredJudgeRequest) -> JudgeOutcome {
+        match self.judge(req).await {
+            Ok(o) => o,
+            Err(err) => {
+                error!("Invoke failed: {:#}", err);
+                self.create_fault_protocols(req, &err).await.ok();
+                JudgeOutcome::Fault
+            }
+        }
+    }
+
+    fn remote_assets(&mut self) -> anyhow::Result<&mut RemoteAssets> {
+        self.remote
+            .as_mut()
+            .context("assets are only accepted by remote workers")
+    }
+
+    /// Starts receiving asset, transferred from controller.
+    /// Errors returned by this and other asset functions are protocol
+    /// violations, and failures to save asset are reported in `AssetEnd`.
+    fn begin_asset(&mut self, header: remote::AssetHeader) -> anyhow::Result<()> {
+        let remote = self.remote_assets()?;
+        if remote.incoming.is_some() {
+            anyhow::bail!("previous asset is not finished");
+        }
+        let data = tempfile::NamedTempFile::new_in(remote.work_dir.path())
+            .context("failed to create asset file");
+        remote.incoming = Some(IncomingAsset { header, data });
+        Ok(())
+    }
+
+    fn put_asset_chunk(&mut self, chunk: &str) -> anyhow::Result<()> {
+        let incoming = self
+            .remote_assets()?
+            .incoming
+            .as_mut()
+            .context("asset chunk sent without asset header")?;
+        if let Ok(data) = &mut incoming.data {
+            let res = base64::decode(chunk)
+                .context("asset data is not valid base64")
+                .and_then(|chunk| data.write_all(&chunk).context("failed to write asset data"));
+            if let Err(err) = res {
+                incoming.data = Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves received asset. Outer error is protocol violation, inner error
+    /// means that asset was not saved.
+    fn finish_asset(&mut self) -> anyhow::Result<anyhow::Result<()>> {
+        let remote = self.remote_assets()?;
+        let incoming = remote
+            .incoming
+            .take()
+            .context("asset end sent without asset header")?;
+        let target = remote
+            .work_dir
+            .path()
+            .join(format!("asset-{}", remote.next_asset_id));
+        remote.next_asset_id += 1;
+        let IncomingAsset { header, data } = incoming;
+        // data file is removed when `data` is dropped, unless it was moved
+        let res = data.and_then(|data| remote::unpack_asset(&header, data.path(), &target));
+        if res.is_ok() {
+            remote.assets.insert(header.key, target);
+        }
+        Ok(res)
+    }
+
+    /// Removes asset evicted by controller
+    fn drop_asset(&mut self, key: &remote::AssetKey) -> anyhow::Result<()> {
+        let path = match self.remote_assets()?.assets.remove(key) {
+            Some(path) => path,
+            // asset could have failed to save
+            None => return Ok(()),
+        };
+        let res = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        if let Err(err) = res {
+            error!("Failed to remove asset {}: {}", path.display(), err);
+        }
+        Ok(())
+    }
+
+    /// For remote worker, replaces controller paths in `req` with local ones
+    fn localize_request(
+        &mut self,
+        mut req: LoweredJudgeRequest,
+    ) -> anyhow::Result<LoweredJudgeRequest> {
+        let remote = match &mut self.remote {
+            Some(remote) => remote,
+            None => return Ok(req),
+        };
+        req.problem_dir = remote.cached(&remote::AssetKey {
+            path: req.problem_dir.clone(),
+            revision: Some(req.problem_revision.clone()),
+        })?;
+        req.toolchain_dir = remote.cached(&remote::AssetKey {
+            path: req.toolchain_dir.clone(),
+            revision: None,
+        })?;
+        // run source is used once
+        let key = remote::AssetKey {
+            path: req.run_source.clone(),
+            revision: None,
         };
+        req.run_source = remote
+            .assets
+            .remove(&key)
+            .context("run source was not transferred")?;
+        if let Some(stdin) = req.custom_run_stdin() {
+            let key = remote::AssetKey {
+                path: stdin,
+                revision: None,
+            };
+            let local = remote
+                .assets
+                .remove(&key)
+                .context("custom run input was not transferred")?;
+            req.problem.tests[0].path.path = local.display().to_string();
+        }
+        req.out_dir = tempfile::tempdir_in(remote.work_dir.path())
+            .context("failed to create judging dir")?
+            .into_path();
+        Ok(req)
+    }
+
+    /// Removes files of finished request. Remote worker does not share them
+    /// with controller, so they are not needed anymore.
+    fn cleanup_remote_request(&self, req: &LoweredJudgeRequest) {
+        if let Err(err) = std::fs::remove_file(&req.run_source) {
+            error!("Failed to remove run source: {}", err);
+        }
+        if let Some(stdin) = req.custom_run_stdin() {
+            if let Err(err) = std::fs::remove_file(&stdin) {
+                error!("Failed to remove custom run input: {}", err);
+            }
+        }
+        if let Err(err) = std::fs::remove_dir_all(&req.out_dir) {
+            error!("Failed to remove judging dir: {}", err);
+        }
+    }
 
+    async fn judge(&mut self, req: &LoweredJudgeRequest) -> anyhow::Result<JudgeOutcome> {
         if !req.run_source.exists() {
             anyhow::bail!("Run source file not exists");
         }
@@ -174,18 +486,26 @@ impl Worker {
             anyhow::bail!("Run output dir not exists");
         }
 
-        let compiler_response = compiler.compile();
+        if let Some(previous) = &req.previous_judge_log {
+            return self.revalue(req, previous).await;
+        }
 
         let outcome;
 
-        match compiler_response {
-            Err(err) => return Err(err).context("compilation error"),
-            Ok(BuildOutcome::Error(st)) => {
-                self.create_fake_protocols(req, &st).await?;
+        match self.compile(req)? {
+            BuildOutcome::Error(st) => {
+                self.create_fake_protocols(req, &st, None).await?;
                 outcome = JudgeOutcome::CompileError(st);
             }
-            Ok(BuildOutcome::Success) => {
-                self.run_tests(req).await.context("failed to run tests")?;
+            BuildOutcome::Success if req.custom_run => {
+                let result = self.custom_run(req).context("custom run failed")?;
+                self.send(Response::CustomRun(result)).await;
+                outcome = JudgeOutcome::TestingDone;
+            }
+            BuildOutcome::Success => {
+                self.run_tests(req, HashMap::new(), true)
+                    .await
+                    .context("failed to run tests")?;
 
                 outcome = JudgeOutcome::TestingDone;
             }
@@ -193,12 +513,46 @@ impl Worker {
         Ok(outcome)
     }
 
+    fn compile(&mut self, req: &LoweredJudgeRequest) -> anyhow::Result<BuildOutcome> {
+        let compiler = Compiler {
+            req,
+            minion: &self.minion,
+            config: &self.config,
+        };
+        let compile_started_at = Instant::now();
+        let compiler_response = compiler.compile();
+        self.stats.compile_time = Some(compile_started_at.elapsed());
+        compiler_response.context("compilation error")
+    }
+
+    /// Values results of previous judging with fresh valuer.
+    /// Run is only compiled if valuer requests test which was not run
+    /// previously.
+    async fn revalue(
+        &mut self,
+        req: &LoweredJudgeRequest,
+        previous: &invoker_api::judge_log::JudgeLog,
+    ) -> anyhow::Result<JudgeOutcome> {
+        if let Some(st) = revalue::previous_build_error(previous) {
+            self.create_fake_protocols(req, st, None).await?;
+            return Ok(JudgeOutcome::CompileError(st.clone()));
+        }
+        let stored_results = revalue::restore_previous_results(req, previous)
+            .context("failed to restore previous results")?;
+        self.run_tests(req, stored_results, false)
+            .await
+            .context("failed to run tests")?;
+        Ok(JudgeOutcome::TestingDone)
+    }
+
     /// Used when we are unable to produce protocols, i.e. on compilation errors
     /// and judge faults.
+    /// `valuer_log` is only included into full judge log.
     async fn create_fake_protocols(
         &mut self,
         req: &LoweredJudgeRequest,
         status: &invoker_api::Status,
+        valuer_log: Option<&str>,
     ) -> anyhow::Result<()> {
         for kind in invoker_api::judge_log::JudgeLogKind::list() {
             let pseudo_valuer_proto = invoker_api::valuer_proto::JudgeLog {
@@ -210,21 +564,45 @@ impl Worker {
             };
             let mut protocol = self.process_judge_log(&pseudo_valuer_proto, req, &[])?;
             protocol.status = status.clone();
+            if let invoker_api::judge_log::JudgeLogKind::Full = kind {
+                protocol.valuer_log = valuer_log.map(ToString::to_string);
+            }
             self.put_protocol(req, protocol).await?;
         }
         Ok(())
     }
 
+    /// Creates protocols for run which was not judged because of `err`.
+    /// Valuer stderr and error description are attached to full judge log.
+    async fn create_fault_protocols(
+        &mut self,
+        req: &LoweredJudgeRequest,
+        err: &anyhow::Error,
+    ) -> anyhow::Result<()> {
+        let mut valuer_log = std::fs::read(req.out_dir.join("valuer-log.txt"))
+            .map(|log| String::from_utf8_lossy(&log).into_owned())
+            .unwrap_or_default();
+        valuer_log.push_str(&format!("--- judge fault ---\n{:#}\n", err));
+        let status = invoker_api::Status {
+            kind: invoker_api::StatusKind::InternalError,
+            code: invoker_api::status_codes::JUDGE_FAULT.to_string(),
+        };
+        self.create_fake_protocols(req, &status, Some(&valuer_log))
+            .await
+    }
+
     async fn put_outcome(
         &mut self,
         score: u32,
         status: invoker_api::Status,
         kind: invoker_api::judge_log::JudgeLogKind,
+        subtasks: Vec<invoker_api::judge_log::JudgeLogSubtaskRow>,
     ) {
         let header = invoker_api::JudgeOutcomeHeader {
             score: Some(score),
             status,
             kind,
+            subtasks,
         };
         self.send(Response::OutcomeHeader(header)).await;
     }
@@ -241,42 +619,62 @@ impl Worker {
         let protocol_file = std::io::BufWriter::new(protocol_file);
         serde_json::to_writer(protocol_file, &protocol)
             .context("failed to write judge log to file")?;
-        self.put_outcome(protocol.score, protocol.status, protocol.kind)
-            .await;
+        self.put_outcome(
+            protocol.score,
+            protocol.status,
+            protocol.kind,
+            protocol.subtasks,
+        )
+        .await;
         Ok(())
     }
 
-    async fn run_tests(&mut self, req: &LoweredJudgeRequest) -> anyhow::Result<()> {
+    /// Runs tests requested by valuer.
+    /// Outcomes from `stored_results` are passed to valuer without running
+    /// the test. If `is_built` is false, run is compiled before running first
+    /// test which is not stored.
+    async fn run_tests(
+        &mut self,
+        req: &LoweredJudgeRequest,
+        stored_results: HashMap<pom::TestId, exec_test::ExecOutcome>,
+        mut is_built: bool,
+    ) -> anyhow::Result<()> {
         let mut test_results = vec![];
 
-        let mut valuer = Valuer::new(req).context("failed to init valuer")?;
+        let mut valuer =
+            Valuer::new(req, &self.minion, &self.config).context("failed to init valuer")?;
         valuer
             .write_problem_data(req)
             .await
             .context("failed to send problem data")?;
         loop {
-            match valuer.poll().await? {
+            let poll_started_at = Instant::now();
+            let valuer_response = valuer.poll().await?;
+            *self.stats.valuer_time.get_or_insert_with(Duration::default) +=
+                poll_started_at.elapsed();
+            match valuer_response {
                 ValuerResponse::Test { test_id: tid, live } => {
                     if live {
                         self.send(Response::LiveTest(tid.get())).await;
                     }
-                    let tid_u32: u32 = tid.into();
-                    let test = &req.problem.tests[(tid_u32 - 1u32) as usize];
-                    let judge_request = ExecRequest {
-                        test,
-                        test_id: tid.into(),
-                    };
-
-                    let test_exec = TestExecutor {
-                        exec: judge_request,
-                        req,
-                        minion: &*self.minion,
-                        config: &self.config,
+                    let judge_response = match stored_results.get(&tid) {
+                        Some(stored) => {
+                            debug!("Replaying stored result of test {}", tid);
+                            stored.clone()
+                        }
+                        None => {
+                            if !is_built {
+                                if let BuildOutcome::Error(st) = self.compile(req)? {
+                                    anyhow::bail!(
+                                        "run was judged previously, but now compilation fails: {:?}",
+                                        st
+                                    );
+                                }
+                                is_built = true;
+                            }
+                            self.exec_test(req, tid)?
+                        }
                     };
-
-                    let judge_response = test_exec
-                        .exec()
-                        .with_context(|| format!("failed to judge solution on test {}", tid))?;
                     test_results.push((tid, judge_response.clone()));
                     valuer
                         .notify_test_done(TestDoneNotification {
@@ -307,6 +705,54 @@ impl Worker {
 
         Ok(())
     }
+
+    fn custom_run(
+        &mut self,
+        req: &LoweredJudgeRequest,
+    ) -> anyhow::Result<invoker_api::CustomRunResult> {
+        let test_exec = TestExecutor {
+            exec: ExecRequest {
+                test: &req.problem.tests[0],
+                test_id: 1,
+            },
+            req,
+            minion: &self.minion,
+            config: &self.config,
+        };
+        let exec_started_at = Instant::now();
+        let result = test_exec.exec_custom()?;
+        self.stats.tests_time = Some(exec_started_at.elapsed());
+        Ok(result)
+    }
+
+    fn exec_test(
+        &mut self,
+        req: &LoweredJudgeRequest,
+        tid: pom::TestId,
+    ) -> anyhow::Result<exec_test::ExecOutcome> {
+        let test = &req.problem.tests[tid];
+        let judge_request = ExecRequest {
+            test,
+            test_id: tid.into(),
+        };
+
+        let test_exec = TestExecutor {
+            exec: judge_request,
+            req,
+            minion: &self.minion,
+            config: &self.config,
+        };
+
+        let exec_started_at = Instant::now();
+        let judge_response = test_exec
+            .exec()
+            .with_context(|| format!("failed to judge solution on test {}", tid))?;
+        *self.stats.tests_time.get_or_insert_with(Duration::default) += exec_started_at.elapsed();
+        self.stats
+            .test_statuses
+            .push(judge_response.status.code.clone());
+        Ok(judge_response)
+    }
 }
 
 #[derive(Serialize, Debug, Clone, Deserialize)]
@@ -325,7 +771,70 @@ pub async fn main() -> anyhow::Result<()> {
     let config_data = std::env::var("__JJS_WORKER_INVOKER_CONFIG")
         .context("__JJS_WORKER_INVOKER_CONFIG missing")?;
     let config = serde_json::from_str(&config_data)?;
-    let w = Worker::new(config).context("worker initialization failed")?;
-    w.main_loop().await;
-    Ok(())
+    let w = Worker::new(config, Box::new(std::io::stdout()), false)
+        .context("worker initialization failed")?;
+    w.main_loop(Box::new(std::io::BufReader::new(std::io::stdin())))
+        .await
+}
+
+/// Sets read timeout of controller connection
+type SetReadTimeout = Box<dyn Fn(Option<Duration>) -> std::io::Result<()> + Send>;
+
+/// Runs remote worker, which connects to controller at `endpoint`.
+/// Unless `trusted_network` is set, TCP endpoint must be a loopback address.
+pub async fn remote_main(endpoint: &str, token: &str, trusted_network: bool) -> anyhow::Result<()> {
+    let endpoint: remote::Endpoint = endpoint.parse()?;
+    let (mut input, mut output, set_read_timeout): (
+        Box<dyn BufRead + Send>,
+        Box<dyn Write + Send>,
+        SetReadTimeout,
+    ) = match endpoint {
+        remote::Endpoint::Tcp(addr) => {
+            let stream = std::net::TcpStream::connect(&addr)
+                .with_context(|| format!("failed to connect to {}", addr))?;
+            remote::check_address_trusted(stream.peer_addr()?, trusted_network)?;
+            let output = stream.try_clone()?;
+            let control = stream.try_clone()?;
+            (
+                Box::new(std::io::BufReader::new(stream)),
+                Box::new(output),
+                Box::new(move |timeout| control.set_read_timeout(timeout)),
+            )
+        }
+        remote::Endpoint::Unix(path) => {
+            let stream = std::os::unix::net::UnixStream::connect(&path)
+                .with_context(|| format!("failed to connect to {}", path.display()))?;
+            let output = stream.try_clone()?;
+            let control = stream.try_clone()?;
+            (
+                Box::new(std::io::BufReader::new(stream)),
+                Box::new(output),
+                Box::new(move |timeout| control.set_read_timeout(timeout)),
+            )
+        }
+    };
+    let hello = remote::Hello {
+        name: nix::unistd::gethostname(&mut [0; 256])
+            .ok()
+            .and_then(|name| name.to_str().ok())
+            .unwrap_or("unknown")
+            .to_string(),
+        token: token.to_string(),
+    };
+    let mut msg = serde_json::to_vec(&hello)?;
+    msg.push(b'\n');
+    output.write_all(&msg).context("failed to send hello")?;
+    let mut line = String::new();
+    set_read_timeout(Some(remote::WELCOME_TIMEOUT))?;
+    input
+        .read_line(&mut line)
+        .context("failed to receive welcome")?;
+    set_read_timeout(None)?;
+    if line.is_empty() {
+        anyhow::bail!("controller rejected connection");
+    }
+    let welcome: remote::Welcome = serde_json::from_str(&line).context("invalid welcome")?;
+    let config = serde_json::from_str(&welcome.config).context("invalid config")?;
+    let w = Worker::new(config, output, true).context("worker initialization failed")?;
+    w.main_loop(input).await
 }
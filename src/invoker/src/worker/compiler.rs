@@ -1,7 +1,7 @@
-use crate::worker::{invoke_util, LoweredJudgeRequest};
+use crate::worker::{hardening, invoke_util, LoweredJudgeRequest};
 use anyhow::Context;
 use invoker_api::{status_codes, Status, StatusKind};
-use std::fs;
+use std::{collections::HashSet, fs, io::Read, path::Path, sync::Arc, time::Duration};
 
 pub(crate) enum BuildOutcome {
     Success,
@@ -11,12 +11,119 @@ pub(crate) enum BuildOutcome {
 /// Compiler turns SubmissionInfo into Artifact
 pub(crate) struct Compiler<'a> {
     pub(crate) req: &'a LoweredJudgeRequest,
-    pub(crate) minion: &'a dyn minion::erased::Backend,
+    pub(crate) minion: &'a Arc<dyn minion::erased::Backend>,
     pub(crate) config: &'a crate::config::InvokerConfig,
 }
 
+/// How long compiler output may stay open after compiler exited
+const OUTPUT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// gzip header magic
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Upper bound for uncompressed size of output-only submission
+const MAX_ARCHIVE_SIZE: u64 = 1 << 30;
+
+/// Space for tar headers in output-only submission
+const ARCHIVE_OVERHEAD: u64 = 1 << 20;
+
+/// Reader which fails when more than `remaining` bytes are read, so that
+/// compressed archive can not expand unboundedly
+struct CappedReader<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for CappedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let cnt = self.inner.read(buf)?;
+        self.remaining = self.remaining.checked_sub(cnt as u64).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::Other, "archive is too large")
+        })?;
+        Ok(cnt)
+    }
+}
+
+/// Returns test id, which answer file `path` is intended for
+fn answer_test_id(path: &Path) -> Option<u32> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
 impl<'a> Compiler<'a> {
     pub(crate) fn compile(&self) -> anyhow::Result<BuildOutcome> {
+        match self.req.problem.kind {
+            pom::ProblemKind::Standard => self.build(),
+            pom::ProblemKind::OutputOnly => self.unpack_answers(),
+        }
+    }
+
+    /// For output-only problems, extracts answer files from submitted archive
+    /// to `req.answer_path(test_id)`.
+    /// Each answer is limited by test output limit, and whole archive is
+    /// limited by sum of these limits.
+    fn unpack_answers(&self) -> anyhow::Result<BuildOutcome> {
+        let archive = fs::read(&self.req.run_source).context("failed to read run source")?;
+        let reader: Box<dyn Read> = if archive.starts_with(&GZIP_MAGIC) {
+            Box::new(flate2::read::GzDecoder::new(archive.as_slice()))
+        } else {
+            Box::new(archive.as_slice())
+        };
+        let answers_size: u64 = self
+            .req
+            .problem
+            .tests
+            .iter()
+            .map(|test| test.limits.output_limit())
+            .sum();
+        let reader = CappedReader {
+            inner: reader,
+            remaining: std::cmp::min(answers_size + ARCHIVE_OVERHEAD, MAX_ARCHIVE_SIZE),
+        };
+        fs::create_dir_all(self.req.answers_dir()).context("failed to create answers dir")?;
+        let invalid_archive = BuildOutcome::Error(Status {
+            kind: StatusKind::Rejected,
+            code: status_codes::INVALID_ARCHIVE.to_string(),
+        });
+        let mut archive = tar::Archive::new(reader);
+        let entries = match archive.entries() {
+            Ok(entries) => entries,
+            Err(_) => return Ok(invalid_archive),
+        };
+        let mut seen = HashSet::new();
+        for entry in entries {
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => return Ok(invalid_archive),
+            };
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let test_id = match entry.path().ok().and_then(|path| answer_test_id(&path)) {
+                Some(id) if id >= 1 && id as usize <= self.req.problem.tests.len() => id,
+                _ => continue,
+            };
+            if !seen.insert(test_id) {
+                // two answers for one test
+                return Ok(invalid_archive);
+            }
+            let output_limit = self.req.problem.tests[test_id as usize - 1]
+                .limits
+                .output_limit();
+            let mut answer = Vec::new();
+            if (&mut entry)
+                .take(output_limit + 1)
+                .read_to_end(&mut answer)
+                .is_err()
+                || answer.len() as u64 > output_limit
+            {
+                return Ok(invalid_archive);
+            }
+            fs::write(self.req.answer_path(test_id), &answer).context("failed to save answer")?;
+        }
+        Ok(BuildOutcome::Success)
+    }
+
+    fn build(&self) -> anyhow::Result<BuildOutcome> {
         let sandbox = invoke_util::create_sandbox(self.req, None, self.minion, self.config)
             .context("failed to create sandbox")?;
         let step_dir = self.req.step_dir(None);
@@ -34,11 +141,11 @@ impl<'a> Compiler<'a> {
 
             let mut native_command = minion::Command::new();
             invoke_util::command_set_from_judge_req(&mut native_command, &command);
-            invoke_util::command_set_stdio(&mut native_command, &stdout_path, &stderr_path);
+            invoke_util::command_set_stdio(&mut native_command);
 
             native_command.sandbox(sandbox.sandbox.clone());
 
-            let child = match native_command.spawn(self.minion) {
+            let mut child = match native_command.spawn(&**self.minion) {
                 Ok(child) => child,
                 Err(err) => {
                     let is_internal_error = match err.downcast_ref::<minion::linux::Error>() {
@@ -56,9 +163,47 @@ impl<'a> Compiler<'a> {
                 }
             };
 
+            let output_limit = self.req.compile_limits.output_limit();
+            let stdout = invoke_util::CappedOutput::start(
+                child.stdout().context("compiler stdout was not captured")?,
+                &stdout_path,
+                output_limit,
+                sandbox.sandbox.clone(),
+            )?;
+            let stderr = invoke_util::CappedOutput::start(
+                child.stderr().context("compiler stderr was not captured")?,
+                &stderr_path,
+                output_limit,
+                sandbox.sandbox.clone(),
+            )?;
             let wait_result = child
                 .wait_for_exit(None)
                 .context("failed to wait for compiler")?;
+            // Compiler can leave background processes (e.g. build daemons),
+            // which keep output pipes open. Killed sandbox can not run
+            // following commands, so it is only killed after last one.
+            let is_last_command = i + 1 == self.req.compile_commands.len();
+            if is_last_command {
+                sandbox.sandbox.kill().ok();
+            } else if !stdout.wait_closed(OUTPUT_CLOSE_TIMEOUT)
+                || !stderr.wait_closed(OUTPUT_CLOSE_TIMEOUT)
+            {
+                sandbox.sandbox.kill().ok();
+                stdout.finish()?;
+                stderr.finish()?;
+                return Ok(BuildOutcome::Error(Status {
+                    kind: StatusKind::Rejected,
+                    code: status_codes::COMPILER_FAILED.to_string(),
+                }));
+            }
+            let stdout_overflow = stdout.finish()?;
+            let stderr_overflow = stderr.finish()?;
+            if stdout_overflow || stderr_overflow {
+                return Ok(BuildOutcome::Error(Status {
+                    kind: StatusKind::Rejected,
+                    code: status_codes::COMPILER_FAILED.to_string(),
+                }));
+            }
             match wait_result {
                 minion::WaitOutcome::Timeout => {
                     return Ok(BuildOutcome::Error(Status {
@@ -68,12 +213,17 @@ impl<'a> Compiler<'a> {
                 }
                 minion::WaitOutcome::AlreadyFinished => unreachable!("not expected other to wait"),
                 minion::WaitOutcome::Exited => {
-                    if child
+                    let exit_code = child
                         .get_exit_code()
                         .context("failed to get compiler exit code")?
-                        .unwrap()
-                        != 0
-                    {
+                        .unwrap();
+                    if hardening::is_security_violation(exit_code, &self.req.sandbox_settings) {
+                        return Ok(BuildOutcome::Error(Status {
+                            kind: StatusKind::Rejected,
+                            code: status_codes::SECURITY_VIOLATION.to_string(),
+                        }));
+                    }
+                    if exit_code != 0 {
                         return Ok(BuildOutcome::Error(Status {
                             kind: StatusKind::Rejected,
                             code: status_codes::COMPILER_FAILED.to_string(),
@@ -1,10 +1,24 @@
 mod checker_proto;
 
-use crate::worker::{invoke_util, os_util, LoweredJudgeRequest};
+use crate::worker::{hardening, invoke_util, LoweredJudgeRequest};
 use anyhow::Context;
 use invoker_api::{status_codes, Status, StatusKind};
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+    ffi::OsString,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tracing::{debug, error};
+
+/// Shell which opens checker files inside sandbox
+const CHECKER_LAUNCHER_SHELL: &str = "/bin/sh";
+/// Opens files in `/jjs` as descriptors listed in checker environment, and
+/// then execs checker (passed as `$0`) with its arguments
+const CHECKER_LAUNCHER_SCRIPT: &str = "exec 3</jjs/test.txt 4</jjs/corr.txt 5</jjs/sol.txt \
+    6>/jjs/checker-out.txt 7>/jjs/comment.txt \"$0\" \"$@\"";
+
 pub(crate) struct ExecRequest<'a> {
     pub(crate) test_id: u32,
     pub(crate) test: &'a pom::Test,
@@ -20,7 +34,7 @@ pub(crate) struct ExecOutcome {
 pub(crate) struct TestExecutor<'a> {
     pub(crate) exec: ExecRequest<'a>,
     pub(crate) req: &'a LoweredJudgeRequest,
-    pub(crate) minion: &'a dyn minion::erased::Backend,
+    pub(crate) minion: &'a Arc<dyn minion::erased::Backend>,
     pub(crate) config: &'a crate::config::InvokerConfig,
 }
 
@@ -32,6 +46,8 @@ enum RunOutcomeVar {
 struct RunOutcome {
     var: RunOutcomeVar,
     resource_usage: minion::ResourceUsageData,
+    /// `None` if solution was not launched or was killed
+    exit_code: Option<i64>,
 }
 
 fn map_checker_outcome_to_status(out: checker_proto::Output) -> Status {
@@ -73,14 +89,14 @@ impl<'a> TestExecutor<'a> {
         let mut native_command = minion::Command::new();
 
         invoke_util::command_set_from_judge_req(&mut native_command, &command);
-        invoke_util::command_set_stdio(&mut native_command, &stdout_path, &stderr_path);
+        invoke_util::command_set_stdio(&mut native_command);
 
         native_command.sandbox(sandbox.sandbox.clone());
 
         // capture child input
         native_command.stdin(minion::InputSpecification::pipe());
 
-        let mut child = match native_command.spawn(&*self.minion) {
+        let mut child = match native_command.spawn(&**self.minion) {
             Ok(child) => child,
             Err(err) => {
                 let is_internal_error = match err.downcast_ref::<minion::linux::Error>() {
@@ -97,10 +113,24 @@ impl<'a> TestExecutor<'a> {
                     return Ok(RunOutcome {
                         var: run_outcome_var,
                         resource_usage: Default::default(),
+                        exit_code: None,
                     });
                 }
             }
         };
+        let output_limit = self.exec.test.limits.output_limit();
+        let stdout = invoke_util::CappedOutput::start(
+            child.stdout().context("child stdout was not captured")?,
+            &stdout_path,
+            output_limit,
+            sandbox.sandbox.clone(),
+        )?;
+        let stderr = invoke_util::CappedOutput::start(
+            child.stderr().context("child stderr was not captured")?,
+            &stderr_path,
+            output_limit,
+            sandbox.sandbox.clone(),
+        )?;
         let mut stdin = child.stdin().unwrap();
         stdin.write_all(test_data).ok();
         std::mem::drop(stdin); // close pipe
@@ -113,8 +143,22 @@ impl<'a> TestExecutor<'a> {
             .sandbox
             .resource_usage()
             .context("cannot get resource usage")?;
+        // make sure that no process keeps pipes open
+        sandbox.sandbox.kill().ok();
+        let stdout_overflow = stdout.finish()?;
+        let stderr_overflow = stderr.finish()?;
+        if stdout_overflow || stderr_overflow {
+            return Ok(RunOutcome {
+                var: RunOutcomeVar::Fail(Status {
+                    kind: StatusKind::Rejected,
+                    code: status_codes::OUTPUT_LIMIT_EXCEEDED.to_string(),
+                }),
+                resource_usage,
+                exit_code: None,
+            });
+        }
 
-        match wait_result {
+        let exit_code = match wait_result {
             minion::WaitOutcome::Timeout => {
                 return Ok(RunOutcome {
                     var: RunOutcomeVar::Fail(Status {
@@ -122,134 +166,277 @@ impl<'a> TestExecutor<'a> {
                         code: status_codes::TIME_LIMIT_EXCEEDED.to_string(),
                     }),
                     resource_usage,
+                    exit_code: None,
                 });
             }
             minion::WaitOutcome::AlreadyFinished => unreachable!("not expected other to wait"),
             minion::WaitOutcome::Exited => {
-                if child
+                let exit_code = child
                     .get_exit_code()
                     .context("failed to get exit code")?
-                    .unwrap()
-                    != 0
-                {
+                    .unwrap();
+                if exit_code != 0 {
+                    let code = if hardening::is_security_violation(
+                        exit_code,
+                        &self.req.sandbox_settings,
+                    ) {
+                        status_codes::SECURITY_VIOLATION
+                    } else {
+                        status_codes::RUNTIME_ERROR
+                    };
                     return Ok(RunOutcome {
                         var: RunOutcomeVar::Fail(Status {
                             kind: StatusKind::Rejected,
-                            code: status_codes::RUNTIME_ERROR.to_string(),
+                            code: code.to_string(),
                         }),
                         resource_usage,
+                        exit_code: Some(exit_code),
                     });
                 }
+                exit_code
             }
-        }
+        };
 
         Ok(RunOutcome {
             var: RunOutcomeVar::Success {
                 out_data_path: stdout_path,
             },
             resource_usage,
+            exit_code: Some(exit_code),
         })
     }
 
-    pub fn exec(self) -> anyhow::Result<ExecOutcome> {
-        use std::os::unix::io::IntoRawFd;
-        let input_file = self.req.resolve_asset(&self.exec.test.path);
-        let test_data = std::fs::read(input_file).context("failed to read test")?;
-        let run_outcome = self.run_solution(&test_data, self.exec.test_id)?;
-        let sol_file_path = match run_outcome.var {
-            RunOutcomeVar::Success { out_data_path } => out_data_path,
-            RunOutcomeVar::Fail(status) => {
-                return Ok(ExecOutcome {
-                    status,
-                    resource_usage: run_outcome.resource_usage,
-                });
+    /// For output-only problems, uses submitted answer as solution output
+    fn take_answer(&self) -> anyhow::Result<RunOutcome> {
+        let step_dir = self.req.step_dir(Some(self.exec.test_id));
+        fs::create_dir_all(&step_dir).context("failed to create step dir")?;
+        let stdout_path = step_dir.join("stdout.txt");
+        // judge log expects solution stderr
+        fs::write(step_dir.join("stderr.txt"), b"").context("failed to create stderr file")?;
+        let answer_path = self.req.answer_path(self.exec.test_id);
+        let var = if answer_path.exists() {
+            fs::copy(&answer_path, &stdout_path).context("failed to copy answer")?;
+            RunOutcomeVar::Success {
+                out_data_path: stdout_path,
             }
+        } else {
+            fs::write(&stdout_path, b"").context("failed to create stdout file")?;
+            RunOutcomeVar::Fail(Status {
+                kind: StatusKind::Rejected,
+                code: status_codes::ANSWER_MISSING.to_string(),
+            })
         };
-        // run checker
-        let step_dir = self.req.step_dir(Some(self.exec.test_id));
-        let sol_file = fs::File::open(sol_file_path).context("failed to open run's answer")?;
-        let sol_handle = os_util::handle_inherit(sol_file.into_raw_fd().into(), true);
+        Ok(RunOutcome {
+            var,
+            resource_usage: Default::default(),
+            exit_code: None,
+        })
+    }
+
+    /// Runs checker in sandbox.
+    /// Returns `Ok(Err(message))` if checker failed, which means judge fault.
+    /// Checker output and error description are written to `check-log.txt`.
+    fn run_checker(
+        &self,
+        test_data: &[u8],
+        sol_file_path: &Path,
+    ) -> anyhow::Result<Result<checker_proto::Output, String>> {
+        let test_id = self.exec.test_id;
+        let limits = self.req.problem.checker_limits;
+        let corr_data = match &self.exec.test.correct {
+            Some(corr_path) => {
+                let full_path = self.req.resolve_asset(corr_path);
+                fs::read(full_path).context("failed to read correct answer")?
+            }
+            None => Vec::new(),
+        };
+        let sol_size = fs::metadata(sol_file_path)
+            .context("failed to stat run's answer")?
+            .len();
+        let data_size = test_data.len() as u64 + corr_data.len() as u64 + sol_size;
+        let sandbox = invoke_util::create_checker_sandbox(
+            self.req,
+            test_id,
+            data_size,
+            self.minion,
+            self.config,
+        )?;
+        let check_dir = self.req.step_dir(Some(test_id)).join("check");
+        let data_dir = check_dir.join("data");
+        fs::write(data_dir.join("test.txt"), test_data).context("failed to write test")?;
+        fs::write(data_dir.join("corr.txt"), &corr_data)
+            .context("failed to write correct answer")?;
+        fs::copy(sol_file_path, data_dir.join("sol.txt")).context("failed to copy run's answer")?;
+
         let full_checker_path = self.req.resolve_asset(&self.req.problem.checker_exe);
-        let mut cmd = std::process::Command::new(full_checker_path.clone());
         debug!(
             "full checker path: {}, short path: {}",
-            full_checker_path.to_str().unwrap(),
+            full_checker_path.display(),
             &self.req.problem.checker_exe.path
         );
+        let mut cmd = minion::Command::new();
+        // minion only passes stdio to sandboxed processes, so files are
+        // opened inside sandbox by shell, which then execs checker
+        cmd.path(CHECKER_LAUNCHER_SHELL);
+        let mut args: Vec<OsString> = vec![
+            "-c".into(),
+            CHECKER_LAUNCHER_SCRIPT.into(),
+            full_checker_path.into(),
+        ];
+        args.extend(self.req.problem.checker_cmd.iter().map(Into::into));
+        cmd.args(&args);
         cmd.current_dir(&self.req.problem_dir);
+        cmd.envs(&[
+            "JJS_TEST=3",
+            "JJS_CORR=4",
+            "JJS_SOL=5",
+            "JJS_CHECKER_OUT=6",
+            "JJS_CHECKER_COMMENT=7",
+        ]);
+        let stdout_path = check_dir.join("stdout.txt");
+        let stderr_path = check_dir.join("stderr.txt");
+        invoke_util::command_set_stdio(&mut cmd);
+        cmd.sandbox(sandbox.sandbox.clone());
 
-        for arg in &self.req.problem.checker_cmd {
-            cmd.arg(arg);
-        }
-
-        let test_cfg = self.exec.test;
-
-        let corr_handle = if let Some(corr_path) = &test_cfg.correct {
-            let full_path = self.req.resolve_asset(corr_path);
-            let data = fs::read(full_path).context("failed to read correct answer")?;
-            os_util::buffer_to_file(&data, "invoker-correct-data")
-        } else {
-            os_util::buffer_to_file(&[], "invoker-correct-data")
+        let mut child = cmd
+            .spawn(&**self.minion)
+            .context("failed to spawn checker")?;
+        let stdout = invoke_util::CappedOutput::start(
+            child.stdout().context("checker stdout was not captured")?,
+            &stdout_path,
+            limits.output_limit(),
+            sandbox.sandbox.clone(),
+        )?;
+        let stderr = invoke_util::CappedOutput::start(
+            child.stderr().context("checker stderr was not captured")?,
+            &stderr_path,
+            limits.output_limit(),
+            sandbox.sandbox.clone(),
+        )?;
+        let wait_result = child
+            .wait_for_exit(None)
+            .context("failed to wait for checker")?;
+        let resource_usage = sandbox
+            .sandbox
+            .resource_usage()
+            .context("cannot get resource usage")?;
+        sandbox.sandbox.kill().ok();
+        let stdout_overflow = stdout.finish()?;
+        let stderr_overflow = stderr.finish()?;
+        let fault = match wait_result {
+            _ if stdout_overflow || stderr_overflow => Some(format!(
+                "checker exceeded output limit ({} bytes)",
+                limits.output_limit()
+            )),
+            minion::WaitOutcome::Timeout => Some(format!(
+                "checker exceeded time limit ({} ms)",
+                limits.time()
+            )),
+            minion::WaitOutcome::AlreadyFinished => unreachable!("not expected other to wait"),
+            minion::WaitOutcome::Exited => {
+                let exit_code = child
+                    .get_exit_code()
+                    .context("failed to get exit code")?
+                    .unwrap();
+                if exit_code == 0 {
+                    None
+                } else if resource_usage
+                    .memory
+                    .map_or(false, |m| m >= limits.memory())
+                {
+                    Some(format!(
+                        "checker exceeded memory limit ({} bytes)",
+                        limits.memory()
+                    ))
+                } else {
+                    Some(format!("checker exited with code {}", exit_code))
+                }
+            }
         };
-        let test_handle = os_util::buffer_to_file(&test_data, "invoker-test-data");
-
-        cmd.env("JJS_CORR", corr_handle.to_string());
-        cmd.env("JJS_SOL", sol_handle.to_string());
-        cmd.env("JJS_TEST", test_handle.to_string());
-
-        let (out_judge_side, out_checker_side) = os_util::make_pipe();
-        cmd.env("JJS_CHECKER_OUT", out_checker_side.to_string());
-        let (comments_judge_side, comments_checker_side) = os_util::make_pipe();
-        cmd.env("JJS_CHECKER_COMMENT", comments_checker_side.to_string());
-        let st = cmd.output().context("failed to execute checker")?;
-        os_util::close(out_checker_side);
-        os_util::close(comments_checker_side);
-        os_util::close(corr_handle);
-        os_util::close(test_handle);
-        os_util::close(sol_handle);
-        // TODO: capture comments
-        os_util::close(comments_judge_side);
-
-        let checker_out = std::fs::File::create(step_dir.join("check-log.txt"))?;
-        let mut checker_out = std::io::BufWriter::new(checker_out);
-        checker_out.write_all(b" --- stdout ---\n")?;
-        checker_out.write_all(&st.stdout)?;
-        checker_out.write_all(b"--- stderr ---\n")?;
-        checker_out.write_all(&st.stderr)?;
-        let return_value_for_judge_fault = Ok(ExecOutcome {
-            status: Status {
-                kind: StatusKind::InternalError,
-                code: status_codes::JUDGE_FAULT.to_string(),
+        // missing files are reported by checker_proto::parse
+        let checker_out = fs::read(data_dir.join("checker-out.txt")).unwrap_or_default();
+        let comment = fs::read(data_dir.join("comment.txt")).unwrap_or_default();
+        let result = match fault {
+            Some(message) => Err(message),
+            None => match String::from_utf8(checker_out) {
+                Ok(checker_out) => checker_proto::parse(&checker_out)
+                    .map_err(|err| format!("checker output couldn't be parsed: {:#}", err)),
+                Err(_) => Err("checker produced non-utf8 output".to_string()),
             },
-            resource_usage: Default::default(),
-        });
+        };
 
-        let succ = st.status.success();
-        if !succ {
-            error!("Judge fault: checker returned non-zero: {}", st.status);
-            os_util::close(out_judge_side);
-            return return_value_for_judge_fault;
+        let check_log = fs::File::create(self.req.step_dir(Some(test_id)).join("check-log.txt"))?;
+        let mut check_log = std::io::BufWriter::new(check_log);
+        check_log.write_all(b"--- stdout ---\n")?;
+        check_log.write_all(&fs::read(&stdout_path).unwrap_or_default())?;
+        check_log.write_all(b"--- stderr ---\n")?;
+        check_log.write_all(&fs::read(&stderr_path).unwrap_or_default())?;
+        check_log.write_all(b"--- comment ---\n")?;
+        check_log.write_all(&comment)?;
+        if let Err(message) = &result {
+            writeln!(check_log, "--- judge fault ---\n{}", message)?;
         }
-        let checker_out = match String::from_utf8(os_util::handle_read_all(out_judge_side)) {
-            Ok(c) => c,
-            Err(_) => {
-                error!("checker produced non-utf8 output");
-                return return_value_for_judge_fault;
+        check_log.flush()?;
+        Ok(result)
+    }
+
+    pub fn exec(self) -> anyhow::Result<ExecOutcome> {
+        let input_file = self.req.resolve_asset(&self.exec.test.path);
+        let test_data = std::fs::read(input_file).context("failed to read test")?;
+        let run_outcome = match self.req.problem.kind {
+            pom::ProblemKind::Standard => self.run_solution(&test_data, self.exec.test_id)?,
+            pom::ProblemKind::OutputOnly => self.take_answer()?,
+        };
+        let sol_file_path = match run_outcome.var {
+            RunOutcomeVar::Success { out_data_path } => out_data_path,
+            RunOutcomeVar::Fail(status) => {
+                return Ok(ExecOutcome {
+                    status,
+                    resource_usage: run_outcome.resource_usage,
+                });
             }
         };
-        let parsed_out = match checker_proto::parse(&checker_out) {
-            Ok(o) => o,
-            Err(err) => {
-                error!("checker output couldn't be parsed: {}", err);
-                return return_value_for_judge_fault;
+        let status = match self.run_checker(&test_data, &sol_file_path)? {
+            Ok(checker_out) => map_checker_outcome_to_status(checker_out),
+            Err(message) => {
+                error!("Judge fault on test {}: {}", self.exec.test_id, message);
+                Status {
+                    kind: StatusKind::InternalError,
+                    code: status_codes::JUDGE_FAULT.to_string(),
+                }
             }
         };
 
-        let status = map_checker_outcome_to_status(parsed_out);
-
         Ok(ExecOutcome {
             status,
             resource_usage: run_outcome.resource_usage,
         })
     }
+
+    /// Runs solution on custom input, without checker.
+    /// Solution output is returned as is.
+    pub fn exec_custom(self) -> anyhow::Result<invoker_api::CustomRunResult> {
+        let input_file = self.req.resolve_asset(&self.exec.test.path);
+        let test_data = std::fs::read(input_file).context("failed to read custom input")?;
+        let run_outcome = self.run_solution(&test_data, self.exec.test_id)?;
+        let status = match run_outcome.var {
+            RunOutcomeVar::Success { .. } => Status {
+                kind: StatusKind::Accepted,
+                code: status_codes::RUN_FINISHED.to_string(),
+            },
+            RunOutcomeVar::Fail(status) => status,
+        };
+        let step_dir = self.req.step_dir(Some(self.exec.test_id));
+        // output files are missing if solution was not launched
+        let stdout = fs::read(step_dir.join("stdout.txt")).unwrap_or_default();
+        let stderr = fs::read(step_dir.join("stderr.txt")).unwrap_or_default();
+        Ok(invoker_api::CustomRunResult {
+            status,
+            exit_code: run_outcome.exit_code,
+            stdout: base64::encode(&stdout),
+            stderr: base64::encode(&stderr),
+            time_usage: run_outcome.resource_usage.time,
+            memory_usage: run_outcome.resource_usage.memory,
+        })
+    }
 }
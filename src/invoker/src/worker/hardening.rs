@@ -0,0 +1,288 @@
+//! Sandbox hardening: network isolation and syscall filtering.
+//!
+//! Minion does not support these settings, so they are applied to a helper
+//! thread, which then creates the sandbox. Network namespace and seccomp
+//! filter are per-thread attributes, and they are inherited by minion zygote
+//! (which is forked by this thread) and by all processes it spawns. This is
+//! verified after sandbox is created.
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub struct SandboxSettings {
+    /// Run sandboxed processes in new network namespace, which has no
+    /// interfaces except loopback
+    #[serde(default = "SandboxSettings::default_isolate_network")]
+    pub isolate_network: bool,
+    /// Syscall filter. If not set, all syscalls are allowed.
+    #[serde(default)]
+    pub seccomp: Option<SeccompProfile>,
+    /// Toolchain runs programs through shell wrapper, which reports death by
+    /// signal N as exit code 128 + N
+    #[serde(default)]
+    pub shell_wrapped: bool,
+}
+
+impl SandboxSettings {
+    fn default_isolate_network() -> bool {
+        true
+    }
+}
+
+impl Default for SandboxSettings {
+    fn default() -> Self {
+        SandboxSettings {
+            isolate_network: SandboxSettings::default_isolate_network(),
+            seccomp: None,
+            shell_wrapped: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SeccompProfile {
+    /// Denies syscalls which can be used to inspect or modify kernel or
+    /// other processes, and sockets other than Unix domain sockets
+    Default,
+    /// Same as `default`, but allows IP sockets: JVM creates them on startup
+    /// (there is still no network if it is isolated)
+    Jvm,
+    /// Same as `default`, but denies all sockets
+    Strict,
+}
+
+/// Syscalls which are denied by all profiles
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_userfaultfd,
+    libc::SYS_kexec_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_acct,
+    SYS_IO_URING_SETUP,
+    SYS_IO_URING_ENTER,
+    SYS_IO_URING_REGISTER,
+];
+
+// io_uring can perform operations (e.g. create sockets) without syscalls,
+// bypassing the filter. Numbers are the same on all architectures.
+const SYS_IO_URING_SETUP: libc::c_long = 425;
+const SYS_IO_URING_ENTER: libc::c_long = 426;
+const SYS_IO_URING_REGISTER: libc::c_long = 427;
+
+enum SocketPolicy {
+    AllowAll,
+    AllowUnix,
+    DenyAll,
+}
+
+impl SeccompProfile {
+    fn socket_policy(self) -> SocketPolicy {
+        match self {
+            SeccompProfile::Default => SocketPolicy::AllowUnix,
+            SeccompProfile::Jvm => SocketPolicy::AllowAll,
+            SeccompProfile::Strict => SocketPolicy::DenyAll,
+        }
+    }
+}
+
+// see linux/seccomp.h, linux/audit.h and linux/bpf_common.h
+const SECCOMP_SET_MODE_FILTER: libc::c_ulong = 1;
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xc000_00b7;
+/// BPF_LD | BPF_W | BPF_ABS
+const BPF_LOAD: u16 = 0x20;
+/// BPF_JMP | BPF_JEQ | BPF_K
+const BPF_JUMP_EQ: u16 = 0x15;
+/// BPF_JMP | BPF_JGE | BPF_K
+const BPF_JUMP_GE: u16 = 0x35;
+/// Set in numbers of x32 ABI syscalls, which share AUDIT_ARCH_X86_64
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+/// BPF_RET | BPF_K
+const BPF_RETURN: u16 = 0x06;
+// offsets in `struct seccomp_data`
+const OFFSET_NR: u32 = 0;
+const OFFSET_ARCH: u32 = 4;
+/// Low half of first argument (little-endian)
+const OFFSET_ARG0: u32 = 16;
+
+fn stmt(code: u16, k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn jump_eq(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter {
+        code: BPF_JUMP_EQ,
+        jt,
+        jf,
+        k,
+    }
+}
+
+fn jump_ge(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter {
+        code: BPF_JUMP_GE,
+        jt,
+        jf,
+        k,
+    }
+}
+
+/// Builds BPF program, which kills process on denied syscall
+fn build_filter(profile: SeccompProfile) -> Vec<libc::sock_filter> {
+    let mut prog = vec![
+        stmt(BPF_LOAD, OFFSET_ARCH),
+        jump_eq(AUDIT_ARCH_CURRENT, 1, 0),
+        stmt(BPF_RETURN, SECCOMP_RET_KILL_PROCESS),
+        stmt(BPF_LOAD, OFFSET_NR),
+        // otherwise denied syscalls could be called by their x32 numbers
+        jump_ge(X32_SYSCALL_BIT, 0, 1),
+        stmt(BPF_RETURN, SECCOMP_RET_KILL_PROCESS),
+    ];
+    for &nr in DENIED_SYSCALLS {
+        prog.push(jump_eq(nr as u32, 0, 1));
+        prog.push(stmt(BPF_RETURN, SECCOMP_RET_KILL_PROCESS));
+    }
+    match profile.socket_policy() {
+        SocketPolicy::AllowAll => {}
+        SocketPolicy::AllowUnix => {
+            prog.push(jump_eq(libc::SYS_socket as u32, 0, 4));
+            prog.push(stmt(BPF_LOAD, OFFSET_ARG0));
+            prog.push(jump_eq(libc::AF_UNIX as u32, 0, 1));
+            prog.push(stmt(BPF_RETURN, SECCOMP_RET_ALLOW));
+            prog.push(stmt(BPF_RETURN, SECCOMP_RET_KILL_PROCESS));
+        }
+        SocketPolicy::DenyAll => {
+            prog.push(jump_eq(libc::SYS_socket as u32, 0, 1));
+            prog.push(stmt(BPF_RETURN, SECCOMP_RET_KILL_PROCESS));
+        }
+    }
+    prog.push(stmt(BPF_RETURN, SECCOMP_RET_ALLOW));
+    prog
+}
+
+/// Installs seccomp filter for current thread
+fn install_filter(profile: SeccompProfile) -> anyhow::Result<()> {
+    let mut filter = build_filter(profile);
+    let prog = libc::sock_fprog {
+        len: filter.len() as libc::c_ushort,
+        filter: filter.as_mut_ptr(),
+    };
+    // Safety: `prog` points to valid filter, which outlives syscall
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(std::io::Error::last_os_error()).context("failed to set no_new_privs");
+        }
+        if libc::syscall(
+            libc::SYS_seccomp,
+            SECCOMP_SET_MODE_FILTER,
+            0,
+            &prog as *const libc::sock_fprog,
+        ) != 0
+        {
+            return Err(std::io::Error::last_os_error()).context("failed to install filter");
+        }
+    }
+    Ok(())
+}
+
+/// Creates sandbox with `settings` applied
+pub(crate) fn new_sandbox(
+    backend: &Arc<dyn minion::erased::Backend>,
+    options: minion::SandboxOptions,
+    settings: SandboxSettings,
+) -> anyhow::Result<Box<dyn minion::erased::Sandbox>> {
+    let backend = backend.clone();
+    let helper = std::thread::spawn(move || {
+        if settings.isolate_network {
+            nix::sched::unshare(nix::sched::CloneFlags::CLONE_NEWNET)
+                .context("failed to unshare network namespace")?;
+        }
+        if let Some(profile) = settings.seccomp {
+            install_filter(profile)
+                .with_context(|| format!("failed to apply seccomp profile {:?}", profile))?;
+        }
+        let sandbox = backend
+            .new_sandbox(options)
+            .context("failed to create minion dominion")?;
+        if settings.isolate_network || settings.seccomp.is_some() {
+            verify_inherited(settings).context("sandbox is not hardened")?;
+        }
+        Ok(sandbox)
+    });
+    match helper.join() {
+        Ok(res) => res,
+        Err(_) => bail!("sandbox creation thread panicked"),
+    }
+}
+
+/// Checks that processes forked by current thread (i.e. minion zygote) have
+/// the same network namespace, and seccomp filter if it is enabled
+fn verify_inherited(settings: SandboxSettings) -> anyhow::Result<()> {
+    let tid = nix::unistd::gettid();
+    let children_path = format!("/proc/self/task/{}/children", tid);
+    let children = std::fs::read_to_string(&children_path)
+        .with_context(|| format!("failed to read {}", children_path))?;
+    let children: Vec<&str> = children.split_whitespace().collect();
+    if children.is_empty() {
+        bail!("minion zygote was not forked by sandbox creation thread");
+    }
+    let net_ns = std::fs::read_link(format!("/proc/self/task/{}/ns/net", tid))
+        .context("failed to get network namespace")?;
+    for pid in children {
+        if settings.isolate_network {
+            let child_net_ns = std::fs::read_link(format!("/proc/{}/ns/net", pid))
+                .context("failed to get zygote network namespace")?;
+            if child_net_ns != net_ns {
+                bail!("zygote {} is not in isolated network namespace", pid);
+            }
+        }
+        if settings.seccomp.is_some() {
+            let status = std::fs::read_to_string(format!("/proc/{}/status", pid))
+                .context("failed to read zygote status")?;
+            // 2 means SECCOMP_MODE_FILTER
+            let is_filtered = status
+                .lines()
+                .any(|line| line.strip_prefix("Seccomp:").map(str::trim) == Some("2"));
+            if !is_filtered {
+                bail!("zygote {} does not have seccomp filter", pid);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks if process was killed by seccomp filter, i.e. with SIGSYS.
+/// Without filter, SIGSYS means something else, so it is not a violation.
+pub(crate) fn is_security_violation(exit_code: i64, settings: &SandboxSettings) -> bool {
+    if settings.seccomp.is_none() {
+        return false;
+    }
+    let signal = i64::from(libc::SIGSYS);
+    // minion reports death by signal as negated signal number. 128 + signal
+    // is also a valid exit code, so it is trusted only for shell wrappers.
+    exit_code == -signal || (settings.shell_wrapped && exit_code == 128 + signal)
+}
@@ -1,8 +1,10 @@
-use crate::worker::{Command, LoweredJudgeRequest};
+use crate::worker::{hardening, Command, LoweredJudgeRequest};
 use anyhow::Context;
 use std::{
     fs,
+    io::{Read, Write},
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 use tracing::{debug, error};
@@ -35,28 +37,34 @@ static DEFAULT_HOST_MOUNTS: once_cell::sync::Lazy<Vec<String>> = once_cell::sync
     ]
 });
 
+/// Returns host directories, which should be exposed into sandbox
+fn host_mounts(config: &crate::config::InvokerConfig) -> Vec<minion::SharedDir> {
+    let dirs = config
+        .expose_host_dirs
+        .as_ref()
+        .unwrap_or_else(|| &*DEFAULT_HOST_MOUNTS);
+    dirs.iter()
+        .map(|item| {
+            let item = format!("/{}", item);
+            minion::SharedDir {
+                src: item.clone().into(),
+                dest: item.into(),
+                kind: minion::SharedDirKind::Readonly,
+            }
+        })
+        .collect()
+}
+
 pub(crate) fn create_sandbox(
     req: &LoweredJudgeRequest,
     test_id: Option<u32>,
-    backend: &dyn minion::erased::Backend,
+    backend: &Arc<dyn minion::erased::Backend>,
     config: &crate::config::InvokerConfig,
 ) -> anyhow::Result<Sandbox> {
-    let mut shared_dirs = vec![];
-    if config.host_toolchains {
-        let dirs = config
-            .expose_host_dirs
-            .as_ref()
-            .unwrap_or_else(|| &*DEFAULT_HOST_MOUNTS);
-        for item in dirs {
-            let item = format!("/{}", item);
-            let shared_dir = minion::SharedDir {
-                src: item.clone().into(),
-                dest: item.into(),
-                kind: minion::SharedDirKind::Readonly,
-            };
-            shared_dirs.push(shared_dir)
-        }
+    let shared_dirs = if config.host_toolchains {
+        host_mounts(config)
     } else {
+        let mut shared_dirs = vec![];
         let toolchain_dir = &req.toolchain_dir;
         let opt_items =
             fs::read_dir(&toolchain_dir).context("failed to list toolchains sysroot")?;
@@ -70,7 +78,8 @@ pub(crate) fn create_sandbox(
             };
             shared_dirs.push(shared_dir)
         }
-    }
+        shared_dirs
+    };
 
     let limits = if let Some(test_id) = test_id {
         req.problem.tests[(test_id - 1) as usize].limits
@@ -78,12 +87,105 @@ pub(crate) fn create_sandbox(
         req.compile_limits
     };
     let out_dir = req.step_dir(test_id);
+    create_sandbox_in(
+        &out_dir,
+        limits,
+        limits.work_dir_size(),
+        Duration::from_millis(limits.real_time()),
+        shared_dirs,
+        req.sandbox_settings,
+        backend,
+    )
+}
+
+/// Creates sandbox for running checker on test `test_id`.
+/// Checker work dir is `check` subdirectory of test step dir. Its `data`
+/// directory is exposed as `/jjs`, and caller should place checker input files
+/// there; `data_size` is total size of these files.
+/// Checkers are built on host, so host directories are always exposed. Problem
+/// directory is exposed read-only at the same path.
+pub(crate) fn create_checker_sandbox(
+    req: &LoweredJudgeRequest,
+    test_id: u32,
+    data_size: u64,
+    backend: &Arc<dyn minion::erased::Backend>,
+    config: &crate::config::InvokerConfig,
+) -> anyhow::Result<Sandbox> {
+    let mut shared_dirs = host_mounts(config);
+    shared_dirs.push(minion::SharedDir {
+        src: req.problem_dir.clone(),
+        dest: req.problem_dir.clone(),
+        kind: minion::SharedDirKind::Readonly,
+    });
+    let checker_exe = &req.problem.checker_exe;
+    if let pom::FileRefRoot::Root = checker_exe.root {
+        let checker_dir = req
+            .resolve_asset(checker_exe)
+            .parent()
+            .context("checker path has no parent")?
+            .to_path_buf();
+        shared_dirs.push(minion::SharedDir {
+            src: checker_dir.clone(),
+            dest: checker_dir,
+            kind: minion::SharedDirKind::Readonly,
+        });
+    }
+    let limits = req.problem.checker_limits;
+    let check_dir = req.step_dir(Some(test_id)).join("check");
+    create_sandbox_in(
+        &check_dir,
+        limits,
+        limits.work_dir_size() + data_size,
+        Duration::from_millis(limits.real_time()),
+        shared_dirs,
+        hardening::SandboxSettings::default(),
+        backend,
+    )
+}
+
+/// Creates sandbox for valuer in `valuer` subdirectory of `req.out_dir`.
+/// Problem directory is exposed read-only at the same path.
+/// Valuer real time limit is wall-clock budget of judging session.
+pub(crate) fn create_valuer_sandbox(
+    req: &LoweredJudgeRequest,
+    backend: &Arc<dyn minion::erased::Backend>,
+    config: &crate::config::InvokerConfig,
+) -> anyhow::Result<Sandbox> {
+    let mut shared_dirs = host_mounts(config);
+    shared_dirs.push(minion::SharedDir {
+        src: req.problem_dir.clone(),
+        dest: req.problem_dir.clone(),
+        kind: minion::SharedDirKind::Readonly,
+    });
+    let limits = req.problem.valuer_limits;
+    create_sandbox_in(
+        &req.out_dir.join("valuer"),
+        limits,
+        limits.work_dir_size(),
+        Duration::from_millis(req.problem.valuer_budget()),
+        shared_dirs,
+        hardening::SandboxSettings::default(),
+        backend,
+    )
+}
+
+/// Creates sandbox in `out_dir`. `out_dir/data` becomes tmpfs of size
+/// `work_dir_size`, which is exposed as `/jjs`.
+/// Sandbox is hardened according to `settings`.
+fn create_sandbox_in(
+    out_dir: &Path,
+    limits: pom::Limits,
+    work_dir_size: u64,
+    real_time_limit: Duration,
+    mut shared_dirs: Vec<minion::SharedDir>,
+    settings: hardening::SandboxSettings,
+    backend: &Arc<dyn minion::erased::Backend>,
+) -> anyhow::Result<Sandbox> {
     std::fs::create_dir_all(&out_dir).context("failed to create step directory")?;
     let umount_path;
     #[cfg(target_os = "linux")]
     {
-        let quota = limits.work_dir_size();
-        let quota = minion::linux::ext::Quota::bytes(quota);
+        let quota = minion::linux::ext::Quota::bytes(work_dir_size);
         minion::linux::ext::make_tmpfs(&out_dir.join("data"), quota)
             .context("failed to set size limit on shared directory")?;
         umount_path = Some(out_dir.join("data"));
@@ -98,7 +200,6 @@ pub(crate) fn create_sandbox(
         kind: minion::SharedDirKind::Full,
     });
     let cpu_time_limit = Duration::from_millis(limits.time() as u64);
-    let real_time_limit = Duration::from_millis(limits.time() * 3 as u64);
     std::fs::create_dir(out_dir.join("root")).context("failed to create chroot dir")?;
     // TODO adjust integer types
     let sandbox_options = minion::SandboxOptions {
@@ -109,9 +210,7 @@ pub(crate) fn create_sandbox(
         cpu_time_limit,
         real_time_limit,
     };
-    let sandbox = backend
-        .new_sandbox(sandbox_options)
-        .context("failed to create minion dominion")?;
+    let sandbox = hardening::new_sandbox(backend, sandbox_options, settings)?;
     Ok(Sandbox {
         sandbox,
         umount: umount_path,
@@ -128,14 +227,65 @@ pub(crate) fn command_set_from_judge_req(cmd: &mut minion::Command, command: &Co
     cmd.envs(&command.env);
 }
 
-pub(crate) fn command_set_stdio(cmd: &mut minion::Command, stdout_path: &Path, stderr_path: &Path) {
-    let stdout_file = fs::File::create(stdout_path).expect("io error");
+/// Configures command to pass stdout and stderr through invoker.
+/// Use `CappedOutput` to save them.
+pub(crate) fn command_set_stdio(cmd: &mut minion::Command) {
+    cmd.stdout(minion::OutputSpecification::pipe());
+    cmd.stderr(minion::OutputSpecification::pipe());
+}
+
+/// Copies child output stream to file, writing at most `limit` bytes.
+/// If child produces more output, sandbox is killed, so that it can not
+/// fill invoker disk.
+pub(crate) struct CappedOutput {
+    handle: std::thread::JoinHandle<std::io::Result<bool>>,
+    /// Disconnected when copying is done
+    done: std::sync::mpsc::Receiver<()>,
+}
+
+impl CappedOutput {
+    pub(crate) fn start(
+        mut stream: Box<dyn Read + Send + Sync>,
+        path: &Path,
+        limit: u64,
+        sandbox: Box<dyn minion::erased::Sandbox>,
+    ) -> anyhow::Result<CappedOutput> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        let (done_tx, done) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let _done_tx = done_tx;
+            let mut writer = std::io::BufWriter::new(file);
+            let copied = std::io::copy(&mut (&mut stream).take(limit), &mut writer)?;
+            writer.flush()?;
+            let mut overflow = false;
+            if copied == limit {
+                let mut buf = [0; 1];
+                if stream.read(&mut buf)? != 0 {
+                    overflow = true;
+                    sandbox.kill().ok();
+                }
+            }
+            Ok(overflow)
+        });
+        Ok(CappedOutput { handle, done })
+    }
 
-    let stderr_file = fs::File::create(stderr_path).expect("io error");
-    // Safety: std::fs::File owns it's handle
-    unsafe {
-        cmd.stdout(minion::OutputSpecification::handle_of(stdout_file));
+    /// Waits at most `timeout` until stream is closed. Returns false if
+    /// stream is still open, e.g. because it is inherited by background
+    /// process.
+    pub(crate) fn wait_closed(&self, timeout: Duration) -> bool {
+        match self.done.recv_timeout(timeout) {
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => false,
+            Ok(()) | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => true,
+        }
+    }
 
-        cmd.stderr(minion::OutputSpecification::handle_of(stderr_file));
+    /// Waits until stream is closed. Returns true if limit was exceeded.
+    pub(crate) fn finish(self) -> anyhow::Result<bool> {
+        self.handle
+            .join()
+            .expect("output copying thread panicked")
+            .context("failed to copy child output")
     }
 }
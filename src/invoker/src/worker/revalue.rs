@@ -0,0 +1,78 @@
+//! Implements re-valuation, i.e. valuing results of previous judging
+//! with fresh valuer.
+use crate::worker::{exec_test::ExecOutcome, LoweredJudgeRequest};
+use anyhow::Context;
+use invoker_api::{judge_log::JudgeLog, status_codes, Status};
+use std::{collections::HashMap, path::Path};
+
+/// Returns build status, if previous judging has not reached testing
+/// (i.e. run was not compiled). Such status is preserved by re-valuation.
+pub(super) fn previous_build_error(previous: &JudgeLog) -> Option<&Status> {
+    let code = previous.status.code.as_str();
+    if code == status_codes::ACCEPTED
+        || code == status_codes::PARTIAL_SOLUTION
+        || code == status_codes::JUDGE_FAULT
+    {
+        None
+    } else {
+        Some(&previous.status)
+    }
+}
+
+fn write_base64(path: &Path, data: Option<&str>) -> anyhow::Result<()> {
+    let data = match data {
+        Some(data) => base64::decode(data).context("judge log contains invalid base64")?,
+        None => Vec::new(),
+    };
+    std::fs::write(path, &data).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Restores files in `req.out_dir`, which were produced by previous judging
+/// and are read when judge logs are created.
+/// Returns outcomes of tests which were run previously. Tests without
+/// stored status are considered never run.
+pub(super) fn restore_previous_results(
+    req: &LoweredJudgeRequest,
+    previous: &JudgeLog,
+) -> anyhow::Result<HashMap<pom::TestId, ExecOutcome>> {
+    let compile_dir = req.step_dir(None);
+    std::fs::create_dir_all(&compile_dir).context("failed to create compile dir")?;
+    write_base64(
+        &compile_dir.join("stdout-0.txt"),
+        Some(&previous.compile_stdout),
+    )?;
+    write_base64(
+        &compile_dir.join("stderr-0.txt"),
+        Some(&previous.compile_stderr),
+    )?;
+
+    let mut outcomes = HashMap::new();
+    for row in &previous.tests {
+        let status = match &row.status {
+            Some(status) => status.clone(),
+            None => continue,
+        };
+        if row.test_id.to_idx() >= req.problem.tests.len() {
+            anyhow::bail!("previous judge log contains unknown test {}", row.test_id);
+        }
+        let step_dir = req.step_dir(Some(row.test_id.get()));
+        std::fs::create_dir_all(&step_dir).context("failed to create test dir")?;
+        write_base64(&step_dir.join("stdout.txt"), row.test_stdout.as_deref())?;
+        write_base64(&step_dir.join("stderr.txt"), row.test_stderr.as_deref())?;
+        if let Some(checker_log) = &row.checker_log {
+            std::fs::write(step_dir.join("check-log.txt"), checker_log)
+                .context("failed to write checker log")?;
+        }
+        outcomes.insert(
+            row.test_id,
+            ExecOutcome {
+                status,
+                resource_usage: minion::ResourceUsageData {
+                    time: row.time_usage,
+                    memory: row.memory_usage,
+                },
+            },
+        );
+    }
+    Ok(outcomes)
+}
@@ -74,6 +74,9 @@ impl Worker {
                     status: None,
                     time_usage: None,
                     memory_usage: None,
+                    checker_log: None,
+                    time_limit: None,
+                    memory_limit: None,
                 };
                 let test_local_dir = req.step_dir(Some(item.test_id.get()));
                 if item.components.contains(TestVisibleComponents::TEST_DATA) {
@@ -109,6 +112,18 @@ impl Worker {
                 if item.components.contains(TestVisibleComponents::STATUS) {
                     new_item.status = Some(item.status.clone());
                 }
+                if let invoker_api::judge_log::JudgeLogKind::Full = valuer_log.kind {
+                    let check_log_file = test_local_dir.join("check-log.txt");
+                    if check_log_file.exists() {
+                        let check_log =
+                            std::fs::read(check_log_file).context("failed to read checker log")?;
+                        new_item.checker_log =
+                            Some(String::from_utf8_lossy(&check_log).into_owned());
+                    }
+                }
+                let limits = req.problem.tests[item.test_id].limits;
+                new_item.time_limit = Some(limits.time());
+                new_item.memory_limit = Some(limits.memory());
                 if let Some(resource_usage) = resource_usage_by_test.get(&item.test_id) {
                     if item
                         .components
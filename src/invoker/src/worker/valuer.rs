@@ -1,26 +1,48 @@
-use crate::worker::LoweredJudgeRequest;
+use crate::worker::{invoke_util, LoweredJudgeRequest};
 use anyhow::{bail, Context};
 use invoker_api::valuer_proto::{ProblemInfo, TestDoneNotification, ValuerResponse};
-use std::os::unix::io::IntoRawFd;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use std::{
+    io::{BufRead, Write},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
 use tracing::warn;
+
+/// Maximal time valuer can spend on single response
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Valuer, running in minion sandbox.
+/// Minion provides blocking pipes, so they are served by helper threads.
 pub(crate) struct Valuer {
-    stdin: BufWriter<tokio::process::ChildStdin>,
-    stdout: BufReader<tokio::process::ChildStdout>,
-    // ties lifetime of valuer instance to `Valuer` lifetime
-    _child: tokio::process::Child,
+    /// Messages which should be written to valuer stdin
+    requests: std::sync::mpsc::Sender<String>,
+    /// Lines, read from valuer stdout
+    responses: mpsc::UnboundedReceiver<std::io::Result<String>>,
+    /// Wall-clock budget for judging session
+    budget: Duration,
+    started_at: Instant,
+    sandbox: invoke_util::Sandbox,
+}
+
+impl Drop for Valuer {
+    fn drop(&mut self) {
+        self.sandbox.sandbox.kill().ok();
+    }
 }
 
 impl Valuer {
-    pub(crate) fn new(req: &LoweredJudgeRequest) -> anyhow::Result<Valuer> {
+    pub(crate) fn new(
+        req: &LoweredJudgeRequest,
+        minion: &Arc<dyn minion::erased::Backend>,
+        config: &crate::config::InvokerConfig,
+    ) -> anyhow::Result<Valuer> {
+        let sandbox = invoke_util::create_valuer_sandbox(req, minion, config)
+            .context("failed to create valuer sandbox")?;
         let valuer_exe = req.resolve_asset(&req.problem.valuer_exe);
-        let mut cmd = tokio::process::Command::new(&valuer_exe);
-        cmd.kill_on_drop(true);
-        cmd.stdin(std::process::Stdio::piped());
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::inherit());
-        cmd.env("JJS_VALUER", "1");
-        cmd.env("RUST_LOG", "info,svaluer=debug");
+        let mut cmd = minion::Command::new();
+        cmd.path(&valuer_exe);
+        cmd.envs(&["JJS_VALUER=1", "RUST_LOG=info,svaluer=debug"]);
         let work_dir = req.resolve_asset(&req.problem.valuer_cfg);
         if work_dir.exists() {
             cmd.current_dir(&work_dir);
@@ -30,32 +52,75 @@ impl Valuer {
                 work_dir.display()
             );
         }
+        cmd.stdin(minion::InputSpecification::pipe());
+        cmd.stdout(minion::OutputSpecification::pipe());
         let log = std::fs::File::create(req.out_dir.join("valuer-log.txt"))
-            .context("failed to create valuer log file")?
-            .into_raw_fd();
+            .context("failed to create valuer log file")?;
+        // Safety: std::fs::File owns it's handle
         unsafe {
-            cmd.pre_exec(move || {
-                nix::unistd::dup3(log, libc::STDERR_FILENO, nix::fcntl::OFlag::empty())
-                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-                Ok(())
-            });
+            cmd.stderr(minion::OutputSpecification::handle_of(log));
         }
-        let mut child = cmd.spawn().with_context(|| {
+        cmd.sandbox(sandbox.sandbox.clone());
+        let mut child = cmd.spawn(&**minion).with_context(|| {
             format!(
                 "failed to spawn valuer {} (requested current dir {})",
                 valuer_exe.display(),
                 work_dir.display()
             )
         })?;
-        let stdin = child.stdin.take().unwrap();
-        let stdout = child.stdout.take().unwrap();
-        let val = Valuer {
-            stdin: BufWriter::new(stdin),
-            stdout: BufReader::new(stdout),
-            _child: child,
-        };
+        let mut stdin = child.stdin().context("valuer stdin was not captured")?;
+        let stdout = child.stdout().context("valuer stdout was not captured")?;
+
+        let (requests_tx, requests_rx) = std::sync::mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for msg in requests_rx {
+                if stdin
+                    .write_all(msg.as_bytes())
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    // valuer exited; it will be detected when reading response
+                    break;
+                }
+            }
+        });
+        let (responses_tx, responses_rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let mut stdout = std::io::BufReader::new(stdout);
+            loop {
+                let mut line = String::new();
+                match stdout.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if responses_tx.send(Ok(line)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        responses_tx.send(Err(err)).ok();
+                        break;
+                    }
+                }
+            }
+        });
 
-        Ok(val)
+        Ok(Valuer {
+            requests: requests_tx,
+            responses: responses_rx,
+            budget: Duration::from_millis(req.problem.valuer_budget()),
+            started_at: Instant::now(),
+            sandbox,
+        })
+    }
+
+    fn check_budget(&self) -> anyhow::Result<()> {
+        if self.started_at.elapsed() >= self.budget {
+            bail!(
+                "valuer exceeded wall-clock budget of {} ms",
+                self.budget.as_millis()
+            );
+        }
+        Ok(())
     }
 
     async fn write_val(&mut self, msg: impl serde::Serialize) -> anyhow::Result<()> {
@@ -64,14 +129,9 @@ impl Valuer {
             bail!("bug: serialized message is not oneline");
         }
         msg.push('\n');
-        self.stdin
-            .write_all(msg.as_bytes())
-            .await
-            .context("failed to write message")?;
-        self.stdin
-            .flush()
-            .await
-            .context("failed to flush valuer stdin")?;
+        if self.requests.send(msg).is_err() {
+            bail!("failed to write message: valuer closed its input");
+        }
         Ok(())
     }
 
@@ -91,16 +151,23 @@ impl Valuer {
     }
 
     pub(crate) async fn poll(&mut self) -> anyhow::Result<ValuerResponse> {
-        let mut line = String::new();
-        let read_line_fut = self.stdout.read_line(&mut line);
-        match tokio::time::timeout(std::time::Duration::from_secs(15), read_line_fut).await {
-            Ok(read) => {
-                read.context("early eof")?;
+        let remaining = self
+            .budget
+            .checked_sub(self.started_at.elapsed())
+            .unwrap_or_default();
+        let timeout = std::cmp::min(RESPONSE_TIMEOUT, remaining);
+        let line = match tokio::time::timeout(timeout, self.responses.recv()).await {
+            Ok(Some(line)) => line.context("failed to read valuer response")?,
+            Ok(None) => {
+                // minion kills valuer when budget is exceeded
+                self.check_budget()?;
+                bail!("early eof");
             }
             Err(_elapsed) => {
+                self.check_budget()?;
                 bail!("valuer response timed out");
             }
-        }
+        };
         let response = serde_json::from_str(&line).context("failed to parse valuer message")?;
 
         Ok(response)
@@ -1,6 +1,7 @@
 #pragma once
 
 #include "jtl.h"
+#include "reader.h"
 #include <cstdio>
 
 namespace checker {
@@ -35,6 +36,17 @@ struct CheckerInput {
 
 CheckerInput init(bool open_files = true);
 
+/// Readers for checker input files. Only available if init() opened files.
+/// Errors in contestant's answer result in PRESENTATION_ERROR (if answer can
+/// not be parsed) or WRONG_ANSWER (if value violates constraints). Errors in
+/// other files result in CHECKER_LOGIC_ERROR. In all cases error position
+/// is written to comment.
+jtl::Reader& sol();
+
+jtl::Reader& corr();
+
+jtl::Reader& test();
+
 /// Reads next char sequence, followed by whitespace
 /// next_token() returns owning pointer to token. This pointer should be freed
 /// by free()
@@ -0,0 +1,95 @@
+#pragma once
+
+#include "jtl.h"
+#include <cstddef>
+#include <cstdint>
+#include <cstdio>
+#include <string>
+
+namespace jtl {
+
+enum class ReadError {
+    /// Data can not be parsed: unexpected EOF, not a number, token does not
+    /// match pattern, extra data, etc.
+    FORMAT,
+    /// Data was parsed, but value violates constraints (e.g. out of range)
+    VALUE,
+};
+
+class Reader;
+
+/// Called when reader fails. `message` already contains stream name and
+/// position. Handler must not return.
+using ErrorHandler = void (*)(const Reader& reader, ReadError kind,
+                              const char* message);
+
+/// testlib-style reader.
+///
+/// In relaxed mode (used for checkers) whitespace between tokens is skipped
+/// automatically. In strict mode (used for validators) all whitespace must be
+/// consumed explicitly with `read_space()` and `read_eoln()`.
+class Reader {
+    FILE* file;
+    const char* stream_name;
+    ErrorHandler on_error;
+    bool strict;
+    size_t cur_line = 1;
+    size_t cur_column = 1;
+    /// Position of last read item, used in error messages
+    size_t item_line = 1;
+    size_t item_column = 1;
+
+    int peek();
+    int get();
+    void skip_whitespace();
+    void mark();
+    std::string next_token(const char* name);
+
+  public:
+    Reader(FILE* file, const char* stream_name, ErrorHandler on_error,
+           bool strict = false);
+
+    Reader(const Reader&) = delete;
+    Reader& operator=(const Reader&) = delete;
+
+    /// Reads integer in range [lo; hi]
+    int64_t read_int(int64_t lo, int64_t hi, const char* name);
+
+    /// Reads finite floating-point number in range [lo; hi]
+    long double read_double(long double lo, long double hi, const char* name);
+
+    /// Reads next whitespace-separated token
+    std::string read_word(const char* name);
+
+    /// Reads next whitespace-separated token, which must fully match
+    /// `pattern` (ECMAScript regular expression)
+    std::string read_word(const char* pattern, const char* name);
+
+    /// Reads rest of current line. Line terminator is consumed, but not
+    /// returned.
+    std::string read_line(const char* name);
+
+    /// Reads single space. Only makes sense in strict mode.
+    void read_space();
+
+    /// Reads line terminator. Only makes sense in strict mode.
+    void read_eoln();
+
+    /// Returns true if only whitespace remains (in strict mode: if nothing
+    /// remains)
+    bool seek_eof();
+
+    /// Fails if stream contains more data
+    void expect_eof();
+
+    /// Reports error at position of last read item
+    void fail(ReadError kind, const char* format, ...) ATTR_NORETURN
+        PRINT_FORMAT_FN(3);
+
+    size_t line() const;
+
+    size_t column() const;
+
+    const char* name() const;
+};
+} // namespace jtl
@@ -0,0 +1,14 @@
+#pragma once
+
+#include "jtl.h"
+#include "reader.h"
+
+namespace validator {
+
+/// Returns strict reader for test, which is read from stdin. All whitespace
+/// must be read explicitly, and validator should call `expect_eof()` at the
+/// end.
+/// On error validator prints message with error position to stderr and exits
+/// with code 1. Exit code 0 means that test is valid.
+jtl::Reader& init();
+} // namespace validator
@@ -2,6 +2,7 @@
 #include <cstddef>
 #include <cstdio>
 #include <cstring>
+#include <string>
 
 using namespace checker;
 struct Random;
@@ -26,7 +27,8 @@ bool is_float(const char* s) {
     return cnt <= 1 && s[0] != '.' && s[n - 1] != '.';
 }
 
-bool compare_tokens(char* expected, char* actual, const Args& args) {
+bool compare_tokens(const char* expected, const char* actual,
+                    const Args& args) {
     if (is_float(expected) && args.enable_epsilon) {
         long double exp = strtold(expected, nullptr);
         long double act = strtold(actual, nullptr);
@@ -39,7 +41,7 @@ bool compare_tokens(char* expected, char* actual, const Args& args) {
 }
 
 int main(int argc, char** argv) {
-    checker::CheckerInput checker_input = init();
+    init();
     Args args;
     for (size_t i = 1; i < argc; ++i) {
         if (strcmp(argv[i], "--epsilon") == 0) {
@@ -62,33 +64,35 @@ int main(int argc, char** argv) {
     }
     size_t i = 0;
     while (true) {
-        char* actual = next_token(checker_input.sol_answer);
-        char* expected = next_token(checker_input.corr_answer);
-        if (!expected && actual) {
-            comment("error: early EOF in actual answer on position %zu", i);
-            comment("note: next expected token was %s", expected);
-            finish(Outcome::WRONG_ANSWER);
+        bool expected_eof = corr().seek_eof();
+        bool actual_eof = sol().seek_eof();
+        if (expected_eof && actual_eof) {
+            break;
         }
-        if (expected && !actual) {
-            comment("error: actual answer contains additional tokens, starting "
-                    "from %zu",
-                    i);
-            comment("note: next actual token was %s", actual);
+        if (actual_eof) {
+            std::string expected = corr().read_word("token");
+            comment("error: early EOF in actual answer on position %zu", i);
+            comment("note: next expected token was %s", expected.c_str());
             finish(Outcome::WRONG_ANSWER);
         }
-        if (!expected) {
-            break;
+        if (expected_eof) {
+            std::string actual = sol().read_word("token");
+            sol().fail(jtl::ReadError::VALUE,
+                       "actual answer contains additional tokens, starting "
+                       "from position %zu (%s)",
+                       i, actual.c_str());
         }
-        bool eq = compare_tokens(expected, actual, args);
+        std::string expected = corr().read_word("token");
+        std::string actual = sol().read_word("token");
+        bool eq = compare_tokens(expected.c_str(), actual.c_str(), args);
         if (!eq) {
-            comment("error: token mismatch on position %zu", i);
-            comment("note: expected %s, got %s", expected, actual);
-
-            finish(Outcome::WRONG_ANSWER);
+            sol().fail(jtl::ReadError::VALUE,
+                       "token mismatch on position %zu: expected %s, got %s",
+                       i, expected.c_str(), actual.c_str());
         }
         ++i;
     }
 
     comment("success: %zu tokens", i);
     finish(Outcome::OK);
-}
\ No newline at end of file
+}
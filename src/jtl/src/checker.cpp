@@ -4,15 +4,35 @@
 #include <cassert>
 #include <cmath>
 #include <cstdarg>
+#include <memory>
 
 struct CheckerData {
     checker::CheckerInput inp;
     FILE* out_file = nullptr;
     FILE* comment_file = nullptr;
+    std::unique_ptr<jtl::Reader> sol_reader;
+    std::unique_ptr<jtl::Reader> corr_reader;
+    std::unique_ptr<jtl::Reader> test_reader;
 };
 
 CheckerData CHECKER;
 
+/// Contestant's answer is malformed or wrong
+static void on_sol_error(const jtl::Reader&, jtl::ReadError kind,
+                         const char* message) {
+    checker::comment("error: %s", message);
+    checker::finish(kind == jtl::ReadError::VALUE
+                        ? checker::Outcome::WRONG_ANSWER
+                        : checker::Outcome::PRESENTATION_ERROR);
+}
+
+/// Test or correct answer is malformed, so checker (or problem) is wrong
+static void on_jury_error(const jtl::Reader&, jtl::ReadError,
+                          const char* message) {
+    checker::comment("fatal: %s", message);
+    checker::finish(checker::Outcome::CHECKER_LOGIC_ERROR);
+}
+
 checker::CheckerInput checker::init(bool open_files) {
     checker::CheckerInput inp;
     if (open_files) {
@@ -20,16 +40,38 @@ checker::CheckerInput checker::init(bool open_files) {
         inp.sol_answer = get_env_file("JJS_SOL", "r");
         inp.test = get_env_file("JJS_TEST", "r");
     } else {
-        inp.fd_corr = get_env_int("JJS_CORR");
-        inp.fd_sol = get_env_int("JJS_SOL");
-        inp.fd_test = get_env_int("JJS_TEST");
+        inp.fd_corr = get_env_fd("JJS_CORR");
+        inp.fd_sol = get_env_fd("JJS_SOL");
+        inp.fd_test = get_env_fd("JJS_TEST");
     }
     CHECKER.out_file = get_env_file("JJS_CHECKER_OUT", "w");
     CHECKER.comment_file = get_env_file("JJS_CHECKER_COMMENT", "w");
     CHECKER.inp = inp;
+    if (open_files) {
+        CHECKER.sol_reader = std::make_unique<jtl::Reader>(
+            inp.sol_answer, "sol", on_sol_error);
+        CHECKER.corr_reader = std::make_unique<jtl::Reader>(
+            inp.corr_answer, "corr", on_jury_error);
+        CHECKER.test_reader =
+            std::make_unique<jtl::Reader>(inp.test, "test", on_jury_error);
+    }
     return inp;
 }
 
+static jtl::Reader& get_reader(std::unique_ptr<jtl::Reader>& reader) {
+    if (!reader) {
+        die("checker readers are only available if checker::init() opened "
+            "files\n");
+    }
+    return *reader;
+}
+
+jtl::Reader& checker::sol() { return get_reader(CHECKER.sol_reader); }
+
+jtl::Reader& checker::corr() { return get_reader(CHECKER.corr_reader); }
+
+jtl::Reader& checker::test() { return get_reader(CHECKER.test_reader); }
+
 void checker::finish(Outcome outcome) {
     FILE* proto_file = CHECKER.out_file;
     fprintf(proto_file, "outcome=");
@@ -24,7 +24,7 @@ int get_env_int(const char* var_name) {
 }
 
 FILE* get_env_file(const char* var_name, const char* mode) {
-    int fd = get_env_int(var_name);
+    int fd = get_env_fd(var_name);
     FILE* file = fdopen(fd, mode);
     if (file == nullptr) {
         die("ERROR: var `%s` contains fd `%d`, which is not file of mode %s",
@@ -33,6 +33,15 @@ FILE* get_env_file(const char* var_name, const char* mode) {
     return file;
 }
 
+int get_env_fd(const char* var_name) {
+    int fd = get_env_int(var_name);
+    if (fd < 0) {
+        die("ERROR: var `%s` contains `%d`, which is not file descriptor\n",
+            var_name, fd);
+    }
+    return fd;
+}
+
 const uint8_t CHAR_BAD = 255;
 
 uint8_t decode_hex_char(char x) {
@@ -8,8 +8,12 @@ char* get_env(const char* var_name);
 
 int get_env_int(const char* var_name);
 
+/// Variable must contain file descriptor
 FILE* get_env_file(const char* var_name, const char* mode);
 
+/// Same as get_env_file, but returns file descriptor itself
+int get_env_fd(const char* var_name);
+
 struct BinString {
     std::unique_ptr<uint8_t[]> head;
     size_t len = 0;
@@ -0,0 +1,235 @@
+#include "reader.h"
+#include <cinttypes>
+#include <cmath>
+#include <cstdarg>
+#include <cstdlib>
+#include <regex>
+#include <unordered_map>
+
+static bool is_space(int c) {
+    return c == ' ' || c == '\n' || c == '\t' || c == '\r';
+}
+
+/// Pattern compilation is slow, so compiled patterns are cached
+static const std::regex& compile_pattern(const char* pattern) {
+    static std::unordered_map<std::string, std::regex> cache;
+    auto it = cache.find(pattern);
+    if (it == cache.end()) {
+        it = cache.emplace(pattern, std::regex(pattern)).first;
+    }
+    return it->second;
+}
+
+/// Returns token in form suitable for error message
+static std::string quote(const std::string& token) {
+    const size_t MAX_LEN = 64;
+    if (token.size() <= MAX_LEN) {
+        return "'" + token + "'";
+    }
+    return "'" + token.substr(0, MAX_LEN) + "...'";
+}
+
+jtl::Reader::Reader(FILE* file, const char* stream_name,
+                    jtl::ErrorHandler on_error, bool strict)
+    : file(file), stream_name(stream_name), on_error(on_error),
+      strict(strict) {}
+
+int jtl::Reader::peek() {
+    int c = getc(file);
+    if (c != EOF) {
+        ungetc(c, file);
+    }
+    return c;
+}
+
+int jtl::Reader::get() {
+    int c = getc(file);
+    if (c == '\n') {
+        ++cur_line;
+        cur_column = 1;
+    } else if (c != EOF) {
+        ++cur_column;
+    }
+    return c;
+}
+
+void jtl::Reader::skip_whitespace() {
+    while (is_space(peek())) {
+        get();
+    }
+}
+
+void jtl::Reader::mark() {
+    item_line = cur_line;
+    item_column = cur_column;
+}
+
+std::string jtl::Reader::next_token(const char* name) {
+    if (!strict) {
+        skip_whitespace();
+    }
+    mark();
+    int c = peek();
+    if (c == EOF) {
+        fail(ReadError::FORMAT, "unexpected EOF, expected %s", name);
+    }
+    if (is_space(c)) {
+        fail(ReadError::FORMAT, "unexpected whitespace, expected %s", name);
+    }
+    std::string token;
+    while (c != EOF && !is_space(c)) {
+        token.push_back((char) get());
+        c = peek();
+    }
+    return token;
+}
+
+int64_t jtl::Reader::read_int(int64_t lo, int64_t hi, const char* name) {
+    std::string token = next_token(name);
+    size_t pos = 0;
+    bool negative = false;
+    if (token[0] == '-') {
+        negative = true;
+        pos = 1;
+    }
+    if (pos == token.size()) {
+        fail(ReadError::FORMAT, "expected integer %s, got %s", name,
+             quote(token).c_str());
+    }
+    for (size_t i = pos; i < token.size(); ++i) {
+        if (token[i] < '0' || token[i] > '9') {
+            fail(ReadError::FORMAT, "expected integer %s, got %s", name,
+                 quote(token).c_str());
+        }
+    }
+    bool has_leading_zero = token[pos] == '0' && token.size() > pos + 1;
+    if (strict && (has_leading_zero || token == "-0")) {
+        fail(ReadError::FORMAT, "integer %s has non-canonical form %s", name,
+             quote(token).c_str());
+    }
+    // magnitude of INT64_MIN
+    const uint64_t limit = negative ? (uint64_t) INT64_MAX + 1 : INT64_MAX;
+    uint64_t magnitude = 0;
+    for (size_t i = pos; i < token.size(); ++i) {
+        uint64_t digit = token[i] - '0';
+        if (magnitude > (limit - digit) / 10) {
+            fail(ReadError::VALUE,
+                 "%s = %s is out of range [%" PRId64 ", %" PRId64 "]", name,
+                 quote(token).c_str(), lo, hi);
+        }
+        magnitude = magnitude * 10 + digit;
+    }
+    int64_t value = negative ? (int64_t)(0 - magnitude) : (int64_t) magnitude;
+    if (value < lo || value > hi) {
+        fail(ReadError::VALUE,
+             "%s = %" PRId64 " is out of range [%" PRId64 ", %" PRId64 "]",
+             name, value, lo, hi);
+    }
+    return value;
+}
+
+long double jtl::Reader::read_double(long double lo, long double hi,
+                                     const char* name) {
+    std::string token = next_token(name);
+    char* end = nullptr;
+    long double value = strtold(token.c_str(), &end);
+    if (end != token.c_str() + token.size() || !std::isfinite(value)) {
+        fail(ReadError::FORMAT, "expected number %s, got %s", name,
+             quote(token).c_str());
+    }
+    if (value < lo || value > hi) {
+        fail(ReadError::VALUE, "%s = %Lg is out of range [%Lg, %Lg]", name,
+             value, lo, hi);
+    }
+    return value;
+}
+
+std::string jtl::Reader::read_word(const char* name) {
+    return next_token(name);
+}
+
+std::string jtl::Reader::read_word(const char* pattern, const char* name) {
+    std::string token = next_token(name);
+    if (!std::regex_match(token, compile_pattern(pattern))) {
+        fail(ReadError::FORMAT, "%s %s does not match pattern '%s'", name,
+             quote(token).c_str(), pattern);
+    }
+    return token;
+}
+
+std::string jtl::Reader::read_line(const char* name) {
+    mark();
+    if (peek() == EOF) {
+        fail(ReadError::FORMAT, "unexpected EOF, expected %s", name);
+    }
+    std::string line;
+    while (true) {
+        int c = get();
+        if (c == EOF || c == '\n') {
+            break;
+        }
+        line.push_back((char) c);
+    }
+    if (!strict && !line.empty() && line.back() == '\r') {
+        line.pop_back();
+    }
+    return line;
+}
+
+void jtl::Reader::read_space() {
+    mark();
+    if (peek() != ' ') {
+        fail(ReadError::FORMAT, "expected space");
+    }
+    get();
+}
+
+void jtl::Reader::read_eoln() {
+    mark();
+    if (!strict && peek() == '\r') {
+        get();
+    }
+    if (peek() != '\n') {
+        fail(ReadError::FORMAT, "expected end of line");
+    }
+    get();
+}
+
+bool jtl::Reader::seek_eof() {
+    if (!strict) {
+        skip_whitespace();
+    }
+    return peek() == EOF;
+}
+
+void jtl::Reader::expect_eof() {
+    if (seek_eof()) {
+        return;
+    }
+    mark();
+    if (strict) {
+        fail(ReadError::FORMAT, "expected EOF");
+    }
+    std::string token = next_token("EOF");
+    fail(ReadError::FORMAT, "expected EOF, got %s", quote(token).c_str());
+}
+
+void jtl::Reader::fail(ReadError kind, const char* format, ...) {
+    const int BUF_LEN = 4096;
+    char buf[BUF_LEN];
+    int prefix_len = snprintf(buf, BUF_LEN, "%s:%zu:%zu: ", stream_name,
+                              item_line, item_column);
+    va_list args;
+    va_start(args, format);
+    vsnprintf(buf + prefix_len, BUF_LEN - prefix_len, format, args);
+    va_end(args);
+    on_error(*this, kind, buf);
+    // handler must not return
+    abort();
+}
+
+size_t jtl::Reader::line() const { return item_line; }
+
+size_t jtl::Reader::column() const { return item_column; }
+
+const char* jtl::Reader::name() const { return stream_name; }
@@ -0,0 +1,13 @@
+#include "validator.h"
+#include <cstdio>
+#include <cstdlib>
+
+static void on_error(const jtl::Reader&, jtl::ReadError, const char* message) {
+    fprintf(stderr, "validation failed: %s\n", message);
+    exit(1);
+}
+
+jtl::Reader& validator::init() {
+    static jtl::Reader reader(stdin, "test", on_error, true);
+    return reader;
+}
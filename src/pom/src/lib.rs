@@ -10,6 +10,9 @@ pub struct Limits {
     pub process_count: Option<u64>,
     /// Working dir size limit in bytes
     pub work_dir_size: Option<u64>,
+    /// Stdout (and, separately, stderr) size limit in bytes
+    #[serde(default)]
+    pub output_limit: Option<u64>,
 }
 
 impl Limits {
@@ -29,6 +32,10 @@ impl Limits {
         16 * 1024 * 1024
     }
 
+    fn default_output_limit() -> u64 {
+        64 * 1024 * 1024
+    }
+
     pub fn time(self) -> u64 {
         self.time.unwrap_or_else(Self::default_time)
     }
@@ -41,10 +48,29 @@ impl Limits {
         self.process_count.unwrap_or_else(Self::default_num_procs)
     }
 
+    /// Real time limit in milliseconds. Sandboxed processes may wait (e.g.
+    /// for IO), so it is greater than CPU time limit.
+    pub fn real_time(self) -> u64 {
+        self.time() * 3
+    }
+
+    /// Default limits for valuer. Valuer only computes scores, so its CPU
+    /// time limit is small, even though it lives during whole judging session.
+    pub fn valuer_default() -> Limits {
+        Limits {
+            time: Some(10 * 1000),
+            ..Limits::default()
+        }
+    }
+
     pub fn work_dir_size(self) -> u64 {
         self.work_dir_size
             .unwrap_or_else(Self::default_work_dir_size)
     }
+
+    pub fn output_limit(self) -> u64 {
+        self.output_limit.unwrap_or_else(Self::default_output_limit)
+    }
 }
 
 impl Default for Limits {
@@ -54,6 +80,7 @@ impl Default for Limits {
             time: Some(Limits::default_time()),
             process_count: Some(Limits::default_num_procs()),
             work_dir_size: Some(Limits::default_work_dir_size()),
+            output_limit: Some(Limits::default_output_limit()),
         }
     }
 }
@@ -120,6 +147,24 @@ impl std::ops::Index<TestId> for Vec<Test> {
     }
 }
 
+/// Defines what contestants submit
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProblemKind {
+    /// Submission is program source, which is compiled and run on tests
+    Standard,
+    /// Submission is archive (tar, possibly gzipped) of answer files.
+    /// Answer for test `N` is file with stem `N` (e.g. `1.txt` or `01.out`).
+    /// Answers are passed to checker as is.
+    OutputOnly,
+}
+
+impl Default for ProblemKind {
+    fn default() -> Self {
+        ProblemKind::Standard
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Problem {
     pub title: String,
@@ -129,4 +174,37 @@ pub struct Problem {
     pub checker_cmd: Vec<String>,
     pub valuer_exe: FileRef,
     pub valuer_cfg: FileRef,
+    /// Limits for checker. Checker is launched in sandbox once per test.
+    #[serde(default)]
+    pub checker_limits: Limits,
+    /// Limits for valuer. Time limit is CPU time limit, see
+    /// [`valuer_budget`](Problem::valuer_budget) for wall-clock limit.
+    #[serde(default = "Limits::valuer_default")]
+    pub valuer_limits: Limits,
+    /// Wall-clock budget for judging session in milliseconds. If not set,
+    /// it is derived from test limits.
+    #[serde(default)]
+    pub valuer_budget: Option<u64>,
+    #[serde(default)]
+    pub kind: ProblemKind,
+}
+
+impl Problem {
+    /// Extra time for judging session, which is not spent running tests
+    const VALUER_BUDGET_MARGIN: u64 = 60 * 1000;
+
+    /// Returns wall-clock budget for judging session in milliseconds.
+    /// By default it is enough to run solution and checker on every test
+    /// until real time limit.
+    pub fn valuer_budget(&self) -> u64 {
+        if let Some(budget) = self.valuer_budget {
+            return budget;
+        }
+        let tests_time: u64 = self
+            .tests
+            .iter()
+            .map(|test| test.limits.real_time() + self.checker_limits.real_time())
+            .sum();
+        tests_time + Self::VALUER_BUDGET_MARGIN
+    }
 }
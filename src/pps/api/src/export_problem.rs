@@ -0,0 +1,39 @@
+//! Export problem into some other format
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize)]
+pub struct Request {
+    /// Path to problem source directory (containing `problem.toml`)
+    /// or to compiled problem (containing `manifest.json`)
+    pub src_path: PathBuf,
+    /// Where to put exported package
+    pub out_path: PathBuf,
+    /// Target format
+    pub format: ExportFormat,
+    /// Ignore existing files in out_path
+    pub force: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    /// Polygon package (`problem.xml`)
+    Polygon,
+    /// Kattis problem package (`problem.yaml`)
+    Kattis,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Update {
+    /// Contains one warning: something was not exported or was exported
+    /// approximately. May appear multiple times.
+    Warning(String),
+    /// Started exporting tests. `count` tests will be exported.
+    ExportTests { count: usize },
+    /// Started exporting checker
+    ExportChecker,
+    /// Started exporting solution with specific name
+    ExportSolution(String),
+    /// Started exporting valuer config (test groups and scoring)
+    ExportValuerConfig,
+}
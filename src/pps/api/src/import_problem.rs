@@ -9,6 +9,9 @@ pub struct Request {
     pub out_path: PathBuf,
     /// do not check that dest is empty
     pub force: bool,
+    /// Contest name (used only when contest is imported).
+    /// If not specified, name of source directory is used.
+    pub contest_name: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -35,6 +38,15 @@ pub enum Update {
     ImportValuerConfig,
     /// Valuer config was not found, default will be used
     DefaultValuerConfig,
+    /// Contest is being imported, and it contains `count` problems.
+    /// Appears at most once.
+    ImportContestProblems { count: usize },
+    /// Started importing contest problem.
+    /// All updates until corresponding `ContestProblemDone` relate to this problem.
+    ImportContestProblem { code: String, name: String },
+    /// Finished importing contest problem.
+    /// If import failed, `error` contains description and problem is skipped.
+    ContestProblemDone { code: String, error: Option<String> },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,4 +61,6 @@ pub enum PropertyName {
     OutputPathPattern,
     /// Value is problem title.
     ProblemTitle,
+    /// Value is contest title.
+    ContestTitle,
 }
@@ -2,6 +2,7 @@
 //!
 //! All paths are relative to workspace root.
 pub mod compile_problem;
+pub mod export_problem;
 pub mod import_problem;
 
 use rpc::Route;
@@ -24,6 +25,15 @@ impl Route for ImportProblem {
     const ENDPOINT: &'static str = "/problems/import";
 }
 
+pub struct ExportProblem(Infallible);
+
+impl Route for ExportProblem {
+    type Request = rpc::Unary<export_problem::Request>;
+    type Response = rpc::Streaming<export_problem::Update, SimpleFinish>;
+
+    const ENDPOINT: &'static str = "/problems/export";
+}
+
 /// Contains possible error os success
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 #[must_use = "this is Result in fact"]
@@ -0,0 +1,56 @@
+use anyhow::Context as _;
+use pps_api::export_problem::{ExportFormat, Update};
+use std::path::PathBuf;
+
+fn parse_format(s: &str) -> anyhow::Result<ExportFormat> {
+    match s {
+        "polygon" => Ok(ExportFormat::Polygon),
+        "kattis" => Ok(ExportFormat::Kattis),
+        _ => anyhow::bail!("unknown format {}: expected 'polygon' or 'kattis'", s),
+    }
+}
+
+#[derive(clap::Clap, Debug)]
+pub struct ExportArgs {
+    /// Path to problem source or compiled problem
+    #[clap(long = "pkg", short = 'P')]
+    pub pkg_path: PathBuf,
+    /// Out path (will contain exported package)
+    #[clap(long = "out", short = 'O')]
+    pub out_path: PathBuf,
+    /// Target format: `polygon` or `kattis`
+    #[clap(long, short = 'f', parse(try_from_str = parse_format))]
+    pub format: ExportFormat,
+    /// Rewrite dir
+    #[clap(long, short = 'F')]
+    pub force: bool,
+}
+
+#[tracing::instrument(skip(client, args))]
+pub(crate) async fn exec(client: &mut rpc::Client, args: ExportArgs) -> anyhow::Result<()> {
+    let req = pps_api::export_problem::Request {
+        src_path: args.pkg_path,
+        out_path: args.out_path,
+        format: args.format,
+        force: args.force,
+    };
+    let (tx, mut export) = client
+        .start::<pps_api::ExportProblem>()
+        .await
+        .context("failed to start RPC call")?;
+    tx.send(req).await?;
+    while let Some(update) = export.next_event().await? {
+        match update {
+            Update::Warning(warning) => eprintln!("warning: {}", warning),
+            Update::ExportTests { count } => println!("Exporting {} tests", count),
+            Update::ExportChecker => println!("Exporting checker"),
+            Update::ExportSolution(solution) => println!("Exporting solution '{}'", solution),
+            Update::ExportValuerConfig => println!("Exporting valuer config"),
+        }
+    }
+    if let Err(err) = export.finish().await?.0 {
+        anyhow::bail!("Failed to export problem: {}", err)
+    }
+    println!("Export successful");
+    Ok(())
+}
@@ -19,16 +19,18 @@ pub struct ImportArgs {
     pub contest_name: Option<String>,
 }
 
-async fn import_one_problem(
+async fn import(
     client: &mut rpc::Client,
     src: &Path,
     dest: &Path,
     force: bool,
+    contest_name: Option<String>,
 ) -> anyhow::Result<()> {
     let import_req = pps_api::import_problem::Request {
         src_path: src.to_path_buf(),
         out_path: dest.to_path_buf(),
         force,
+        contest_name,
     };
     let (tx, mut import) = client.start::<pps_api::ImportProblem>().await?;
     tx.send(import_req).await?;
@@ -50,6 +52,7 @@ async fn import_one_problem(
                     println!("Test output file path pattern: {}", property_value)
                 }
                 PropertyName::ProblemTitle => println!("Problem title: {}", property_value),
+                PropertyName::ContestTitle => println!("Contest title: {}", property_value),
             },
             Update::Warning(warning) => eprintln!("warning: {}", warning),
             Update::ImportChecker => println!("Importing checker"),
@@ -59,6 +62,16 @@ async fn import_one_problem(
             Update::ImportSolution(solution) => println!("Importing solution '{}'", solution),
             Update::ImportValuerConfig => println!("Importing valuer config"),
             Update::DefaultValuerConfig => println!("Defaulting valuer config"),
+            Update::ImportContestProblems { count } => {
+                println!("Importing contest with {} problems", count)
+            }
+            Update::ImportContestProblem { code, name } => {
+                println!("--- Importing problem {} ({}) ---", code, name)
+            }
+            Update::ContestProblemDone { code, error } => match error {
+                Some(err) => eprintln!("Problem {} failed to import: {}", code, err),
+                None => println!("Problem {} imported", code),
+            },
         }
     }
     import.finish().await?.0.context("build failure")?;
@@ -80,9 +93,7 @@ pub(crate) async fn exec(client: &mut rpc::Client, args: ImportArgs) -> anyhow::
     let src = &args.in_path;
     let dest = &args.out_path;
 
-    import_one_problem(client, src, dest, args.force).await?;
-
-    // TODO support importing contests
+    import(client, src, dest, args.force, args.contest_name).await?;
 
     Ok(())
 }
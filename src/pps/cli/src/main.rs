@@ -3,6 +3,7 @@
 
 mod client_util;
 mod compile;
+mod export;
 mod import;
 mod progress_notifier;
 
@@ -11,6 +12,7 @@ mod progress_notifier;
 pub enum Args {
     Compile(compile::CompileArgs),
     Import(import::ImportArgs),
+    Export(export::ExportArgs),
 }
 
 use anyhow::Context as _;
@@ -52,5 +54,6 @@ async fn process_args(args: Args, client: &mut rpc::Client) -> anyhow::Result<()
     match args {
         Args::Compile(compile_args) => compile::exec(client, compile_args).await,
         Args::Import(import_args) => import::exec(client, import_args).await,
+        Args::Export(export_args) => export::exec(client, export_args).await,
     }
 }
@@ -1,7 +1,9 @@
 // TODO: split all this stuff to separate library
-use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::ffi::{OsStr, OsString};
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Command {
@@ -12,13 +14,22 @@ pub struct Command {
 }
 
 impl Command {
-    pub fn to_tokio_command(&self) -> tokio::process::Command {
-        let mut cmd = tokio::process::Command::new(&self.exe);
+    /// Creates minion command.
+    /// Executable is resolved on host using `PATH`, because sandbox
+    /// exposes host directories at the same locations.
+    pub fn to_minion_command(&self) -> minion::Command {
+        let mut cmd = minion::Command::new();
+        cmd.path(resolve_executable(&self.exe));
         cmd.args(self.argv.iter());
         if let Some(cwd) = &self.cwd {
             cmd.current_dir(cwd);
         }
-        cmd.envs(self.env.iter().cloned());
+        cmd.envs(self.env.iter().map(|(k, v)| {
+            let mut item = k.clone();
+            item.push("=");
+            item.push(v);
+            item
+        }));
         cmd
     }
 
@@ -37,29 +48,19 @@ impl Command {
         }
         out
     }
+}
 
-    pub async fn run_quiet(&mut self) -> anyhow::Result<std::process::Output> {
-        use std::os::unix::process::ExitStatusExt;
-        let mut s = self.to_tokio_command();
-        let out = s.output().await.context("couldn't spawn")?;
-        let status = out.status;
-        if status.success() {
-            return Ok(out);
-        }
-
-        let exit_code = if status.code().is_some() {
-            format!("normal: {}", status.code().unwrap())
-        } else {
-            format!("signaled: {}", status.signal().unwrap())
-        };
-        anyhow::bail!(
-            "child process did not finished successfully (exit code {})\ncommand: `{}`\nchild stdout:\n{}\nchild stderr:\n{}",
-            exit_code,
-            self,
-            String::from_utf8_lossy(&out.stdout),
-            String::from_utf8_lossy(&out.stderr)
-        )
+/// Searches for `exe` in `PATH` if it is not a path already.
+fn resolve_executable(exe: &OsStr) -> PathBuf {
+    let exe = Path::new(exe);
+    if exe.components().count() > 1 {
+        return exe.to_path_buf();
     }
+    let search_path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::split_paths(&search_path)
+        .map(|dir| dir.join(exe))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| exe.to_path_buf())
 }
 
 impl std::fmt::Display for Command {
@@ -1,6 +1,7 @@
 //! This module implements compiling source package into invoker package
 pub(crate) mod build;
 mod builder;
+mod sandbox;
 
 use anyhow::Context as _;
 use pps_api::{
@@ -55,6 +56,11 @@ pub(crate) async fn exec_compile_request(
         .canonicalize()
         .context("resolve problem dir")?;
 
+    let sandbox = sandbox::Sandbox::new(
+        data.minion.clone(),
+        vec![problem_dir.clone(), out_dir.clone(), data.jjs_dir.clone()],
+    );
+
     let mut builder = builder::ProblemBuilder {
         cfg: &problem_cfg,
         problem_dir: &problem_dir,
@@ -62,7 +68,13 @@ pub(crate) async fn exec_compile_request(
         jtl_dir: &data.jjs_dir,
         build_backend: &build::Pibs {
             jjs_dir: Path::new(&data.jjs_dir),
+            sandbox: &sandbox,
+            limits: sandbox::Limits::resolve(
+                &problem_cfg.build_limits,
+                sandbox::Limits::BUILD_DEFAULTS,
+            ),
         },
+        sandbox: &sandbox,
         tx,
     };
     builder.build().await?;
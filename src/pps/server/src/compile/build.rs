@@ -1,3 +1,7 @@
+use crate::{
+    command::Command,
+    compile::sandbox::{Limits, Sandbox, Step, StepError},
+};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -12,13 +16,13 @@ pub(crate) struct Task {
 }
 
 pub(crate) struct TaskSuccess {
-    pub(crate) command: crate::command::Command,
+    pub(crate) command: Command,
 }
 
 #[derive(Debug, Error)]
 pub(crate) enum TaskError {
-    #[error("child command errored: code {:?}", _0.status.code())]
-    ExitCodeNonZero(std::process::Output),
+    #[error(transparent)]
+    Step(#[from] StepError),
     #[error("io error: {source}")]
     Io {
         #[from]
@@ -34,23 +38,6 @@ impl Task {
     }
 }
 
-#[async_trait::async_trait]
-trait CommandExt {
-    async fn run(&mut self) -> Result<(), TaskError>;
-}
-
-#[async_trait::async_trait]
-impl CommandExt for tokio::process::Command {
-    async fn run(&mut self) -> Result<(), TaskError> {
-        let out = self.output().await?;
-        if out.status.success() {
-            Ok(())
-        } else {
-            Err(TaskError::ExitCodeNonZero(out))
-        }
-    }
-}
-
 #[async_trait::async_trait]
 pub(crate) trait BuildBackend: Send + Sync {
     async fn process_task(&self, task: Task) -> Result<TaskSuccess, TaskError>;
@@ -59,28 +46,37 @@ pub(crate) trait BuildBackend: Send + Sync {
 /// Ppc-integrated build system
 pub(crate) struct Pibs<'a> {
     pub(crate) jjs_dir: &'a Path,
+    /// All build commands are executed in this sandbox
+    pub(crate) sandbox: &'a Sandbox,
+    pub(crate) limits: Limits,
 }
 
 impl<'a> Pibs<'a> {
+    /// Runs one build command in sandbox
+    async fn run(&self, task: &Task, command: Command) -> Result<(), TaskError> {
+        let step = Step {
+            name: format!("build of {} (`{}`)", task.src.display(), command),
+            command,
+            limits: self.limits,
+            stdin: None,
+            writable: vec![task.dest.clone(), task.tmp.clone()],
+        };
+        self.sandbox.run(step).await?;
+        Ok(())
+    }
+
     async fn process_cmake_task(&self, task: Task) -> Result<TaskSuccess, TaskError> {
-        //    let cmake_lists = task.src.join("CMakeLists.txt");
-        tokio::process::Command::new("cmake")
-            .arg("-S")
-            .arg(&task.src)
-            .arg("-B")
-            .arg(&task.tmp)
-            .run()
-            .await?;
+        let mut configure = Command::new("cmake");
+        configure.arg("-S").arg(&task.src).arg("-B").arg(&task.tmp);
+        self.run(&task, configure).await?;
 
-        tokio::process::Command::new("cmake")
-            .arg("--build")
-            .arg(&task.tmp)
-            .run()
-            .await?;
+        let mut build = Command::new("cmake");
+        build.arg("--build").arg(&task.tmp);
+        self.run(&task, build).await?;
 
         let dst = task.dest.join("bin");
         tokio::fs::copy(task.tmp.join("Out"), &dst).await?;
-        let run_cmd = crate::command::Command::new(dst);
+        let run_cmd = Command::new(dst);
         Ok(TaskSuccess { command: run_cmd })
     }
 }
@@ -97,7 +93,7 @@ impl<'a> BuildBackend for Pibs<'a> {
             if python_path.exists() {
                 let out_path = task.dest.join("out.py");
                 std::fs::copy(&python_path, &out_path)?;
-                let mut command = crate::command::Command::new("python3");
+                let mut command = Command::new("python3");
                 command.arg(&out_path);
                 return Ok(TaskSuccess { command });
             }
@@ -110,21 +106,21 @@ impl<'a> BuildBackend for Pibs<'a> {
         let link_arg = format!("-L{}/lib", self.jjs_dir.display());
 
         let dest_file = task.dest.join("bin");
-        tokio::process::Command::new("g++")
+        let mut compile = Command::new("g++");
+        compile
             .arg("-std=c++17")
             .arg(incl_arg)
             .arg(link_arg)
             .arg("-DPPC=1")
-            .arg(task.src)
+            .arg(&task.src)
             .arg("-o")
             .arg(&dest_file)
             .arg("-ljtl")
             .arg("-lpthread")
-            .arg("-ldl")
-            .run()
-            .await?;
+            .arg("-ldl");
+        self.run(&task, compile).await?;
 
-        let command = crate::command::Command::new(&dest_file);
+        let command = Command::new(&dest_file);
         Ok(TaskSuccess { command })
     }
 }
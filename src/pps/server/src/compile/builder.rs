@@ -1,6 +1,9 @@
 use crate::{
     command::Command,
-    compile::build::{BuildBackend, Task, TaskError},
+    compile::{
+        build::{BuildBackend, Task},
+        sandbox::{self, Sandbox, Step},
+    },
 };
 use anyhow::Context as _;
 use pom::{FileRef, FileRefRoot, Limits};
@@ -8,7 +11,6 @@ use pps_api::compile_problem::Update;
 use std::{
     collections::HashMap,
     fmt::Write,
-    os::unix::io::IntoRawFd,
     path::{Path, PathBuf},
 };
 
@@ -25,6 +27,8 @@ pub(crate) struct ProblemBuilder<'a> {
     pub(crate) jtl_dir: &'a Path,
     /// Used to execute build tasks (e.g. builds checker or solution)
     pub(crate) build_backend: &'a dyn BuildBackend,
+    /// Used to run generators and solutions
+    pub(crate) sandbox: &'a Sandbox,
     /// Used to return live building progress
     pub(crate) tx: &'a mut rpc::StreamingTx<Update, pps_api::SimpleFinish>,
 }
@@ -58,6 +62,7 @@ fn merge_limits(limits_set: &[Limits]) -> Limits {
         merge_option(&mut res.memory, lim.memory);
         merge_option(&mut res.process_count, lim.process_count);
         merge_option(&mut res.time, lim.time);
+        merge_option(&mut res.output_limit, lim.output_limit);
     }
     res
 }
@@ -95,20 +100,6 @@ impl<'a> ProblemBuilder<'a> {
                     err
                 )
                 .unwrap();
-                if let TaskError::ExitCodeNonZero(out) = err {
-                    writeln!(
-                        &mut description,
-                        "--- stdout ---\n{}",
-                        String::from_utf8_lossy(&out.stdout)
-                    )
-                    .unwrap();
-                    writeln!(
-                        &mut description,
-                        "--- stderr ---\n{}",
-                        String::from_utf8_lossy(&out.stderr)
-                    )
-                    .unwrap();
-                }
                 writeln!(&mut description, "Build task: {:#?}", task).unwrap();
                 anyhow::bail!("task execution error: {}", description)
             }
@@ -198,6 +189,25 @@ impl<'a> ProblemBuilder<'a> {
         cmd.env("JJS_PROBLEM_DEST", &self.out_dir);
     }
 
+    /// Runs generator or solution in sandbox, returning its stdout
+    async fn run_step(
+        &self,
+        name: String,
+        mut command: Command,
+        stdin: Option<PathBuf>,
+    ) -> anyhow::Result<Vec<u8>> {
+        self.configure_command(&mut command);
+        let step = Step {
+            name,
+            command,
+            limits: sandbox::Limits::resolve(&self.cfg.run_limits, sandbox::Limits::RUN_DEFAULTS),
+            stdin,
+            writable: Vec::new(),
+        };
+        let output = self.sandbox.run(step).await?;
+        Ok(output.stdout)
+    }
+
     /// Builds all tests
     async fn build_tests(
         &mut self,
@@ -235,9 +245,9 @@ impl<'a> ProblemBuilder<'a> {
                     }
                     cmd.env("JJS_TEST_ID", &tid.to_string());
                     cmd.env("JJS_RANDOM_SEED", &entropy);
-                    self.configure_command(&mut cmd);
-                    let gen_out = cmd.run_quiet().await?;
-                    tokio::fs::write(&out_file_path, gen_out.stdout)
+                    let step_name = format!("generator '{}' (test {})", testgen, tid);
+                    let gen_out = self.run_step(step_name, cmd, None).await?;
+                    tokio::fs::write(&out_file_path, gen_out)
                         .await
                         .context("failed to write test")?;
                 }
@@ -262,61 +272,34 @@ impl<'a> ProblemBuilder<'a> {
                 limits: merge_limits(&[self.cfg.limits, test_spec.limits]),
                 group: test_spec.group.clone(),
             };
-            if let Some(cmd) = gen_answers {
-                let test_data = tokio::fs::File::open(&out_file_path).await?;
-
-                let correct_file_path = format!("{}/{}-out.txt", &tests_path, tid);
-
-                let answer_data = tokio::fs::File::create(&correct_file_path).await?;
-
-                let mut cmd = cmd.clone();
-                self.configure_command(&mut cmd);
-                let mut cmd = cmd.to_tokio_command();
-                let mut close_handles = vec![];
-                unsafe {
-                    let test_data_fd = test_data.into_std().await.into_raw_fd();
-                    close_handles.push(test_data_fd);
-                    let test_data_fd = libc::dup(test_data_fd);
-                    close_handles.push(test_data_fd);
-
-                    let ans_data_fd = answer_data.into_std().await.into_raw_fd();
-                    close_handles.push(ans_data_fd);
-                    let ans_data_fd = libc::dup(ans_data_fd);
-                    close_handles.push(ans_data_fd);
-                    cmd.pre_exec(move || {
-                        if libc::dup2(test_data_fd, 0) == -1 {
-                            return Err(std::io::Error::last_os_error());
-                        }
-                        if libc::dup2(ans_data_fd, 1) == -1 {
-                            return Err(std::io::Error::last_os_error());
-                        }
-                        Ok(())
-                    });
-                }
-                let output = cmd
-                    .stdin(crate::Stdio::piped())
-                    .stdout(crate::Stdio::piped())
-                    .stderr(crate::Stdio::piped())
-                    .output()
+            let correct_file_path = format!("{}/{}-out.txt", &tests_path, tid);
+            if let Some(answer) = &test_spec.answer {
+                let src_path = self.problem_dir.join("tests").join(answer);
+                tokio::fs::copy(&src_path, &correct_file_path)
                     .await
-                    .context("launch main solution error: {}")?;
-                if !output.status.success() {
-                    anyhow::bail!(
-                        "Error while generating correct answer for test {}: main solution failed: {}",
-                        tid,
-                        String::from_utf8_lossy(&output.stderr)
-                    );
-                }
+                    .with_context(|| {
+                        format!("failed to copy correct answer {}", src_path.display())
+                    })?;
+                test_info.correct.replace(FileRef {
+                    path: format!("tests/{}-out.txt", tid),
+                    root: FileRefRoot::Problem,
+                });
+            } else if let Some(cmd) = gen_answers {
+                let step_name = format!("main solution (test {})", tid);
+                let answer = self
+                    .run_step(step_name, cmd.clone(), Some(out_file_path.into()))
+                    .await
+                    .with_context(|| {
+                        format!("Error while generating correct answer for test {}", tid)
+                    })?;
+                tokio::fs::write(&correct_file_path, answer)
+                    .await
+                    .context("failed to write correct answer")?;
                 let short_file_path = format!("tests/{}-out.txt", tid);
                 test_info.correct.replace(FileRef {
                     path: short_file_path,
                     root: FileRefRoot::Problem,
                 });
-                for handle in close_handles {
-                    unsafe {
-                        libc::close(handle);
-                    }
-                }
             }
             out.push(test_info);
         }
@@ -455,6 +438,11 @@ impl<'a> ProblemBuilder<'a> {
             path: "valuer-cfg".to_string(),
         };
 
+        let valuer_limits = match self.cfg.valuer_limits {
+            Some(limits) => merge_limits(&[Limits::valuer_default(), limits]),
+            None => Limits::valuer_default(),
+        };
+
         let problem = pom::Problem {
             title: self.cfg.title.clone(),
             name: self.cfg.name.clone(),
@@ -463,6 +451,10 @@ impl<'a> ProblemBuilder<'a> {
             valuer_exe,
             tests,
             valuer_cfg,
+            checker_limits: self.cfg.checker_limits,
+            valuer_limits,
+            valuer_budget: self.cfg.valuer_budget,
+            kind: self.cfg.kind,
         };
         let manifest_path = format!("{}/manifest.json", self.out_dir.display());
         let manifest_data =
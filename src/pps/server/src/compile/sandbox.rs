@@ -0,0 +1,315 @@
+//! Executes problem building steps (compilers, generators, solutions)
+//! inside minion sandboxes.
+use crate::command::Command;
+use anyhow::Context as _;
+use std::{
+    ffi::CString,
+    io::{Read, Write},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Host directories which are exposed into each sandbox (read-only), so that
+/// compilers, interpreters and shared libraries can be found.
+const HOST_DIRS: &[&str] = &["/usr", "/bin", "/lib", "/lib64"];
+
+/// `PATH` value used inside sandbox
+const SANDBOX_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Resolved limits for a single step.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    pub(crate) time: Duration,
+    /// Memory limit in bytes
+    pub(crate) memory: u64,
+    /// Limit for stdout and stderr (each one separately) in bytes
+    pub(crate) output: u64,
+    pub(crate) process_count: u64,
+}
+
+impl Limits {
+    /// Used for compilation of solutions, generators, checkers and modules
+    pub(crate) const BUILD_DEFAULTS: Limits = Limits {
+        time: Duration::from_secs(60),
+        memory: 1024 * 1024 * 1024,
+        output: 16 * 1024 * 1024,
+        process_count: 64,
+    };
+
+    /// Used for running generators and solutions
+    pub(crate) const RUN_DEFAULTS: Limits = Limits {
+        time: Duration::from_secs(10),
+        memory: 512 * 1024 * 1024,
+        output: 256 * 1024 * 1024,
+        process_count: 16,
+    };
+
+    /// Fills missing values in `raw` from `defaults`
+    pub(crate) fn resolve(raw: &crate::manifest::StepLimits, defaults: Limits) -> Limits {
+        Limits {
+            time: raw.time.map_or(defaults.time, Duration::from_millis),
+            memory: raw.memory.unwrap_or(defaults.memory),
+            output: raw.output.unwrap_or(defaults.output),
+            process_count: raw.process_count.unwrap_or(defaults.process_count),
+        }
+    }
+}
+
+/// Single command which should be executed in sandbox
+pub(crate) struct Step {
+    /// Human-readable step description, e.g. `generator 'main' (test 5)`.
+    /// Used in error messages.
+    pub(crate) name: String,
+    pub(crate) command: Command,
+    pub(crate) limits: Limits,
+    /// If set, this file will be connected to step's stdin
+    pub(crate) stdin: Option<PathBuf>,
+    /// Directories which step is allowed to modify.
+    /// They are exposed at the same path.
+    pub(crate) writable: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub(crate) struct StepOutput {
+    pub(crate) stdout: Vec<u8>,
+    pub(crate) stderr: Vec<u8>,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum StepError {
+    #[error("{step} exceeded time limit ({} ms)", limit.as_millis())]
+    TimeLimitExceeded { step: String, limit: Duration },
+    #[error("{step} exceeded memory limit ({limit} bytes)")]
+    MemoryLimitExceeded { step: String, limit: u64 },
+    #[error("{step} exceeded output limit ({limit} bytes written to {stream})")]
+    OutputLimitExceeded {
+        step: String,
+        stream: &'static str,
+        limit: u64,
+    },
+    #[error(
+        "{step} failed with exit code {code}\n--- stdout ---\n{stdout}\n--- stderr ---\n{stderr}"
+    )]
+    ExitCodeNonZero {
+        step: String,
+        code: i64,
+        stdout: String,
+        stderr: String,
+    },
+    #[error("{step} could not be executed: {error:#}")]
+    Sandbox { step: String, error: anyhow::Error },
+}
+
+/// Removes tmpfs on drop
+struct Tmpfs(PathBuf);
+
+impl Drop for Tmpfs {
+    fn drop(&mut self) {
+        let path = CString::new(self.0.as_os_str().as_bytes()).expect("path contains NUL");
+        if unsafe { libc::umount2(path.as_ptr(), libc::MNT_DETACH) } != 0 {
+            tracing::error!(path=%self.0.display(), "Leaking tmpfs: umount2 failed");
+        }
+    }
+}
+
+/// Reads at most `limit` bytes from `src`.
+/// Returns collected data and flag which is set if `src` contained more data.
+fn read_capped(src: impl Read, limit: u64) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut buf = Vec::new();
+    src.take(limit + 1).read_to_end(&mut buf)?;
+    let overflow = buf.len() as u64 > limit;
+    buf.truncate(limit as usize);
+    Ok((buf, overflow))
+}
+
+/// Executes `Step`s in isolated environment
+#[derive(Clone)]
+pub(crate) struct Sandbox {
+    backend: Arc<dyn minion::erased::Backend>,
+    /// These paths are exposed read-only into every sandbox at the same path
+    readonly: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub(crate) fn new(
+        backend: Arc<dyn minion::erased::Backend>,
+        readonly: Vec<PathBuf>,
+    ) -> Sandbox {
+        Sandbox { backend, readonly }
+    }
+
+    /// Runs step, returning its output on success
+    pub(crate) async fn run(&self, step: Step) -> Result<StepOutput, StepError> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.run_blocking(step))
+            .await
+            .expect("sandboxed step panicked")
+    }
+
+    fn run_blocking(&self, step: Step) -> Result<StepOutput, StepError> {
+        let work_dir = tempfile::TempDir::new()
+            .context("failed to create sandbox directory")
+            .map_err(|error| StepError::Sandbox {
+                step: step.name.clone(),
+                error,
+            })?;
+        let outcome = self.exec(&step, work_dir.path());
+        match outcome {
+            Ok(res) => res,
+            Err(error) => Err(StepError::Sandbox {
+                step: step.name,
+                error,
+            }),
+        }
+    }
+
+    fn exposed_paths(&self, step: &Step, scratch: &Path) -> Vec<minion::SharedDir> {
+        let mut exposed = Vec::new();
+        let readonly = HOST_DIRS
+            .iter()
+            .map(Path::new)
+            .chain(self.readonly.iter().map(PathBuf::as_path));
+        for dir in readonly {
+            if !dir.exists() {
+                continue;
+            }
+            exposed.push(minion::SharedDir {
+                src: dir.to_path_buf(),
+                dest: dir.to_path_buf(),
+                kind: minion::SharedDirKind::Readonly,
+            });
+        }
+        exposed.push(minion::SharedDir {
+            src: scratch.to_path_buf(),
+            dest: PathBuf::from("/tmp"),
+            kind: minion::SharedDirKind::Full,
+        });
+        for dir in &step.writable {
+            exposed.push(minion::SharedDir {
+                src: dir.clone(),
+                dest: dir.clone(),
+                kind: minion::SharedDirKind::Full,
+            });
+        }
+        exposed
+    }
+
+    /// Actually runs step. Returned `Err` means sandbox failure.
+    fn exec(&self, step: &Step, work_dir: &Path) -> anyhow::Result<Result<StepOutput, StepError>> {
+        let scratch = work_dir.join("tmp");
+        std::fs::create_dir(&scratch).context("failed to create scratch dir")?;
+        minion::linux::ext::make_tmpfs(
+            &scratch,
+            minion::linux::ext::Quota::bytes(step.limits.output),
+        )
+        .context("failed to set size limit on scratch dir")?;
+        let _scratch_guard = Tmpfs(scratch.clone());
+        let isolation_root = work_dir.join("root");
+        std::fs::create_dir(&isolation_root).context("failed to create chroot dir")?;
+
+        let sandbox_options = minion::SandboxOptions {
+            max_alive_process_count: step.limits.process_count as _,
+            memory_limit: step.limits.memory as _,
+            exposed_paths: self.exposed_paths(step, &scratch),
+            isolation_root,
+            cpu_time_limit: step.limits.time,
+            real_time_limit: step.limits.time * 3,
+        };
+        let sandbox = self
+            .backend
+            .new_sandbox(sandbox_options)
+            .context("failed to create sandbox")?;
+
+        let mut cmd = step.command.to_minion_command();
+        cmd.envs(&[format!("PATH={}", SANDBOX_PATH), "TMPDIR=/tmp".to_string()]);
+        cmd.sandbox(sandbox.clone());
+        cmd.stdin(minion::InputSpecification::pipe());
+        cmd.stdout(minion::OutputSpecification::pipe());
+        cmd.stderr(minion::OutputSpecification::pipe());
+        tracing::debug!(step=%step.name, command=%step.command, "running sandboxed step");
+        let mut child = cmd.spawn(&*self.backend).context("failed to spawn child")?;
+
+        let stdin_data = match &step.stdin {
+            Some(path) => std::fs::read(path)
+                .with_context(|| format!("failed to read input file {}", path.display()))?,
+            None => Vec::new(),
+        };
+        let mut stdin = child.stdin().context("child stdin was not captured")?;
+        let stdin_writer = std::thread::spawn(move || {
+            // child is allowed not to read its input
+            stdin.write_all(&stdin_data).ok();
+        });
+        let spawn_reader = |stream: Box<dyn Read + Send + Sync>| {
+            let limit = step.limits.output;
+            let sandbox = sandbox.clone();
+            std::thread::spawn(move || {
+                let res = read_capped(stream, limit);
+                if let Ok((_, true)) = res {
+                    sandbox.kill().ok();
+                }
+                res
+            })
+        };
+        let stdout_reader = spawn_reader(child.stdout().context("child stdout was not captured")?);
+        let stderr_reader = spawn_reader(child.stderr().context("child stderr was not captured")?);
+
+        let wait_result = child
+            .wait_for_exit(None)
+            .context("failed to wait for child")?;
+        // make sure that no process keeps pipes open
+        sandbox.kill().ok();
+        stdin_writer.join().expect("stdin writer panicked");
+        let (stdout, stdout_overflow) = stdout_reader
+            .join()
+            .expect("stdout reader panicked")
+            .context("failed to read child stdout")?;
+        let (stderr, stderr_overflow) = stderr_reader
+            .join()
+            .expect("stderr reader panicked")
+            .context("failed to read child stderr")?;
+
+        for &(overflow, stream) in &[(stdout_overflow, "stdout"), (stderr_overflow, "stderr")] {
+            if overflow {
+                return Ok(Err(StepError::OutputLimitExceeded {
+                    step: step.name.clone(),
+                    stream,
+                    limit: step.limits.output,
+                }));
+            }
+        }
+        if let minion::WaitOutcome::Timeout = wait_result {
+            return Ok(Err(StepError::TimeLimitExceeded {
+                step: step.name.clone(),
+                limit: step.limits.time,
+            }));
+        }
+        let exit_code = child
+            .get_exit_code()
+            .context("failed to get exit code")?
+            .context("child has not finished")?;
+        if exit_code == 0 {
+            return Ok(Ok(StepOutput { stdout, stderr }));
+        }
+        let resource_usage = sandbox
+            .resource_usage()
+            .context("cannot get resource usage")?;
+        if resource_usage
+            .memory
+            .map_or(false, |m| m >= step.limits.memory)
+        {
+            return Ok(Err(StepError::MemoryLimitExceeded {
+                step: step.name.clone(),
+                limit: step.limits.memory,
+            }));
+        }
+        Ok(Err(StepError::ExitCodeNonZero {
+            step: step.name.clone(),
+            code: exit_code,
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        }))
+    }
+}
@@ -0,0 +1,70 @@
+//! This module implements exporting problem source or compiled problem
+//! into other formats
+mod kattis;
+mod package;
+mod polygon;
+
+use anyhow::Context as _;
+use pps_api::{
+    export_problem::{ExportFormat, Request, Update},
+    ExportProblem, SimpleFinish,
+};
+use std::sync::Arc;
+
+impl rpc::Handler<ExportProblem> for crate::Service {
+    type Error = anyhow::Error;
+    type Fut = futures_util::future::BoxFuture<'static, anyhow::Result<()>>;
+
+    fn handle(
+        self,
+        rx: rpc::UnaryRx<Request>,
+        mut tx: rpc::StreamingTx<Update, SimpleFinish>,
+    ) -> Self::Fut {
+        Box::pin(async move {
+            let req = rx.recv().await?;
+            let result = exec_export_request(req, self.0, &mut tx).await;
+            tx.finish(result.into()).await?;
+            Ok(())
+        })
+    }
+}
+
+pub(crate) async fn exec_export_request(
+    req: Request,
+    data: Arc<crate::ServiceState>,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    if req.force {
+        if req.out_path.exists() {
+            let out_path = req
+                .out_path
+                .canonicalize()
+                .context("failed to resolve output path")?;
+            let src_path = req
+                .src_path
+                .canonicalize()
+                .context("failed to resolve problem source path")?;
+            // do not wipe the problem we are going to export
+            if src_path.starts_with(&out_path) {
+                anyhow::bail!(
+                    "output path {} contains problem source {}",
+                    out_path.display(),
+                    src_path.display()
+                );
+            }
+            std::fs::remove_dir_all(&out_path).context("failed to clear output path")?;
+        }
+        tokio::fs::create_dir_all(&req.out_path).await?;
+    } else {
+        crate::check_dir(&req.out_path, false)?;
+    }
+    let (package, warnings) =
+        package::load(&req.src_path, &data.jjs_dir).context("failed to load problem")?;
+    for warning in warnings {
+        tx.send_event(Update::Warning(warning)).await?;
+    }
+    match req.format {
+        ExportFormat::Polygon => polygon::export(&package, &req.out_path, tx).await,
+        ExportFormat::Kattis => kattis::export(&package, &req.out_path, tx).await,
+    }
+}
@@ -0,0 +1,58 @@
+// Generated from JJS builtin checker `cmp-tokens`.
+// Compares answers token by token.
+#include "testlib.h"
+#include <cctype>
+#include <cmath>
+#include <string>
+
+// Negative value means that tokens are compared as strings
+const long double EPSILON = $EPSILON;
+const bool IGNORE_CASE = $IGNORE_CASE;
+
+static bool is_float(const std::string& s) {
+    size_t dots = 0;
+    for (char c : s) {
+        if (c == '.') {
+            ++dots;
+        } else if (!isdigit(c)) {
+            return false;
+        }
+    }
+    return !s.empty() && dots <= 1 && s.front() != '.' && s.back() != '.';
+}
+
+static bool compare_tokens(const std::string& expected,
+                           const std::string& actual) {
+    if (EPSILON >= 0 && is_float(expected)) {
+        if (!is_float(actual)) {
+            return false;
+        }
+        return doubleCompare(stold(expected), stold(actual), EPSILON);
+    }
+    if (IGNORE_CASE) {
+        return lowerCase(expected) == lowerCase(actual);
+    }
+    return expected == actual;
+}
+
+int main(int argc, char* argv[]) {
+    registerTestlibCmd(argc, argv);
+    size_t n = 0;
+    while (!ans.seekEof()) {
+        if (ouf.seekEof()) {
+            quitf(_wa, "early EOF in actual answer on position %zu", n);
+        }
+        std::string expected = ans.readToken();
+        std::string actual = ouf.readToken();
+        if (!compare_tokens(expected, actual)) {
+            quitf(_wa, "token mismatch on position %zu: expected %s, got %s",
+                  n, expected.c_str(), actual.c_str());
+        }
+        ++n;
+    }
+    if (!ouf.seekEof()) {
+        quitf(_wa, "actual answer contains additional tokens, starting from %zu",
+              n);
+    }
+    quitf(_ok, "%zu tokens", n);
+}
@@ -0,0 +1,227 @@
+//! Writes Kattis problem package (`problem.yaml`).
+//!
+//! Groups with full feedback become samples. If valuer config contains
+//! several scored groups, problem is exported as scoring problem and each
+//! other group becomes subdirectory of `data/secret`.
+use super::package::{Checker, Package, Test, TestInput};
+use anyhow::Context as _;
+use pps_api::{export_problem::Update, SimpleFinish};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+struct Exporter<'a> {
+    package: &'a Package,
+    dest: &'a Path,
+    tx: &'a mut rpc::StreamingTx<Update, SimpleFinish>,
+}
+
+fn is_sample_group(group: &svaluer::cfg::Group) -> bool {
+    matches!(group.feedback, svaluer::cfg::FeedbackKind::Full) || group.name.starts_with("sample")
+}
+
+impl<'a> Exporter<'a> {
+    async fn warn(&mut self, message: String) -> anyhow::Result<()> {
+        self.tx.send_event(Update::Warning(message)).await?;
+        Ok(())
+    }
+
+    fn is_scoring(&self) -> bool {
+        match &self.package.valuer_cfg {
+            Some(cfg) => cfg.groups.iter().filter(|g| g.score != 0).count() > 1,
+            None => false,
+        }
+    }
+
+    /// Returns directory (relative to `data`) for tests of group `group_name`
+    fn group_dir(&self, group_name: &str) -> PathBuf {
+        let group = self
+            .package
+            .valuer_cfg
+            .as_ref()
+            .and_then(|cfg| cfg.groups.iter().find(|g| g.tests_tag() == group_name));
+        match group {
+            Some(group) if is_sample_group(group) => PathBuf::from("sample"),
+            Some(group) if self.is_scoring() => Path::new("secret").join(&group.name),
+            _ => PathBuf::from("secret"),
+        }
+    }
+
+    async fn export_test(&mut self, tid: usize, test: &Test) -> anyhow::Result<()> {
+        let input = match &test.input {
+            TestInput::File(path) => path,
+            TestInput::Generated { generator, .. } => {
+                self.warn(format!(
+                    "test {} skipped: it is produced by generator {}, which Kattis does not support; export compiled problem instead",
+                    tid, generator
+                ))
+                .await?;
+                return Ok(());
+            }
+        };
+        let dir = self.dest.join("data").join(self.group_dir(&test.group));
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        std::fs::copy(input, dir.join(format!("{:0>3}.in", tid)))
+            .with_context(|| format!("copy test {}", tid))?;
+        if let Some(answer) = &test.answer {
+            std::fs::copy(answer, dir.join(format!("{:0>3}.ans", tid)))
+                .with_context(|| format!("copy answer {}", tid))?;
+        }
+        Ok(())
+    }
+
+    /// Writes `testdata.yaml` for each scored group
+    async fn export_groups(&mut self) -> anyhow::Result<()> {
+        let package = self.package;
+        let config = match &package.valuer_cfg {
+            Some(cfg) => cfg,
+            None => return Ok(()),
+        };
+        self.tx.send_event(Update::ExportValuerConfig).await?;
+        let is_scoring = self.is_scoring();
+        for group in &config.groups {
+            if !group.deps.is_empty() {
+                self.warn(format!(
+                    "dependencies of group {} can not be represented in Kattis format",
+                    group.name
+                ))
+                .await?;
+            }
+            if !is_scoring || is_sample_group(group) {
+                continue;
+            }
+            // group score is granted only if all tests passed
+            let mut testdata = Mapping::new();
+            testdata.insert("grader_flags".into(), "min".into());
+            testdata.insert("accept_score".into(), group.score.into());
+            testdata.insert("range".into(), format!("0 {}", group.score).into());
+            let on_reject = if group.run_to_first_failure {
+                "break"
+            } else {
+                "continue"
+            };
+            testdata.insert("on_reject".into(), on_reject.into());
+            let dir = self.dest.join("data/secret").join(&group.name);
+            std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+            std::fs::write(
+                dir.join("testdata.yaml"),
+                serde_yaml::to_string(&Value::Mapping(testdata))?,
+            )
+            .context("write testdata.yaml")?;
+        }
+        Ok(())
+    }
+
+    /// Exports checker and returns `validator_flags`
+    async fn export_checker(&mut self) -> anyhow::Result<Option<String>> {
+        let package = self.package;
+        self.tx.send_event(Update::ExportChecker).await?;
+        match &package.checker {
+            Checker::CmpTokens {
+                epsilon,
+                ignore_case,
+            } => {
+                let mut flags = Vec::new();
+                if let Some(eps) = epsilon {
+                    flags.push(format!("float_tolerance {}", eps));
+                }
+                if !ignore_case {
+                    flags.push("case_sensitive".to_string());
+                }
+                Ok(Some(flags.join(" ")).filter(|f| !f.is_empty()))
+            }
+            Checker::Testlib(_) | Checker::Jtl(_) => {
+                self.warn(
+                    "custom checker can not be exported: Kattis output validators use different protocol; default validation is used"
+                        .to_string(),
+                )
+                .await?;
+                Ok(None)
+            }
+            Checker::Unknown => Ok(None),
+        }
+    }
+
+    async fn export_solutions(&mut self) -> anyhow::Result<()> {
+        let package = self.package;
+        if package.solutions.is_empty() {
+            return Ok(());
+        }
+        let dir = self.dest.join("submissions/accepted");
+        std::fs::create_dir_all(&dir).context("create submissions dir")?;
+        for solution in &package.solutions {
+            self.tx
+                .send_event(Update::ExportSolution(solution.name.clone()))
+                .await?;
+            if package.primary_solution.as_ref() != Some(&solution.name) {
+                self.warn(format!(
+                    "solution {} is exported as accepted: expected verdict is unknown",
+                    solution.name
+                ))
+                .await?;
+            }
+            let dest = dir.join(format!("{}.{}", solution.name, solution.extension()));
+            std::fs::copy(&solution.path, &dest)
+                .with_context(|| format!("copy solution {}", solution.name))?;
+        }
+        Ok(())
+    }
+
+    async fn run(&mut self) -> anyhow::Result<()> {
+        let package = self.package;
+        self.tx
+            .send_event(Update::ExportTests {
+                count: package.tests.len(),
+            })
+            .await?;
+        for (i, test) in package.tests.iter().enumerate() {
+            self.export_test(i + 1, test).await?;
+        }
+        if package.tests.iter().any(|test| test.answer.is_none()) {
+            self.warn(
+                "some answers are missing: export compiled problem to include them".to_string(),
+            )
+            .await?;
+        }
+        self.export_groups().await?;
+        let validator_flags = self.export_checker().await?;
+        self.export_solutions().await?;
+
+        let mut limits = Mapping::new();
+        limits.insert(
+            "time_limit".into(),
+            (package.limits.time() as f64 / 1000.0).into(),
+        );
+        limits.insert(
+            "memory".into(),
+            (package.limits.memory() / (1024 * 1024)).into(),
+        );
+        let mut manifest = Mapping::new();
+        manifest.insert("name".into(), package.title.clone().into());
+        let problem_type = if self.is_scoring() {
+            "scoring"
+        } else {
+            "pass-fail"
+        };
+        manifest.insert("type".into(), problem_type.into());
+        manifest.insert("limits".into(), Value::Mapping(limits));
+        manifest.insert("validation".into(), "default".into());
+        if let Some(flags) = validator_flags {
+            manifest.insert("validator_flags".into(), flags.into());
+        }
+        std::fs::write(
+            self.dest.join("problem.yaml"),
+            serde_yaml::to_string(&Value::Mapping(manifest))?,
+        )
+        .context("write problem.yaml")?;
+        Ok(())
+    }
+}
+
+pub(super) async fn export(
+    package: &Package,
+    dest: &Path,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    let mut exporter = Exporter { package, dest, tx };
+    exporter.run().await
+}
@@ -0,0 +1,312 @@
+//! Format-independent problem description, which is loaded either from
+//! problem source or from compiled problem.
+use anyhow::{bail, Context as _};
+use std::path::{Path, PathBuf};
+
+pub(super) struct Package {
+    pub(super) title: String,
+    pub(super) name: String,
+    /// Limits of the first test. Exported formats do not support per-test
+    /// limits, so they are applied to all tests.
+    pub(super) limits: pom::Limits,
+    pub(super) tests: Vec<Test>,
+    pub(super) generators: Vec<Source>,
+    pub(super) checker: Checker,
+    pub(super) solutions: Vec<Source>,
+    pub(super) primary_solution: Option<String>,
+    pub(super) valuer_cfg: Option<svaluer::Config>,
+    /// `testlib.h`, if problem contains it (e.g. it was imported from Polygon)
+    pub(super) testlib: Option<PathBuf>,
+}
+
+pub(super) enum TestInput {
+    File(PathBuf),
+    Generated {
+        generator: String,
+        args: Vec<String>,
+    },
+}
+
+pub(super) struct Test {
+    pub(super) input: TestInput,
+    /// Only available for compiled problems
+    pub(super) answer: Option<PathBuf>,
+    pub(super) group: String,
+}
+
+/// Single-file program (solution or generator)
+pub(super) struct Source {
+    pub(super) name: String,
+    pub(super) path: PathBuf,
+}
+
+impl Source {
+    /// Returns source file extension, e.g. `cpp`
+    pub(super) fn extension(&self) -> &str {
+        self.path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+    }
+}
+
+pub(super) enum Checker {
+    /// `cmp-tokens` builtin checker
+    CmpTokens {
+        epsilon: Option<String>,
+        ignore_case: bool,
+    },
+    /// Source of checker using testlib (Polygon) protocol
+    Testlib(PathBuf),
+    /// Source of checker using JTL protocol
+    Jtl(PathBuf),
+    /// Checker source is not available
+    Unknown,
+}
+
+impl Checker {
+    fn cmp_tokens(args: &[String]) -> Checker {
+        let mut epsilon = None;
+        let mut ignore_case = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--epsilon" => epsilon = args.next().cloned(),
+                "--ignore-case" => ignore_case = true,
+                _ => {}
+            }
+        }
+        Checker::CmpTokens {
+            epsilon,
+            ignore_case,
+        }
+    }
+}
+
+/// Loads problem from `path`, which must contain either `problem.toml`
+/// or `manifest.json`.
+/// `jjs_dir` is used to recognize builtin checkers in compiled problems.
+pub(super) fn load(path: &Path, jjs_dir: &Path) -> anyhow::Result<(Package, Vec<String>)> {
+    if path.join("problem.toml").exists() {
+        load_source(path)
+    } else if path.join("manifest.json").exists() {
+        load_compiled(path, jjs_dir)
+    } else {
+        bail!(
+            "{} contains neither problem.toml nor manifest.json",
+            path.display()
+        )
+    }
+}
+
+/// Finds single source file in program directory, e.g. `solutions/main`
+fn find_program_source(dir: &Path) -> Option<PathBuf> {
+    ["main.cpp", "main.py"]
+        .iter()
+        .map(|file_name| dir.join(file_name))
+        .find(|path| path.exists())
+}
+
+/// Lists programs in `dir`, skipping ones which do not consist of single
+/// source file.
+fn load_programs(dir: &Path, warnings: &mut Vec<String>) -> anyhow::Result<Vec<Source>> {
+    let mut out = Vec::new();
+    if !dir.exists() {
+        return Ok(out);
+    }
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("read dir {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("utf8 error")?
+            .to_string();
+        match find_program_source(&entry) {
+            Some(path) => out.push(Source { name, path }),
+            None => warnings.push(format!(
+                "{} skipped: only single-file C++ and Python programs are supported",
+                entry.display()
+            )),
+        }
+    }
+    Ok(out)
+}
+
+fn load_valuer_cfg(path: &Path) -> anyhow::Result<svaluer::Config> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("read valuer config {}", path.display()))?;
+    serde_yaml::from_str(&data).context("parse valuer config")
+}
+
+fn load_source(dir: &Path) -> anyhow::Result<(Package, Vec<String>)> {
+    let mut warnings = Vec::new();
+    let manifest_data =
+        std::fs::read_to_string(dir.join("problem.toml")).context("read problem.toml")?;
+    let raw_problem: crate::manifest::RawProblem =
+        toml::from_str(&manifest_data).context("problem.toml parse error")?;
+    // these warnings are about compilation, not about export
+    let (problem, _) = raw_problem.postprocess()?;
+
+    // test limits override problem limits, similarly to compilation
+    let effective_limits = |spec: &crate::manifest::TestSpec| pom::Limits {
+        time: spec.limits.time.or(problem.limits.time),
+        memory: spec.limits.memory.or(problem.limits.memory),
+        ..problem.limits
+    };
+    let limits = problem
+        .tests
+        .first()
+        .map(effective_limits)
+        .unwrap_or(problem.limits);
+    let mut tests = Vec::new();
+    let mut has_per_test_limits = false;
+    for spec in &problem.tests {
+        let input = match &spec.gen {
+            crate::manifest::TestGenSpec::File { path } => {
+                TestInput::File(dir.join("tests").join(path))
+            }
+            crate::manifest::TestGenSpec::Generate { testgen, args } => TestInput::Generated {
+                generator: testgen.clone(),
+                args: args.clone(),
+            },
+        };
+        let test_limits = effective_limits(spec);
+        has_per_test_limits |=
+            test_limits.time != limits.time || test_limits.memory != limits.memory;
+        tests.push(Test {
+            input,
+            answer: spec
+                .answer
+                .as_ref()
+                .map(|answer| dir.join("tests").join(answer)),
+            group: spec.group.clone(),
+        });
+    }
+    if has_per_test_limits {
+        warnings.push("per-test limits are not supported, limits of test 1 are used".to_string());
+    }
+    if tests.iter().any(|test| test.answer.is_none()) {
+        warnings.push(
+            "some correct answers are not stored in problem source; export compiled problem to include them"
+                .to_string(),
+        );
+    }
+
+    let checker = match &problem.check {
+        crate::manifest::Check::Builtin(bc) => match bc.name.as_str() {
+            "cmp-tokens" => Checker::cmp_tokens(&problem.check_options.args),
+            "polygon-compat" if dir.join("modules/checker/main.cpp").exists() => {
+                Checker::Testlib(dir.join("modules/checker/main.cpp"))
+            }
+            other => {
+                warnings.push(format!("builtin checker {} can not be exported", other));
+                Checker::Unknown
+            }
+        },
+        crate::manifest::Check::Custom(_) => Checker::Jtl(dir.join("checkers/main.cpp")),
+    };
+
+    let valuer_cfg = match &problem.valuer_cfg {
+        Some(path) => Some(load_valuer_cfg(&dir.join(path.trim_start_matches('/')))?),
+        None => None,
+    };
+    let testlib = Some(dir.join("testlib.h")).filter(|path| path.exists());
+
+    let package = Package {
+        title: problem.title,
+        name: problem.name,
+        limits,
+        tests,
+        generators: load_programs(&dir.join("generators"), &mut warnings)?,
+        checker,
+        solutions: load_programs(&dir.join("solutions"), &mut warnings)?,
+        primary_solution: problem.primary_solution,
+        valuer_cfg,
+        testlib,
+    };
+    Ok((package, warnings))
+}
+
+/// Checks if `checker` is a copy of builtin checker `name`
+fn is_builtin_checker(checker: &Path, jjs_dir: &Path, name: &str) -> anyhow::Result<bool> {
+    let builtin_path = jjs_dir.join(format!("bin/builtin-checker-{}", name));
+    if !builtin_path.exists() {
+        return Ok(false);
+    }
+    let checker_data = std::fs::read(checker).context("read checker")?;
+    let builtin_data = std::fs::read(&builtin_path).context("read builtin checker")?;
+    Ok(checker_data == builtin_data)
+}
+
+fn load_compiled(dir: &Path, jjs_dir: &Path) -> anyhow::Result<(Package, Vec<String>)> {
+    let mut warnings = Vec::new();
+    let manifest_data =
+        std::fs::read_to_string(dir.join("manifest.json")).context("read manifest.json")?;
+    let problem: pom::Problem =
+        serde_json::from_str(&manifest_data).context("manifest.json parse error")?;
+    let resolve = |file_ref: &pom::FileRef| match file_ref.root {
+        pom::FileRefRoot::Problem => dir.join("assets").join(&file_ref.path),
+        pom::FileRefRoot::Root => Path::new("/").join(&file_ref.path),
+    };
+
+    let limits = problem
+        .tests
+        .first()
+        .map(|test| test.limits)
+        .unwrap_or_default();
+    if problem
+        .tests
+        .iter()
+        .any(|test| test.limits.time != limits.time || test.limits.memory != limits.memory)
+    {
+        warnings.push("per-test limits are not supported, limits of test 1 are used".to_string());
+    }
+    let tests = problem
+        .tests
+        .iter()
+        .map(|test| Test {
+            input: TestInput::File(resolve(&test.path)),
+            answer: test.correct.as_ref().map(resolve),
+            group: test.group.clone(),
+        })
+        .collect();
+
+    let checker_path = resolve(&problem.checker_exe);
+    let checker = if is_builtin_checker(&checker_path, jjs_dir, "cmp-tokens")? {
+        Checker::cmp_tokens(&problem.checker_cmd)
+    } else {
+        warnings.push(
+            "compiled problem does not contain checker source; checker is not exported".to_string(),
+        );
+        Checker::Unknown
+    };
+    warnings.push(
+        "compiled problem does not contain solutions; export problem source to include them"
+            .to_string(),
+    );
+
+    let valuer_cfg_path = resolve(&problem.valuer_cfg).join("cfg.yaml");
+    let valuer_cfg = if valuer_cfg_path.exists() {
+        Some(load_valuer_cfg(&valuer_cfg_path)?)
+    } else {
+        None
+    };
+
+    let package = Package {
+        title: problem.title,
+        name: problem.name,
+        limits,
+        tests,
+        generators: Vec::new(),
+        checker,
+        solutions: Vec::new(),
+        primary_solution: None,
+        valuer_cfg,
+        testlib: None,
+    };
+    Ok((package, warnings))
+}
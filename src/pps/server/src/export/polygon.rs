@@ -0,0 +1,374 @@
+//! Writes Polygon package (`problem.xml`).
+//!
+//! Produced package can be imported back with `pps import`.
+use super::package::{Checker, Package, Source, TestInput};
+use anyhow::Context as _;
+use pps_api::{export_problem::Update, SimpleFinish};
+use std::{collections::HashMap, fmt::Write as _, path::Path};
+
+static CMP_TOKENS_TPL: &str = include_str!("cmp_tokens_testlib.cpp");
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns Polygon source type for program
+fn source_type(source: &Source) -> anyhow::Result<&'static str> {
+    match source.extension() {
+        "cpp" => Ok("cpp.g++17"),
+        "py" => Ok("python.3"),
+        other => anyhow::bail!("unsupported source type: {}", other),
+    }
+}
+
+struct Exporter<'a> {
+    package: &'a Package,
+    dest: &'a Path,
+    tx: &'a mut rpc::StreamingTx<Update, SimpleFinish>,
+    /// Maps svaluer group name to Polygon group number
+    group_ids: HashMap<&'a str, usize>,
+}
+
+impl<'a> Exporter<'a> {
+    async fn warn(&mut self, message: String) -> anyhow::Result<()> {
+        self.tx.send_event(Update::Warning(message)).await?;
+        Ok(())
+    }
+
+    fn copy(&self, src: &Path, dest: impl AsRef<Path>) -> anyhow::Result<()> {
+        let dest = self.dest.join(dest);
+        std::fs::copy(src, &dest)
+            .with_context(|| format!("copy {} to {}", src.display(), dest.display()))
+            .map(drop)
+    }
+
+    /// Exports tests and returns `<tests>` element
+    async fn export_tests(&mut self) -> anyhow::Result<String> {
+        let package = self.package;
+        self.tx
+            .send_event(Update::ExportTests {
+                count: package.tests.len(),
+            })
+            .await?;
+        let mut xml = String::new();
+        writeln!(xml, "      <tests>")?;
+        for (i, test) in package.tests.iter().enumerate() {
+            let tid = i + 1;
+            let group = match self.group_ids.get(test.group.as_str()) {
+                Some(id) => format!(" group=\"{}\"", id),
+                None => String::new(),
+            };
+            match &test.input {
+                TestInput::File(path) => {
+                    self.copy(path, format!("tests/{:0>2}", tid))?;
+                    writeln!(xml, "        <test method=\"manual\"{}/>", group)?;
+                }
+                TestInput::Generated { generator, args } => {
+                    let cmd = std::iter::once(generator)
+                        .chain(args)
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    writeln!(
+                        xml,
+                        "        <test cmd=\"{}\" method=\"generated\"{}/>",
+                        escape(&cmd),
+                        group
+                    )?;
+                }
+            }
+            if let Some(answer) = &test.answer {
+                self.copy(answer, format!("tests/{:0>2}.a", tid))?;
+            }
+        }
+        writeln!(xml, "      </tests>")?;
+        Ok(xml)
+    }
+
+    /// Writes `files/valuer.cfg` and returns `<groups>` element
+    async fn export_groups(&mut self) -> anyhow::Result<String> {
+        let package = self.package;
+        let config = match &package.valuer_cfg {
+            Some(cfg) => cfg,
+            None => return Ok(String::new()),
+        };
+        self.tx.send_event(Update::ExportValuerConfig).await?;
+        let mut xml = String::new();
+        let mut valuer_cfg = String::new();
+        writeln!(xml, "      <groups>")?;
+        for (id, group) in config.groups.iter().enumerate() {
+            let feedback_policy = match group.feedback {
+                svaluer::cfg::FeedbackKind::Hidden => "none",
+                svaluer::cfg::FeedbackKind::Brief => "points",
+                svaluer::cfg::FeedbackKind::Full => "complete",
+            };
+            let mut deps = Vec::new();
+            for dep in &group.deps {
+                match config.get_group(dep) {
+                    Some(dep_id) => deps.push(dep_id),
+                    None => {
+                        self.warn(format!(
+                            "group {} refers to nonexistent group, dependency skipped",
+                            group.name
+                        ))
+                        .await?
+                    }
+                }
+            }
+            write!(
+                xml,
+                "        <group feedback-policy=\"{}\" name=\"{}\" points=\"{}\" points-policy=\"complete-group\"",
+                feedback_policy, id, group.score
+            )?;
+            if deps.is_empty() {
+                writeln!(xml, "/>")?;
+            } else {
+                writeln!(xml, ">")?;
+                writeln!(xml, "          <dependencies>")?;
+                for dep in &deps {
+                    writeln!(xml, "            <dependency group=\"{}\"/>", dep)?;
+                }
+                writeln!(xml, "          </dependencies>")?;
+                writeln!(xml, "        </group>")?;
+            }
+
+            // valuer.cfg can only express contiguous test ranges
+            let tests: Vec<_> = (1..=package.tests.len())
+                .filter(|&tid| package.tests[tid - 1].group == group.tests_tag())
+                .collect();
+            writeln!(valuer_cfg, "group {} {{", id)?;
+            if let (Some(first), Some(last)) = (tests.first(), tests.last()) {
+                if last - first + 1 != tests.len() {
+                    self.warn(format!(
+                        "tests of group {} are not contiguous: valuer.cfg will be incorrect",
+                        group.name
+                    ))
+                    .await?;
+                }
+                writeln!(valuer_cfg, "    tests {}-{};", first, last)?;
+            }
+            writeln!(valuer_cfg, "    score {};", group.score)?;
+            if !deps.is_empty() {
+                let deps = deps
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(valuer_cfg, "    requires {};", deps)?;
+            }
+            if let svaluer::cfg::FeedbackKind::Hidden = group.feedback {
+                writeln!(valuer_cfg, "    offline;")?;
+            }
+            writeln!(valuer_cfg, "}}")?;
+        }
+        writeln!(xml, "      </groups>")?;
+        if config
+            .groups
+            .iter()
+            .enumerate()
+            .any(|(id, g)| g.name != format!("g{}", id))
+        {
+            self.warn("group names are replaced with their numbers".to_string())
+                .await?;
+        }
+        std::fs::write(self.dest.join("files/valuer.cfg"), valuer_cfg)
+            .context("write valuer.cfg")?;
+        Ok(xml)
+    }
+
+    /// Exports checker and returns `<checker>` element
+    async fn export_checker(&mut self) -> anyhow::Result<String> {
+        let package = self.package;
+        self.tx.send_event(Update::ExportChecker).await?;
+        let dest_path = self.dest.join("files/check.cpp");
+        match &package.checker {
+            Checker::CmpTokens {
+                epsilon,
+                ignore_case,
+            } => {
+                let source = CMP_TOKENS_TPL
+                    .replace("$EPSILON", epsilon.as_deref().unwrap_or("-1"))
+                    .replace("$IGNORE_CASE", if *ignore_case { "true" } else { "false" });
+                std::fs::write(&dest_path, source).context("write checker")?;
+            }
+            Checker::Testlib(path) => {
+                self.copy(path, "files/check.cpp")?;
+            }
+            Checker::Jtl(path) => {
+                self.copy(path, "files/check.cpp")?;
+                self.warn(
+                    "checker uses JTL protocol; it must be ported to testlib manually".to_string(),
+                )
+                .await?;
+            }
+            Checker::Unknown => return Ok(String::new()),
+        }
+        if package.testlib.is_none() {
+            self.warn("testlib.h is not available: add it to files/ manually".to_string())
+                .await?;
+        }
+        let mut xml = String::new();
+        writeln!(xml, "    <checker type=\"testlib\">")?;
+        writeln!(
+            xml,
+            "      <source path=\"files/check.cpp\" type=\"cpp.g++17\"/>"
+        )?;
+        writeln!(xml, "    </checker>")?;
+        Ok(xml)
+    }
+
+    /// Exports solutions and returns `<solutions>` element
+    async fn export_solutions(&mut self) -> anyhow::Result<String> {
+        let package = self.package;
+        let mut xml = String::new();
+        writeln!(xml, "    <solutions>")?;
+        for solution in &package.solutions {
+            self.tx
+                .send_event(Update::ExportSolution(solution.name.clone()))
+                .await?;
+            let is_main = package.primary_solution.as_ref() == Some(&solution.name);
+            let tag = if is_main { "main" } else { "accepted" };
+            if !is_main {
+                self.warn(format!(
+                    "solution {} is marked as accepted: expected verdict is unknown",
+                    solution.name
+                ))
+                .await?;
+            }
+            let path = format!("solutions/{}.{}", solution.name, solution.extension());
+            self.copy(&solution.path, &path)?;
+            writeln!(xml, "      <solution tag=\"{}\">", tag)?;
+            writeln!(
+                xml,
+                "        <source path=\"{}\" type=\"{}\"/>",
+                escape(&path),
+                source_type(solution)?
+            )?;
+            writeln!(xml, "      </solution>")?;
+        }
+        writeln!(xml, "    </solutions>")?;
+        Ok(xml)
+    }
+
+    /// Exports testlib and generators and returns `<files>` element
+    fn export_files(&mut self) -> anyhow::Result<String> {
+        let package = self.package;
+        let mut xml = String::new();
+        writeln!(xml, "  <files>")?;
+        if let Some(testlib) = &package.testlib {
+            self.copy(testlib, "files/testlib.h")?;
+            writeln!(xml, "    <resources>")?;
+            writeln!(xml, "      <file path=\"files/testlib.h\" type=\"h.g++\"/>")?;
+            writeln!(xml, "    </resources>")?;
+        }
+        writeln!(xml, "    <executables>")?;
+        for generator in &package.generators {
+            let path = format!("files/{}.{}", generator.name, generator.extension());
+            self.copy(&generator.path, &path)?;
+            writeln!(xml, "      <executable>")?;
+            writeln!(
+                xml,
+                "        <source path=\"{}\" type=\"{}\"/>",
+                escape(&path),
+                source_type(generator)?
+            )?;
+            writeln!(xml, "      </executable>")?;
+        }
+        writeln!(xml, "    </executables>")?;
+        writeln!(xml, "  </files>")?;
+        Ok(xml)
+    }
+
+    async fn run(&mut self) -> anyhow::Result<()> {
+        for suf in &["files", "tests", "solutions"] {
+            let path = self.dest.join(suf);
+            std::fs::create_dir(&path).with_context(|| format!("create {}", path.display()))?;
+        }
+        let package = self.package;
+        if let Some(cfg) = &package.valuer_cfg {
+            self.group_ids = cfg
+                .groups
+                .iter()
+                .enumerate()
+                .map(|(id, group)| (group.tests_tag(), id))
+                .collect();
+        }
+        let tests = self.export_tests().await?;
+        let groups = self.export_groups().await?;
+        let files = self.export_files()?;
+        let checker = self.export_checker().await?;
+        let solutions = self.export_solutions().await?;
+
+        let mut xml = String::new();
+        writeln!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"no\"?>"
+        )?;
+        writeln!(
+            xml,
+            "<problem revision=\"1\" short-name=\"{}\">",
+            escape(&package.name)
+        )?;
+        writeln!(xml, "  <names>")?;
+        writeln!(
+            xml,
+            "    <name language=\"english\" value=\"{}\"/>",
+            escape(&package.title)
+        )?;
+        writeln!(xml, "  </names>")?;
+        writeln!(xml, "  <judging input-file=\"\" output-file=\"\">")?;
+        writeln!(xml, "    <testset name=\"tests\">")?;
+        writeln!(
+            xml,
+            "      <time-limit>{}</time-limit>",
+            package.limits.time()
+        )?;
+        writeln!(
+            xml,
+            "      <memory-limit>{}</memory-limit>",
+            package.limits.memory()
+        )?;
+        writeln!(
+            xml,
+            "      <test-count>{}</test-count>",
+            package.tests.len()
+        )?;
+        writeln!(
+            xml,
+            "      <input-path-pattern>tests/%02d</input-path-pattern>"
+        )?;
+        writeln!(
+            xml,
+            "      <answer-path-pattern>tests/%02d.a</answer-path-pattern>"
+        )?;
+        xml.push_str(&tests);
+        xml.push_str(&groups);
+        writeln!(xml, "    </testset>")?;
+        writeln!(xml, "  </judging>")?;
+        xml.push_str(&files);
+        writeln!(xml, "  <assets>")?;
+        xml.push_str(&checker);
+        xml.push_str(&solutions);
+        writeln!(xml, "  </assets>")?;
+        writeln!(xml, "</problem>")?;
+        std::fs::write(self.dest.join("problem.xml"), xml).context("write problem.xml")?;
+        Ok(())
+    }
+}
+
+pub(super) async fn export(
+    package: &Package,
+    dest: &Path,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    let mut exporter = Exporter {
+        package,
+        dest,
+        tx,
+        group_ids: HashMap::new(),
+    };
+    exporter.run().await
+}
@@ -1,14 +1,20 @@
+mod contest_import;
+mod ejudge_import;
+mod kattis_import;
 mod problem_importer;
 mod template;
 mod valuer_cfg;
 
 use anyhow::{bail, Context as _};
 use pps_api::{
-    import_problem::{Request, Update},
+    import_problem::{PropertyName, Request, Update},
     ImportProblem, SimpleFinish,
 };
 use problem_importer::Importer;
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+};
 
 impl rpc::Handler<ImportProblem> for crate::Service {
     type Error = anyhow::Error;
@@ -33,14 +39,201 @@ pub(crate) async fn execute_import_request(
     tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
 ) -> anyhow::Result<()> {
     match detect_import_kind(&req.src_path)? {
-        ImportKind::Problem => (),
-        ImportKind::Contest => anyhow::bail!("TODO"),
+        ImportKind::Problem => import_problem(&req.src_path, &req.out_path, tx).await?,
+        ImportKind::KattisProblem => {
+            import_kattis_problem(&req.src_path, &req.out_path, tx).await?
+        }
+        ImportKind::Contest => {
+            import_contest(
+                &req.src_path,
+                &req.out_path,
+                req.contest_name.as_deref(),
+                tx,
+            )
+            .await?
+        }
+        ImportKind::EjudgeContest(serve_cfg_path) => {
+            import_ejudge_contest(
+                &req.src_path,
+                &serve_cfg_path,
+                &req.out_path,
+                req.contest_name.as_deref(),
+                tx,
+            )
+            .await?
+        }
     }
-    import_problem(&req.src_path, &req.out_path, tx).await?;
 
     Ok(())
 }
 
+/// Imports Polygon contest.
+///
+/// Problems are placed into `dest/problems/<problem name>`, and contest
+/// description is written to `dest/contest.yaml`.
+pub(crate) async fn import_contest(
+    src: &Path,
+    dest: &Path,
+    contest_name: Option<&str>,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    let contest_name = derive_contest_name(src, contest_name)?;
+    let mut contest = contest_import::import(&src.join("contest.xml"), &contest_name)
+        .context("failed to import contest.xml")?;
+    tx.send_event(Update::Property {
+        property_name: PropertyName::ContestTitle,
+        property_value: contest.title.clone(),
+    })
+    .await?;
+
+    let problems_dir = dest.join("problems");
+    tokio::fs::create_dir_all(&problems_dir)
+        .await
+        .context("create problems dir")?;
+    let problem_count = contest.problems.len();
+    contest_import::import_problems(&src.join("problems"), &problems_dir, &mut contest, tx).await?;
+    if problem_count != 0 && contest.problems.is_empty() {
+        bail!("all {} problems failed to import", problem_count);
+    }
+
+    write_contest(dest, &contest).await
+}
+
+/// Imports contest in ejudge `serve.cfg` layout.
+///
+/// Output layout is the same as for Polygon contests.
+pub(crate) async fn import_ejudge_contest(
+    src: &Path,
+    serve_cfg_path: &Path,
+    dest: &Path,
+    contest_name: Option<&str>,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    let contest_name = derive_contest_name(src, contest_name)?;
+    tx.send_event(Update::Property {
+        property_name: PropertyName::ContestTitle,
+        property_value: contest_name.clone(),
+    })
+    .await?;
+    let problems_dir = dest.join("problems");
+    tokio::fs::create_dir_all(&problems_dir)
+        .await
+        .context("create problems dir")?;
+    let contest = ejudge_import::import(src, serve_cfg_path, &problems_dir, &contest_name, tx)
+        .await
+        .context("failed to import serve.cfg")?;
+    if contest.problems.is_empty() {
+        bail!("no problems were imported");
+    }
+    write_contest(dest, &contest).await
+}
+
+fn derive_contest_name(src: &Path, contest_name: Option<&str>) -> anyhow::Result<String> {
+    match contest_name {
+        Some(name) => Ok(name.to_string()),
+        None => Ok(src
+            .canonicalize()
+            .context("resolve contest dir")?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("contest name was not provided and can not be derived from path")?
+            .to_string()),
+    }
+}
+
+/// Checks that `name` taken from imported config is a single path component,
+/// so it can be used as a directory name without escaping the parent.
+fn check_file_name(name: &str) -> anyhow::Result<()> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => bail!("{:?} is not a valid file name", name),
+    }
+}
+
+/// Resolves relative `path` taken from imported config against `base`, which
+/// is itself relative to `root`. Fails if `path` is absolute or leads outside
+/// of `root`.
+fn join_confined(root: &Path, base: &Path, path: &str) -> anyhow::Result<PathBuf> {
+    let mut parts = Vec::new();
+    for component in base.components().chain(Path::new(path).components()) {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    bail!("path {:?} leads outside of {}", path, root.display());
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                bail!("path {:?} must be relative", path)
+            }
+        }
+    }
+    Ok(parts
+        .into_iter()
+        .fold(root.to_path_buf(), |acc, part| acc.join(part)))
+}
+
+async fn write_contest(dest: &Path, contest: &entity::Contest) -> anyhow::Result<()> {
+    let contest_data = serde_yaml::to_string(contest).context("serialize contest")?;
+    tokio::fs::write(dest.join("contest.yaml"), contest_data)
+        .await
+        .context("write contest.yaml")?;
+    Ok(())
+}
+
+/// Writes `problem.toml` into `dest`
+async fn write_manifest(
+    dest: &Path,
+    problem_cfg: &crate::manifest::RawProblem,
+) -> anyhow::Result<()> {
+    let manifest_toml =
+        toml::Value::try_from(problem_cfg.clone()).context("serialize ppc config")?;
+    let manifest_data = toml::ser::to_string_pretty(&manifest_toml)
+        .with_context(|| format!("stringify ppc config: {:#?}", problem_cfg))?;
+    tokio::fs::write(dest.join("problem.toml"), manifest_data)
+        .await
+        .context("write ppc manifest")?;
+    Ok(())
+}
+
+/// Generates value for `random-seed` manifest field
+fn random_seed() -> anyhow::Result<String> {
+    let mut random_seed = [0; 8];
+    getrandom::getrandom(&mut random_seed)?;
+    let rand_seed_hex = hex::encode(&random_seed);
+    assert_eq!(rand_seed_hex.len(), crate::manifest::RANDOM_SEED_LENGTH);
+    Ok(rand_seed_hex)
+}
+
+/// Copies single-file solution `src_file` to `dest/solutions/main`.
+/// C++ and Python sources are supported.
+async fn import_main_solution(src_file: &Path, dest: &Path) -> anyhow::Result<()> {
+    let dir = dest.join("solutions/main");
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .context("create main solution dir")?;
+    let extension = src_file.extension().and_then(|ext| ext.to_str());
+    let file_name = match extension {
+        Some("cpp") | Some("cc") | Some("cxx") => "main.cpp",
+        Some("py") => "main.py",
+        _ => bail!("unsupported solution language: {}", src_file.display()),
+    };
+    tokio::fs::copy(src_file, dir.join(file_name))
+        .await
+        .with_context(|| format!("copy solution {}", src_file.display()))?;
+    if file_name == "main.cpp" {
+        tokio::fs::write(
+            dir.join("CMakeLists.txt"),
+            include_str!("./import/solution.cmake"),
+        )
+        .await
+        .context("write CMakeLists.txt for solution")?;
+    }
+    Ok(())
+}
+
 pub(crate) async fn import_problem(
     src: &Path,
     dest: &Path,
@@ -61,20 +254,34 @@ pub(crate) async fn import_problem(
     };
 
     importer.run().await?;
+    write_manifest(dest, &importer.problem_cfg).await
+}
 
-    let manifest_path = dest.join("problem.toml");
-    let manifest_toml =
-        toml::Value::try_from(importer.problem_cfg.clone()).context("serialize ppc config")?;
-    let manifest_data = toml::ser::to_string_pretty(&manifest_toml)
-        .with_context(|| format!("stringify ppc config: {:#?}", &importer.problem_cfg))?;
-    std::fs::write(manifest_path, manifest_data).expect("write ppc manifest");
-
-    Ok(())
+/// Imports problem from Kattis `problem.yaml` package
+pub(crate) async fn import_kattis_problem(
+    src: &Path,
+    dest: &Path,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    let mut importer = kattis_import::Importer {
+        src,
+        dest,
+        problem_cfg: Default::default(),
+        tx,
+    };
+    importer.run().await?;
+    write_manifest(dest, &importer.problem_cfg).await
 }
 
 enum ImportKind {
+    /// Polygon problem package
     Problem,
+    /// Kattis problem package
+    KattisProblem,
+    /// Polygon contest package
     Contest,
+    /// ejudge contest; contains path to `serve.cfg`
+    EjudgeContest(PathBuf),
 }
 
 fn detect_import_kind(path: &Path) -> anyhow::Result<ImportKind> {
@@ -85,9 +292,17 @@ fn detect_import_kind(path: &Path) -> anyhow::Result<ImportKind> {
     if path.join("problem.xml").exists() {
         return Ok(ImportKind::Problem);
     }
+    if path.join("problem.yaml").exists() {
+        return Ok(ImportKind::KattisProblem);
+    }
     if path.join("contest.xml").exists() {
         return Ok(ImportKind::Contest);
     }
+    for serve_cfg_path in &[path.join("conf/serve.cfg"), path.join("serve.cfg")] {
+        if serve_cfg_path.exists() {
+            return Ok(ImportKind::EjudgeContest(serve_cfg_path.clone()));
+        }
+    }
 
     bail!("unknown src")
 }
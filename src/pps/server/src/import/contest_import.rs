@@ -1,4 +1,8 @@
-// TODO revive when contests are reintroduced
+//! Imports contest from Polygon contest package.
+//!
+//! Such package contains `contest.xml` and `problems` directory, which in
+//! turn contains one problem package per problem.
+use pps_api::{import_problem::Update, SimpleFinish};
 use std::{borrow::Cow, path::Path};
 use thiserror::Error;
 #[derive(Error, Debug)]
@@ -7,7 +11,7 @@ pub(super) enum ImportContestError {
     Io(#[from] std::io::Error),
     #[error("syntax error: {0}")]
     XmlSyn(#[from] roxmltree::Error),
-    #[error("bad context.xml: {0}")]
+    #[error("bad contest.xml: {0}")]
     BadManifest(Cow<'static, str>),
 }
 
@@ -21,9 +25,16 @@ fn go(node: roxmltree::Node, cfg: &mut entity::Contest) -> Result<(), ImportCont
                 ImportContestError::BadManifest("url attribute missing in <problem />".into())
             })?;
             let problem_name = url
+                .trim_end_matches('/')
                 .rsplit('/')
                 .next()
                 .expect("rsplit() should never be empty");
+            // problem name is used as directory name
+            if let Err(err) = super::check_file_name(problem_name) {
+                return Err(ImportContestError::BadManifest(
+                    format!("problem url {}: {:#}", url, err).into(),
+                ));
+            }
             let binding = entity::entities::contest::ProblemBinding {
                 name: problem_name.to_string(),
                 code: index.to_string(),
@@ -31,6 +42,15 @@ fn go(node: roxmltree::Node, cfg: &mut entity::Contest) -> Result<(), ImportCont
             cfg.problems.push(binding);
             Ok(())
         }
+        "name" => {
+            // first name wins, similarly to problem import
+            if cfg.title.is_empty() {
+                if let Some(title) = node.attribute("value") {
+                    cfg.title = title.to_string();
+                }
+            }
+            Ok(())
+        }
         _ => {
             for child in node.children() {
                 go(child, cfg)?;
@@ -40,6 +60,7 @@ fn go(node: roxmltree::Node, cfg: &mut entity::Contest) -> Result<(), ImportCont
     }
 }
 
+/// Parses `contest.xml`
 pub(super) fn import(
     path: &Path,
     contest_name: &str,
@@ -62,3 +83,51 @@ pub(super) fn import(
     go(doc.root(), &mut cfg)?;
     Ok(cfg)
 }
+
+/// Imports all problems referenced by `contest`.
+/// Each problem is imported into `dest/<problem name>`.
+///
+/// Problems that failed to import are reported as warnings and removed
+/// from the contest.
+pub(super) async fn import_problems(
+    src: &Path,
+    dest: &Path,
+    contest: &mut entity::Contest,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<()> {
+    tx.send_event(Update::ImportContestProblems {
+        count: contest.problems.len(),
+    })
+    .await?;
+    let mut imported = Vec::new();
+    for binding in std::mem::take(&mut contest.problems) {
+        tx.send_event(Update::ImportContestProblem {
+            code: binding.code.clone(),
+            name: binding.name.clone(),
+        })
+        .await?;
+        let problem_dest = dest.join(&binding.name);
+        let res = match tokio::fs::create_dir_all(&problem_dest).await {
+            Ok(_) => super::import_problem(&src.join(&binding.name), &problem_dest, tx).await,
+            Err(err) => Err(anyhow::Error::new(err).context("failed to create problem dir")),
+        };
+        let error = res.err().map(|err| format!("{:#}", err));
+        if let Some(err) = &error {
+            let message = format!(
+                "problem {} ({}) skipped: {}",
+                binding.code, binding.name, err
+            );
+            tx.send_event(Update::Warning(message)).await?;
+        }
+        tx.send_event(Update::ContestProblemDone {
+            code: binding.code.clone(),
+            error: error.clone(),
+        })
+        .await?;
+        if error.is_none() {
+            imported.push(binding);
+        }
+    }
+    contest.problems = imported;
+    Ok(())
+}
@@ -0,0 +1,623 @@
+//! Imports contest from ejudge `serve.cfg` layout.
+//!
+//! Only problem-related parts of `serve.cfg` are used: each non-abstract
+//! `[problem]` section is imported as separate problem. Both classic
+//! (`tests/<problem>`, `checkers/`) and advanced (`problems/<problem>`)
+//! layouts are supported.
+use super::valuer_cfg;
+use anyhow::{bail, Context as _};
+use pps_api::{
+    import_problem::{PropertyName, Update},
+    SimpleFinish,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Group of tests which are not assigned to any group by valuer config
+const DEFAULT_GROUP: &str = "default";
+
+/// Single `[section]` of `serve.cfg`.
+/// Options without value (flags) are stored with empty value.
+#[derive(Debug, Default, Clone)]
+struct Section {
+    name: String,
+    options: HashMap<String, String>,
+}
+
+impl Section {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// Returns true if flag is set, e.g. `abstract` or `use_stdin = 1`
+    fn flag(&self, key: &str) -> bool {
+        match self.get(key) {
+            Some(val) => val.is_empty() || val != "0",
+            None => false,
+        }
+    }
+}
+
+fn parse_serve_cfg(data: &str) -> anyhow::Result<Vec<Section>> {
+    // options before first section header belong to global section
+    let mut sections = vec![Section::default()];
+    for (line_no, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let name = line
+                .strip_prefix('[')
+                .and_then(|l| l.strip_suffix(']'))
+                .with_context(|| format!("line {}: unterminated section header", line_no + 1))?;
+            sections.push(Section {
+                name: name.trim().to_string(),
+                options: HashMap::new(),
+            });
+            continue;
+        }
+        let (key, value) = match line.find('=') {
+            Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+            None => (line, ""),
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        sections
+            .last_mut()
+            .expect("sections are never empty")
+            .options
+            .insert(key.to_string(), value.to_string());
+    }
+    Ok(sections)
+}
+
+/// Parses size, e.g. `256M` or `65536K`, into bytes
+fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let (num, mult) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&s[..s.len() - 1], 1 << 10),
+        Some('M') => (&s[..s.len() - 1], 1 << 20),
+        Some('G') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    let num: u64 = num
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid size: {}", s))?;
+    Ok(num * mult)
+}
+
+/// Formats printf-style `pattern` with test number
+fn format_pattern(pattern: &str, test_id: u32) -> anyhow::Result<String> {
+    let res = formatf::format(
+        pattern.as_bytes(),
+        &[formatf::Value::Int(i128::from(test_id))],
+    )
+    .map_err(|err| anyhow::anyhow!("invalid pattern {}: {:?}", pattern, err))?;
+    String::from_utf8(res).context("interpolation provided non-utf8 data")
+}
+
+/// Resolves `super` references: options of abstract problem are
+/// inherited by concrete problem, unless overridden.
+fn resolve_problems(sections: &[Section]) -> anyhow::Result<Vec<Section>> {
+    let problems = sections.iter().filter(|s| s.name == "problem");
+    let mut abstract_problems = HashMap::new();
+    let mut concrete_problems = Vec::new();
+    for problem in problems {
+        if problem.flag("abstract") {
+            let name = problem
+                .get("short_name")
+                .context("abstract problem does not have short_name")?;
+            abstract_problems.insert(name.to_string(), problem);
+        } else {
+            concrete_problems.push(problem);
+        }
+    }
+    let mut out = Vec::new();
+    for problem in concrete_problems {
+        let mut resolved = match problem.get("super") {
+            Some(sup) => (*abstract_problems
+                .get(sup)
+                .with_context(|| format!("unknown super-problem: {}", sup))?)
+            .clone(),
+            None => Section::default(),
+        };
+        resolved.options.remove("abstract");
+        resolved.name = problem.name.clone();
+        resolved.options.extend(problem.options.clone());
+        out.push(resolved);
+    }
+    Ok(out)
+}
+
+struct ProblemImporter<'a> {
+    /// Contest root (directory containing `conf`)
+    root: &'a Path,
+    global: &'a Section,
+    problem: &'a Section,
+    dest: &'a Path,
+    problem_cfg: crate::manifest::RawProblem,
+    tx: &'a mut rpc::StreamingTx<Update, SimpleFinish>,
+}
+
+impl<'a> ProblemImporter<'a> {
+    async fn warn(&mut self, message: String) -> anyhow::Result<()> {
+        self.tx.send_event(Update::Warning(message)).await?;
+        Ok(())
+    }
+
+    fn short_name(&self) -> &'a str {
+        self.problem.get("short_name").unwrap_or_default()
+    }
+
+    fn internal_name(&self) -> &'a str {
+        self.problem
+            .get("internal_name")
+            .unwrap_or_else(|| self.short_name())
+    }
+
+    /// Directory with problem-specific files
+    fn problem_dir(&self) -> anyhow::Result<PathBuf> {
+        if self.global.flag("advanced_layout") {
+            let problems_dir = self.global.get("problems_dir").unwrap_or("problems");
+            let problem_dir = self
+                .problem
+                .get("problem_dir")
+                .unwrap_or(self.internal_name());
+            let problems_dir = super::join_confined(self.root, Path::new(""), problems_dir)
+                .context("invalid problems_dir")?;
+            super::join_confined(&problems_dir, Path::new(""), problem_dir)
+                .context("invalid problem_dir")
+        } else {
+            Ok(self.root.to_path_buf())
+        }
+    }
+
+    /// Looks up option in problem section, falling back to global section
+    fn option(&self, key: &str) -> Option<&'a str> {
+        self.problem.get(key).or_else(|| self.global.get(key))
+    }
+
+    fn tests_dir(&self) -> anyhow::Result<PathBuf> {
+        let test_dir = if self.global.flag("advanced_layout") {
+            let test_dir = self.problem.get("test_dir").unwrap_or("tests");
+            super::join_confined(&self.problem_dir()?, Path::new(""), test_dir)
+        } else {
+            // global test_dir is relative to conf directory
+            let tests_root = self.global.get("test_dir").unwrap_or("../tests");
+            let tests_root = super::join_confined(self.root, Path::new("conf"), tests_root)
+                .context("invalid global test_dir")?;
+            let test_dir = self.problem.get("test_dir").unwrap_or(self.short_name());
+            super::join_confined(&tests_root, Path::new(""), test_dir)
+        };
+        test_dir.context("invalid test_dir")
+    }
+
+    async fn import_limits(&mut self) -> anyhow::Result<()> {
+        let time_limit = match (self.option("time_limit_millis"), self.option("time_limit")) {
+            (Some(ms), _) => Some(ms.parse::<u64>().context("invalid time_limit_millis")?),
+            (None, Some(s)) => Some(s.parse::<u64>().context("invalid time_limit")? * 1000),
+            (None, None) => None,
+        };
+        if let Some(tl) = time_limit {
+            self.tx
+                .send_event(Update::Property {
+                    property_name: PropertyName::TimeLimit,
+                    property_value: tl.to_string(),
+                })
+                .await?;
+            self.problem_cfg.limits.time.replace(tl);
+        }
+        if let Some(ml) = self.option("max_vm_size") {
+            let ml = parse_size(ml).context("invalid max_vm_size")?;
+            self.tx
+                .send_event(Update::Property {
+                    property_name: PropertyName::MemoryLimit,
+                    property_value: ml.to_string(),
+                })
+                .await?;
+            self.problem_cfg.limits.memory.replace(ml);
+        }
+        Ok(())
+    }
+
+    /// Copies tests and returns their count
+    async fn import_tests(&mut self, test_groups: &HashMap<u32, String>) -> anyhow::Result<u32> {
+        self.tx.send_event(Update::ImportTests).await?;
+        let test_pat = match (self.option("test_pat"), self.option("test_sfx")) {
+            (Some(pat), _) => pat.to_string(),
+            (None, Some(sfx)) => format!("%03d{}", sfx),
+            (None, None) => "%03d.dat".to_string(),
+        };
+        self.tx
+            .send_event(Update::Property {
+                property_name: PropertyName::InputPathPattern,
+                property_value: test_pat.clone(),
+            })
+            .await?;
+        let corr_pat = if self.problem.flag("use_corr") {
+            let corr_pat = match (self.option("corr_pat"), self.option("corr_sfx")) {
+                (Some(pat), _) => pat.to_string(),
+                (None, Some(sfx)) => format!("%03d{}", sfx),
+                (None, None) => "%03d.ans".to_string(),
+            };
+            self.tx
+                .send_event(Update::Property {
+                    property_name: PropertyName::OutputPathPattern,
+                    property_value: corr_pat.clone(),
+                })
+                .await?;
+            Some(corr_pat)
+        } else {
+            None
+        };
+        let tests_dir = self.tests_dir()?;
+        let mut cnt = 0;
+        loop {
+            let src_path = tests_dir.join(format_pattern(&test_pat, cnt + 1)?);
+            if !src_path.exists() {
+                break;
+            }
+            cnt += 1;
+            let dest_path = self.dest.join(format!("tests/{:0>3}.txt", cnt));
+            tokio::fs::copy(&src_path, &dest_path)
+                .await
+                .with_context(|| format!("copy test {}", src_path.display()))?;
+            if let Some(corr_pat) = &corr_pat {
+                let corr_path = tests_dir.join(format_pattern(corr_pat, cnt)?);
+                tokio::fs::copy(&corr_path, self.dest.join(format!("tests/{:0>3}.ans", cnt)))
+                    .await
+                    .with_context(|| format!("copy correct answer {}", corr_path.display()))?;
+            }
+            self.problem_cfg.tests.push(crate::manifest::RawTestsSpec {
+                map: cnt.to_string(),
+                testgen: None,
+                files: Some("%03d.txt".to_string()),
+                answers: corr_pat.as_ref().map(|_| "%03d.ans".to_string()),
+                limits: self.problem_cfg.limits,
+                group: test_groups
+                    .get(&cnt)
+                    .cloned()
+                    .unwrap_or_else(|| DEFAULT_GROUP.to_string()),
+            });
+        }
+        if cnt == 0 {
+            bail!("no tests found in {}", tests_dir.display());
+        }
+        self.tx
+            .send_event(Update::ImportTestsDone {
+                count: cnt as usize,
+            })
+            .await?;
+        Ok(cnt)
+    }
+
+    /// Finds source file for program (e.g. checker) named `name`
+    fn find_source(&self, dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+        for dir in dirs {
+            for ext in &["cpp", "cc", "c"] {
+                let path = dir.join(format!("{}.{}", name, ext));
+                if path.exists() {
+                    return Some(path);
+                }
+            }
+        }
+        None
+    }
+
+    async fn import_checker(&mut self) -> anyhow::Result<()> {
+        self.tx.send_event(Update::ImportChecker).await?;
+        self.problem_cfg.check_type = "builtin".to_string();
+        if let Some(check_cmd) = self.problem.get("check_cmd") {
+            let dirs = [self.problem_dir()?, self.root.join("checkers")];
+            let src_path = self
+                .find_source(&dirs, check_cmd)
+                .with_context(|| format!("source of checker {} not found", check_cmd))?;
+            tokio::fs::create_dir(self.dest.join("modules/checker"))
+                .await
+                .context("create checker module dir")?;
+            tokio::fs::copy(&src_path, self.dest.join("modules/checker/main.cpp"))
+                .await
+                .context("copy checker")?;
+            let cmakedata =
+                super::template::get_checker_cmakefile(super::template::CheckerOptions {});
+            tokio::fs::write(self.dest.join("modules/checker/CMakeLists.txt"), cmakedata)
+                .await
+                .context("write checker's CMakeLists.txt")?;
+            for dir in &dirs {
+                let testlib_path = dir.join("testlib.h");
+                if testlib_path.exists() {
+                    tokio::fs::copy(&testlib_path, self.dest.join("testlib.h"))
+                        .await
+                        .context("copy testlib.h")?;
+                    break;
+                }
+            }
+            let m = &mut self.problem_cfg;
+            m.builtin_check = Some(crate::manifest::BuiltinCheck {
+                name: "polygon-compat".to_string(),
+            });
+            m.check_options = Some(crate::manifest::CheckOptions {
+                args: vec!["assets/module-checker/bin".to_string()],
+            });
+            self.warn(format!(
+                "checker {} is assumed to use testlib exit codes; ejudge-native checkers must be ported manually",
+                check_cmd
+            ))
+            .await?;
+            return Ok(());
+        }
+        self.problem_cfg.builtin_check = Some(crate::manifest::BuiltinCheck {
+            name: "cmp-tokens".to_string(),
+        });
+        let standard_checker = self.problem.get("standard_checker").unwrap_or("cmp_file");
+        let mut args = Vec::new();
+        match standard_checker {
+            "cmp_int"
+            | "cmp_long_long"
+            | "cmp_unsigned_int"
+            | "cmp_unsigned_long_long"
+            | "cmp_file_nospace"
+            | "cmp_int_seq"
+            | "cmp_long_long_seq"
+            | "cmp_unsigned_int_seq"
+            | "cmp_unsigned_long_long_seq" => {}
+            "cmp_double" | "cmp_long_double" | "cmp_double_seq" | "cmp_long_double_seq" => {
+                let eps = self
+                    .problem
+                    .get("checker_env")
+                    .and_then(|env| {
+                        env.split(';')
+                            .find_map(|var| var.trim().strip_prefix("EPS="))
+                    })
+                    .unwrap_or("1e-6");
+                args.push("--epsilon".to_string());
+                args.push(eps.to_string());
+            }
+            "cmp_file" | "cmp_bytes" => {
+                self.warn(format!(
+                    "standard checker {} compares files exactly; token comparison is used instead",
+                    standard_checker
+                ))
+                .await?;
+            }
+            other => {
+                self.warn(format!(
+                    "unknown standard checker {}; token comparison is used instead",
+                    other
+                ))
+                .await?;
+            }
+        }
+        self.problem_cfg.check_options = Some(crate::manifest::CheckOptions { args });
+        Ok(())
+    }
+
+    async fn import_solution(&mut self) -> anyhow::Result<()> {
+        self.tx.send_event(Update::ImportSolutions).await?;
+        let solution_src = match self.problem.get("solution_src") {
+            Some(s) => s,
+            None => bail!("solution_src is not specified: main solution is required"),
+        };
+        self.tx
+            .send_event(Update::ImportSolution("main".to_string()))
+            .await?;
+        let src_path = super::join_confined(&self.problem_dir()?, Path::new(""), solution_src)
+            .context("invalid solution_src")?;
+        super::import_main_solution(&src_path, self.dest).await?;
+        self.problem_cfg.primary_solution = Some("main".to_string());
+        Ok(())
+    }
+
+    /// Imports valuer.cfg if present and returns valuer config and test to
+    /// group mapping. Config is `None` if default config was written.
+    async fn import_valuer_config(
+        &mut self,
+    ) -> anyhow::Result<(Option<svaluer::Config>, HashMap<u32, String>)> {
+        let valuer_cfg_path = self.problem_dir()?.join("valuer.cfg");
+        let (config, test_groups) = if valuer_cfg_path.exists() {
+            self.tx.send_event(Update::ImportValuerConfig).await?;
+            let imported = valuer_cfg::import(&valuer_cfg_path).await?;
+            for warn in imported.warnings {
+                self.warn(format!("while importing valuer config: {}", warn))
+                    .await?;
+            }
+            (imported.config, imported.test_groups)
+        } else if let Some(full_score) = self.problem.get("full_score") {
+            self.tx.send_event(Update::ImportValuerConfig).await?;
+            if self.problem.get("test_score").is_some() {
+                self.warn(
+                    "per-test scoring is not supported, full_score is used for all tests"
+                        .to_string(),
+                )
+                .await?;
+            }
+            let group = svaluer::cfg::Group {
+                name: DEFAULT_GROUP.to_string(),
+                feedback: svaluer::cfg::FeedbackKind::Brief,
+                tests_tag: None,
+                run_to_first_failure: true,
+                score: full_score.parse().context("invalid full_score")?,
+                deps: vec![],
+            };
+            (
+                svaluer::Config {
+                    groups: vec![group],
+                },
+                HashMap::new(),
+            )
+        } else {
+            self.tx.send_event(Update::DefaultValuerConfig).await?;
+            tokio::fs::write(
+                self.dest.join("valuer.yaml"),
+                include_str!("./default_valuer_config.yaml"),
+            )
+            .await?;
+            return Ok((None, HashMap::new()));
+        };
+        Ok((Some(config), test_groups))
+    }
+
+    /// Writes valuer config, adding group for tests not mentioned in it
+    async fn write_valuer_config(
+        &mut self,
+        mut config: svaluer::Config,
+        test_groups: &HashMap<u32, String>,
+        test_count: u32,
+    ) -> anyhow::Result<()> {
+        let has_ungrouped_tests = (1..=test_count).any(|test| !test_groups.contains_key(&test));
+        let has_default_group = config.groups.iter().any(|g| g.name == DEFAULT_GROUP);
+        if has_ungrouped_tests && !has_default_group {
+            self.warn(format!(
+                "some tests are not mentioned in valuer.cfg, they were put into group '{}' worth 0 points",
+                DEFAULT_GROUP
+            ))
+            .await?;
+            config.groups.push(svaluer::cfg::Group {
+                name: DEFAULT_GROUP.to_string(),
+                feedback: svaluer::cfg::FeedbackKind::Brief,
+                tests_tag: None,
+                run_to_first_failure: false,
+                score: 0,
+                deps: vec![],
+            });
+        }
+        tokio::fs::write(
+            self.dest.join("valuer.yaml"),
+            serde_yaml::to_string(&config)?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn run(&mut self) -> anyhow::Result<()> {
+        for suf in &["solutions", "tests", "modules"] {
+            let path = self.dest.join(suf);
+            tokio::fs::create_dir(&path)
+                .await
+                .with_context(|| format!("create {}", path.display()))?;
+        }
+        let title = self
+            .problem
+            .get("long_name")
+            .unwrap_or_else(|| self.short_name())
+            .to_string();
+        self.tx
+            .send_event(Update::Property {
+                property_name: PropertyName::ProblemTitle,
+                property_value: title.clone(),
+            })
+            .await?;
+        let m = &mut self.problem_cfg;
+        m.title = title;
+        m.name = self.internal_name().to_string();
+        m.valuer = "icpc".to_string();
+        m.valuer_cfg = Some("valuer.yaml".to_string());
+        m.random_seed = Some(super::random_seed()?);
+
+        self.import_limits().await?;
+        let (valuer_config, test_groups) = self.import_valuer_config().await?;
+        let test_count = self.import_tests(&test_groups).await?;
+        if let Some(config) = valuer_config {
+            self.write_valuer_config(config, &test_groups, test_count)
+                .await?;
+        }
+        self.import_checker().await?;
+        self.import_solution().await?;
+        super::write_manifest(self.dest, &self.problem_cfg).await
+    }
+}
+
+/// Imports all problems from `serve.cfg` into `dest/problems` and returns
+/// contest description.
+pub(super) async fn import(
+    root: &Path,
+    serve_cfg_path: &Path,
+    dest: &Path,
+    contest_name: &str,
+    tx: &mut rpc::StreamingTx<Update, SimpleFinish>,
+) -> anyhow::Result<entity::Contest> {
+    let data = tokio::fs::read_to_string(serve_cfg_path)
+        .await
+        .context("failed to read serve.cfg")?;
+    let sections = parse_serve_cfg(&data).context("failed to parse serve.cfg")?;
+    let problems = resolve_problems(&sections)?;
+    let global = &sections[0];
+
+    let mut contest = entity::Contest {
+        title: contest_name.to_string(),
+        id: contest_name.to_string(),
+        problems: vec![],
+        judges: vec![],
+        group: vec![],
+        unregistered_visible: false,
+        anon_visible: false,
+        duration: global
+            .get("contest_time")
+            .and_then(|t| t.parse::<u64>().ok())
+            .filter(|&t| t != 0)
+            .map(|minutes| std::time::Duration::from_secs(minutes * 60)),
+        end_time: None,
+        start_time: None,
+        is_virtual: global.flag("virtual"),
+    };
+    tx.send_event(Update::ImportContestProblems {
+        count: problems.len(),
+    })
+    .await?;
+    for problem in &problems {
+        let short_name = problem
+            .get("short_name")
+            .context("problem does not have short_name")?
+            .to_string();
+        let name = problem
+            .get("internal_name")
+            .unwrap_or(&short_name)
+            .to_string();
+        tx.send_event(Update::ImportContestProblem {
+            code: short_name.clone(),
+            name: name.clone(),
+        })
+        .await?;
+        let res = async {
+            super::check_file_name(&name).context("invalid problem name")?;
+            let problem_dest = dest.join(&name);
+            tokio::fs::create_dir_all(&problem_dest)
+                .await
+                .context("failed to create problem dir")?;
+            let mut importer = ProblemImporter {
+                root,
+                global,
+                problem,
+                dest: &problem_dest,
+                problem_cfg: Default::default(),
+                tx: &mut *tx,
+            };
+            importer.run().await
+        }
+        .await;
+        let error = res.err().map(|err| format!("{:#}", err));
+        if let Some(err) = &error {
+            let message = format!("problem {} ({}) skipped: {}", short_name, name, err);
+            tx.send_event(Update::Warning(message)).await?;
+        }
+        tx.send_event(Update::ContestProblemDone {
+            code: short_name.clone(),
+            error: error.clone(),
+        })
+        .await?;
+        if error.is_none() {
+            contest
+                .problems
+                .push(entity::entities::contest::ProblemBinding {
+                    name,
+                    code: short_name,
+                });
+        }
+    }
+    Ok(contest)
+}
@@ -0,0 +1,437 @@
+//! Imports problem from Kattis (ICPC) `problem.yaml` package.
+//!
+//! Tests are taken from `data/sample` and `data/secret`. For scoring problems
+//! each subdirectory of `data/secret` becomes separate svaluer group.
+use anyhow::{bail, Context as _};
+use pps_api::{
+    import_problem::{PropertyName, Update},
+    SimpleFinish,
+};
+use std::path::{Path, PathBuf};
+
+/// Legacy location of time limit (in seconds), written by problemtools
+const TIME_LIMIT_FILE: &str = ".timelimit";
+
+/// Tests of one group (i.e. of one directory in `data`)
+struct TestGroup {
+    name: String,
+    tests: Vec<PathBuf>,
+    /// Maximal score, if specified in `testdata.yaml`
+    score: Option<u32>,
+}
+
+pub(crate) struct Importer<'a> {
+    pub(crate) src: &'a Path,
+    pub(crate) dest: &'a Path,
+    pub(crate) problem_cfg: crate::manifest::RawProblem,
+    pub(crate) tx: &'a mut rpc::StreamingTx<Update, SimpleFinish>,
+}
+
+/// Returns sorted list of directory entries
+fn list_dir(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(path)
+        .with_context(|| format!("read dir {}", path.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Parses maximal score of group with `test_count` tests from
+/// `testdata.yaml`.
+///
+/// It is either upper bound of `range`, or `accept_score` (score of single
+/// test) aggregated over all tests according to `grader_flags`. Default
+/// grader sums test scores.
+fn read_group_score(dir: &Path, test_count: usize) -> anyhow::Result<Option<u32>> {
+    let path = dir.join("testdata.yaml");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = std::fs::read_to_string(&path).context("read testdata.yaml")?;
+    let testdata: serde_yaml::Value = serde_yaml::from_str(&data).context("parse testdata.yaml")?;
+    let aggregation = testdata
+        .get("grader_flags")
+        .and_then(|flags| flags.as_str())
+        .unwrap_or("")
+        .split_whitespace()
+        .filter(|flag| ["sum", "avg", "min", "max"].contains(flag))
+        .last()
+        .unwrap_or("sum");
+    let (score, multiplier) = match (testdata.get("range"), testdata.get("accept_score")) {
+        (Some(range), _) => (
+            range
+                .as_str()
+                .and_then(|r| r.split_whitespace().nth(1))
+                .map(str::to_string),
+            1,
+        ),
+        (None, Some(score)) => {
+            let score = match score {
+                serde_yaml::Value::Number(num) => Some(num.to_string()),
+                serde_yaml::Value::String(s) => Some(s.clone()),
+                _ => None,
+            };
+            let multiplier = if aggregation == "sum" { test_count } else { 1 };
+            (score, multiplier)
+        }
+        (None, None) => return Ok(None),
+    };
+    let score = score.with_context(|| format!("{}: invalid score", path.display()))?;
+    let score: f64 = score
+        .parse()
+        .with_context(|| format!("{}: invalid score {}", path.display(), score))?;
+    Ok(Some((score * multiplier as f64).round() as u32))
+}
+
+impl<'a> Importer<'a> {
+    async fn warn(&mut self, message: String) -> anyhow::Result<()> {
+        self.tx.send_event(Update::Warning(message)).await?;
+        Ok(())
+    }
+
+    /// Collects groups from `dir`: tests in `dir` itself form group `name`,
+    /// subdirectories form groups named after them.
+    fn collect_groups(
+        &self,
+        dir: &Path,
+        name: &str,
+        out: &mut Vec<TestGroup>,
+    ) -> anyhow::Result<()> {
+        let mut group = TestGroup {
+            name: name.to_string(),
+            tests: Vec::new(),
+            score: None,
+        };
+        let mut subdirs = Vec::new();
+        for entry in list_dir(dir)? {
+            if entry.is_dir() {
+                subdirs.push(entry);
+            } else if entry.extension().map_or(false, |ext| ext == "in") {
+                group.tests.push(entry);
+            }
+        }
+        if !group.tests.is_empty() {
+            group.score = read_group_score(dir, group.tests.len())?;
+            out.push(group);
+        }
+        for subdir in subdirs {
+            let subdir_name = subdir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .context("non-utf8 test group name")?;
+            self.collect_groups(&subdir, &format!("{}-{}", name, subdir_name), out)?;
+        }
+        Ok(())
+    }
+
+    async fn import_title(&mut self, manifest: &serde_yaml::Value) -> anyhow::Result<()> {
+        let title = match manifest.get("name") {
+            Some(serde_yaml::Value::String(name)) => Some(name.clone()),
+            // localized names: prefer english
+            Some(serde_yaml::Value::Mapping(names)) => names
+                .get(&serde_yaml::Value::from("en"))
+                .or_else(|| names.iter().next().map(|(_, v)| v))
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            _ => None,
+        };
+        let title = match title {
+            Some(t) => t,
+            None => {
+                self.warn("problem.yaml does not contain name".to_string())
+                    .await?;
+                self.problem_cfg.name.clone()
+            }
+        };
+        self.tx
+            .send_event(Update::Property {
+                property_name: PropertyName::ProblemTitle,
+                property_value: title.clone(),
+            })
+            .await?;
+        self.problem_cfg.title = title;
+        Ok(())
+    }
+
+    async fn import_limits(&mut self, manifest: &serde_yaml::Value) -> anyhow::Result<()> {
+        let limits = manifest.get("limits");
+        let time_limit = match limits.and_then(|l| l.get("time_limit")) {
+            Some(tl) => Some(tl.as_f64().context("limits.time_limit is not a number")?),
+            None => {
+                let path = self.src.join(TIME_LIMIT_FILE);
+                if path.exists() {
+                    let data = std::fs::read_to_string(&path).context("read .timelimit")?;
+                    Some(data.trim().parse::<f64>().context("parse .timelimit")?)
+                } else {
+                    None
+                }
+            }
+        };
+        match time_limit {
+            Some(tl) => {
+                let tl = (tl * 1000.0).round() as u64;
+                self.tx
+                    .send_event(Update::Property {
+                        property_name: PropertyName::TimeLimit,
+                        property_value: tl.to_string(),
+                    })
+                    .await?;
+                self.problem_cfg.limits.time.replace(tl);
+            }
+            None => {
+                self.warn("time limit is not specified, default is used".to_string())
+                    .await?
+            }
+        }
+        if let Some(ml) = limits.and_then(|l| l.get("memory")) {
+            let ml = ml.as_u64().context("limits.memory is not a number")? * 1024 * 1024;
+            self.tx
+                .send_event(Update::Property {
+                    property_name: PropertyName::MemoryLimit,
+                    property_value: ml.to_string(),
+                })
+                .await?;
+            self.problem_cfg.limits.memory.replace(ml);
+        }
+        Ok(())
+    }
+
+    async fn import_checker(&mut self, manifest: &serde_yaml::Value) -> anyhow::Result<()> {
+        self.tx.send_event(Update::ImportChecker).await?;
+        let validation = manifest
+            .get("validation")
+            .and_then(|v| v.as_str())
+            .unwrap_or("default");
+        if validation != "default" {
+            self.warn(format!(
+                "validation '{}' is not supported: output validators use incompatible protocol; token comparison is used instead",
+                validation
+            ))
+            .await?;
+        }
+        let flags = manifest
+            .get("validator_flags")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let mut flags = flags.split_whitespace();
+        let mut args = Vec::new();
+        // default validator is case-insensitive
+        let mut ignore_case = true;
+        while let Some(flag) = flags.next() {
+            match flag {
+                "case_sensitive" => ignore_case = false,
+                "float_tolerance" | "float_relative_tolerance" | "float_absolute_tolerance" => {
+                    let eps = flags
+                        .next()
+                        .with_context(|| format!("validator flag {} requires value", flag))?;
+                    if args.is_empty() {
+                        args.push("--epsilon".to_string());
+                        args.push(eps.to_string());
+                    } else {
+                        self.warn(format!(
+                            "validator flag {} ignored: epsilon is already set",
+                            flag
+                        ))
+                        .await?;
+                    }
+                }
+                "space_change_sensitive" => {
+                    self.warn("validator flag space_change_sensitive is not supported".to_string())
+                        .await?;
+                }
+                other => {
+                    self.warn(format!("unknown validator flag {}", other))
+                        .await?;
+                }
+            }
+        }
+        if ignore_case {
+            args.push("--ignore-case".to_string());
+        }
+        let m = &mut self.problem_cfg;
+        m.check_type = "builtin".to_string();
+        m.builtin_check = Some(crate::manifest::BuiltinCheck {
+            name: "cmp-tokens".to_string(),
+        });
+        m.check_options = Some(crate::manifest::CheckOptions { args });
+        Ok(())
+    }
+
+    /// Imports tests and writes valuer config
+    async fn import_tests(&mut self, manifest: &serde_yaml::Value) -> anyhow::Result<()> {
+        self.tx.send_event(Update::ImportTests).await?;
+        let data_dir = self.src.join("data");
+        let mut groups = Vec::new();
+        for &name in &["sample", "secret"] {
+            let dir = data_dir.join(name);
+            if dir.exists() {
+                self.collect_groups(&dir, name, &mut groups)?;
+            }
+        }
+        let mut cnt: usize = 0;
+        let mut missing_answers = 0;
+        for group in &groups {
+            for test in &group.tests {
+                cnt += 1;
+                let dest_path = self.dest.join(format!("tests/{:0>3}.txt", cnt));
+                tokio::fs::copy(test, &dest_path)
+                    .await
+                    .with_context(|| format!("copy test {}", test.display()))?;
+                let answer = test.with_extension("ans");
+                let has_answer = answer.exists();
+                if has_answer {
+                    tokio::fs::copy(&answer, self.dest.join(format!("tests/{:0>3}.ans", cnt)))
+                        .await
+                        .with_context(|| format!("copy answer {}", answer.display()))?;
+                } else {
+                    missing_answers += 1;
+                }
+                self.problem_cfg.tests.push(crate::manifest::RawTestsSpec {
+                    map: cnt.to_string(),
+                    testgen: None,
+                    files: Some("%03d.txt".to_string()),
+                    answers: if has_answer {
+                        Some("%03d.ans".to_string())
+                    } else {
+                        None
+                    },
+                    limits: self.problem_cfg.limits,
+                    group: group.name.clone(),
+                });
+            }
+        }
+        if cnt == 0 {
+            bail!("no tests found in {}", data_dir.display());
+        }
+        self.tx
+            .send_event(Update::ImportTestsDone { count: cnt })
+            .await?;
+        if missing_answers > 0 {
+            self.warn(format!(
+                "{} tests do not have answer files: answers will be generated by main solution",
+                missing_answers
+            ))
+            .await?;
+        }
+
+        self.tx.send_event(Update::ImportValuerConfig).await?;
+        let is_scoring = manifest.get("type").and_then(|t| t.as_str()) == Some("scoring");
+        let mut config = svaluer::Config { groups: Vec::new() };
+        for group in &groups {
+            let is_sample = group.name.starts_with("sample");
+            let score = if is_scoring && !is_sample {
+                match group.score {
+                    Some(score) => score,
+                    None => {
+                        self.warn(format!(
+                            "group {} does not specify score, 0 is used",
+                            group.name
+                        ))
+                        .await?;
+                        0
+                    }
+                }
+            } else {
+                0
+            };
+            config.groups.push(svaluer::cfg::Group {
+                name: group.name.clone(),
+                feedback: if is_sample {
+                    svaluer::cfg::FeedbackKind::Full
+                } else {
+                    svaluer::cfg::FeedbackKind::Brief
+                },
+                tests_tag: None,
+                run_to_first_failure: !is_scoring,
+                score,
+                deps: vec![],
+            });
+        }
+        if !is_scoring {
+            // pass-fail problem: full score requires all tests to pass
+            let all_groups: Vec<_> = config
+                .groups
+                .iter()
+                .map(|g| svaluer::cfg::GroupRef::ByName(g.name.clone()))
+                .collect();
+            let last = config.groups.last_mut().expect("groups are not empty");
+            last.score = 100;
+            last.deps = all_groups[..all_groups.len() - 1].to_vec();
+        }
+        tokio::fs::write(
+            self.dest.join("valuer.yaml"),
+            serde_yaml::to_string(&config)?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn import_solution(&mut self) -> anyhow::Result<()> {
+        self.tx.send_event(Update::ImportSolutions).await?;
+        let accepted_dir = self.src.join("submissions/accepted");
+        if !accepted_dir.exists() {
+            bail!("submissions/accepted not found: main solution is required");
+        }
+        let mut candidates = Vec::new();
+        for entry in list_dir(&accepted_dir)? {
+            let ext = entry.extension().and_then(|e| e.to_str()).unwrap_or("");
+            if ["cpp", "cc", "cxx", "py"].contains(&ext) && entry.is_file() {
+                candidates.push(entry);
+            }
+        }
+        // prefer C++ solutions, because they are faster
+        candidates.sort_by_key(|c| c.extension().map_or(false, |ext| ext == "py"));
+        let main = candidates
+            .first()
+            .context("no supported accepted solution found (C++ and Python are supported)")?;
+        self.tx
+            .send_event(Update::ImportSolution("main".to_string()))
+            .await?;
+        if candidates.len() > 1 {
+            self.warn(format!(
+                "using {} as main solution, other solutions are skipped",
+                main.display()
+            ))
+            .await?;
+        }
+        super::import_main_solution(main, self.dest).await?;
+        self.problem_cfg.primary_solution = Some("main".to_string());
+        Ok(())
+    }
+
+    pub(crate) async fn run(&mut self) -> anyhow::Result<()> {
+        let manifest_data = tokio::fs::read_to_string(self.src.join("problem.yaml"))
+            .await
+            .context("failed to read problem.yaml")?;
+        let manifest: serde_yaml::Value =
+            serde_yaml::from_str(&manifest_data).context("failed to parse problem.yaml")?;
+        for suf in &["solutions", "tests", "modules"] {
+            let path = self.dest.join(suf);
+            tokio::fs::create_dir(&path)
+                .await
+                .with_context(|| format!("create {}", path.display()))?;
+        }
+        let name = self
+            .src
+            .canonicalize()
+            .context("resolve problem dir")?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("problem name can not be derived from path")?
+            .to_string();
+        let m = &mut self.problem_cfg;
+        m.name = name;
+        m.valuer = "icpc".to_string();
+        m.valuer_cfg = Some("valuer.yaml".to_string());
+        m.random_seed = Some(super::random_seed()?);
+
+        self.import_title(&manifest).await?;
+        self.import_limits(&manifest).await?;
+        self.import_tests(&manifest).await?;
+        self.import_checker(&manifest).await?;
+        self.import_solution().await?;
+        Ok(())
+    }
+}
@@ -268,6 +268,7 @@ impl<'a> Importer<'a> {
                 map: cnt.to_string(),
                 testgen: None,
                 files: None,
+                answers: None,
                 limits: self.limits,
                 group: format!(
                     "g{}",
@@ -385,11 +386,7 @@ impl<'a> Importer<'a> {
             args: vec!["assets/module-checker/bin".to_string()],
         });
         m.valuer_cfg = Some("valuer.yaml".to_string());
-        let mut random_seed = [0; 8];
-        getrandom::getrandom(&mut random_seed)?;
-        let rand_seed_hex = hex::encode(&random_seed);
-        assert_eq!(rand_seed_hex.len(), 16);
-        m.random_seed = Some(rand_seed_hex);
+        m.random_seed = Some(super::random_seed()?);
         Ok(())
     }
 
@@ -444,8 +441,8 @@ impl<'a> Importer<'a> {
         let valuer_cfg_path = self.src.join("files/valuer.cfg");
         let config = if valuer_cfg_path.exists() {
             self.tx.send_event(Update::ImportValuerConfig).await?;
-            let (config, warnings) = super::valuer_cfg::import(&valuer_cfg_path).await?;
-            for warn in warnings {
+            let imported = super::valuer_cfg::import(&valuer_cfg_path).await?;
+            for warn in imported.warnings {
                 self.tx
                     .send_event(Update::Warning(format!(
                         "while importing valuer config: {}",
@@ -453,7 +450,7 @@ impl<'a> Importer<'a> {
                     )))
                     .await?;
             }
-            serde_yaml::to_string(&config)?
+            serde_yaml::to_string(&imported.config)?
         } else {
             self.tx.send_event(Update::DefaultValuerConfig).await?;
             include_str!("./default_valuer_config.yaml").to_string()
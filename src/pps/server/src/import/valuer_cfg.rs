@@ -1,5 +1,5 @@
 use pest::Parser as _;
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,7 +18,7 @@ pub(super) struct P;
 
 struct Visitor<'a> {
     config: &'a mut svaluer::Config,
-    tests_info: std::collections::HashMap<u32, String>,
+    tests_info: HashMap<u32, String>,
     warnings: Vec<String>,
 }
 
@@ -126,18 +126,30 @@ impl<'a> Visitor<'a> {
     }
 }
 
-pub(crate) async fn import(
-    path: &Path,
-) -> Result<(svaluer::Config, Vec<String>), ImportValuerCfgError> {
+/// Result of valuer.cfg import
+pub(crate) struct ImportedValuerCfg {
+    pub(crate) config: svaluer::Config,
+    /// Maps test id to name of group containing this test.
+    /// Tests not mentioned in valuer.cfg are absent.
+    pub(crate) test_groups: HashMap<u32, String>,
+    pub(crate) warnings: Vec<String>,
+}
+
+pub(crate) async fn import(path: &Path) -> Result<ImportedValuerCfg, ImportValuerCfgError> {
     let input = tokio::fs::read_to_string(path).await?;
     let mut ast = P::parse(Rule::config, &input)?;
     let mut config = svaluer::Config { groups: Vec::new() };
     let mut visitor = Visitor {
         config: &mut config,
-        tests_info: std::collections::HashMap::new(),
+        tests_info: HashMap::new(),
         warnings: Vec::new(),
     };
     visitor.visit(ast.next().unwrap());
     let warnings = std::mem::take(&mut visitor.warnings);
-    Ok((config, warnings))
+    let test_groups = std::mem::take(&mut visitor.tests_info);
+    Ok(ImportedValuerCfg {
+        config,
+        test_groups,
+        warnings,
+    })
 }
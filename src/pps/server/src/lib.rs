@@ -3,13 +3,13 @@
 
 mod command;
 mod compile;
+mod export;
 mod import;
 mod manifest;
 
 use anyhow::Context;
 use std::{
     path::{Path, PathBuf},
-    process::Stdio,
     sync::Arc,
 };
 
@@ -65,6 +65,7 @@ pub async fn create_server() -> anyhow::Result<rpc::Router> {
 
     let service = Service(Arc::new(ServiceState::get().await?));
     builder.add_route::<pps_api::CompileProblem, _>(service.clone());
+    builder.add_route::<pps_api::ExportProblem, _>(service.clone());
     builder.add_route::<pps_api::ImportProblem, _>(service);
     Ok(builder.build())
 }
@@ -98,6 +99,8 @@ pub struct Service(pub(crate) Arc<ServiceState>);
 pub struct ServiceState {
     /// JJS installation directory (used to find JTL binaries)
     jjs_dir: PathBuf,
+    /// Minion backend used to sandbox compilers, generators and solutions
+    minion: Arc<dyn minion::erased::Backend>,
 }
 
 impl ServiceState {
@@ -105,6 +108,9 @@ impl ServiceState {
         let jjs_dir: PathBuf = std::env::var_os("JJS_PATH")
             .context("JJS_PATH not set")?
             .into();
-        Ok(ServiceState { jjs_dir })
+        let minion = minion::erased::setup()
+            .context("minion initialization failed")?
+            .into();
+        Ok(ServiceState { jjs_dir, minion })
     }
 }
@@ -22,16 +22,43 @@ pub struct CheckOptions {
     pub args: Vec<String>,
 }
 
+/// Resource limits for problem building steps.
+/// Missing values are replaced with defaults.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct StepLimits {
+    /// Time limit in milliseconds
+    pub time: Option<u64>,
+    /// Memory limit in bytes
+    pub memory: Option<u64>,
+    /// Stdout (and, separately, stderr) size limit in bytes
+    pub output: Option<u64>,
+    /// Process count limit
+    pub process_count: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct RawTestsSpec {
     pub map: String,
     pub testgen: Option<Vec<String>>,
     pub files: Option<String>,
+    /// Template of correct answer file names, similar to `files`.
+    /// If not set, answers are generated by primary solution.
+    #[serde(default)]
+    pub answers: Option<String>,
     #[serde(default)]
     pub limits: pom::Limits,
     pub group: String,
 }
 
+/// Interpolates test id into file name template
+fn format_file_name(template: &str, id: u32) -> anyhow::Result<String> {
+    match formatf::format(template.as_bytes(), &[formatf::Value::Int(i128::from(id))]) {
+        Ok(file) => Ok(String::from_utf8(file).expect("interpolation provided non-utf8 data")),
+        // TODO: implement Display for formatf FormatError
+        Err(err) => bail!("formatting error: {:?}", err),
+    }
+}
+
 impl RawTestsSpec {
     fn parse_mapping_chunk(&self, ch: &str) -> anyhow::Result<Vec<u32>> {
         if ch.contains("..") {
@@ -96,19 +123,8 @@ impl RawTestsSpec {
         let mut out = Vec::new();
         if let Some(file_tpl) = &self.files {
             for &id in idxs.iter() {
-                let res =
-                    formatf::format(file_tpl.as_bytes(), &[formatf::Value::Int(i128::from(id))]);
-                match res {
-                    Ok(file) => {
-                        let file =
-                            String::from_utf8(file).expect("interpolation provided non-utf8 data");
-                        out.push((id, TestGenSpec::File { path: file }));
-                    }
-                    Err(err) => {
-                        bail!("formatting error: {:?}", err);
-                        // TODO: implement Display for formatf FormatError
-                    }
-                }
+                let file = format_file_name(file_tpl, id)?;
+                out.push((id, TestGenSpec::File { path: file }));
             }
         }
         if let Some(testgen_cmd) = &self.testgen {
@@ -121,21 +137,23 @@ impl RawTestsSpec {
                 out.push((id, spec.clone()));
             }
         }
-        let out = out
-            .into_iter()
+        out.into_iter()
             .map(|(id, test_gen_spec)| {
-                (
+                let answer = match &self.answers {
+                    Some(answers_tpl) => Some(format_file_name(answers_tpl, id)?),
+                    None => None,
+                };
+                Ok((
                     id,
                     TestSpec {
                         gen: test_gen_spec,
+                        answer,
                         limits: self.limits,
                         group: self.group.clone(),
                     },
-                )
+                ))
             })
-            .collect();
-
-        Ok(out)
+            .collect()
     }
 }
 
@@ -148,6 +166,8 @@ pub enum TestGenSpec {
 #[derive(Debug)]
 pub struct TestSpec {
     pub gen: TestGenSpec,
+    /// Path to correct answer, relative to tests directory
+    pub answer: Option<String>,
     pub limits: pom::Limits,
     pub group: String,
 }
@@ -185,6 +205,32 @@ pub struct RawProblem {
 
     #[serde(default)]
     pub limits: pom::Limits,
+
+    /// Limits for compiling solutions, generators, checkers and modules
+    #[serde(rename = "build-limits", default)]
+    pub build_limits: StepLimits,
+
+    /// Limits for running generators and solutions
+    #[serde(rename = "run-limits", default)]
+    pub run_limits: StepLimits,
+
+    /// Limits for running checker during judging
+    #[serde(rename = "checker-limits", default)]
+    pub checker_limits: pom::Limits,
+
+    /// Limits for running valuer during judging. Missing values are taken
+    /// from `pom::Limits::valuer_default()`.
+    #[serde(rename = "valuer-limits")]
+    pub valuer_limits: Option<pom::Limits>,
+
+    /// Wall-clock budget for judging session in milliseconds. By default it
+    /// is derived from test limits.
+    #[serde(rename = "valuer-budget")]
+    pub valuer_budget: Option<u64>,
+
+    /// `standard` or `output-only`
+    #[serde(default)]
+    pub kind: pom::ProblemKind,
 }
 
 impl RawProblem {
@@ -272,6 +318,12 @@ impl RawProblem {
             valuer: self.valuer,
             valuer_cfg: self.valuer_cfg,
             limits: self.limits,
+            build_limits: self.build_limits,
+            run_limits: self.run_limits,
+            checker_limits: self.checker_limits,
+            valuer_limits: self.valuer_limits,
+            valuer_budget: self.valuer_budget,
+            kind: self.kind,
         };
 
         Ok((out, warnings))
@@ -296,4 +348,10 @@ pub struct Problem {
     pub valuer: String,
     pub valuer_cfg: Option<String>,
     pub limits: pom::Limits,
+    pub build_limits: StepLimits,
+    pub run_limits: StepLimits,
+    pub checker_limits: pom::Limits,
+    pub valuer_limits: Option<pom::Limits>,
+    pub valuer_budget: Option<u64>,
+    pub kind: pom::ProblemKind,
 }
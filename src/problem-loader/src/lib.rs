@@ -1,36 +1,92 @@
 //! This library is responsible for fetching problem packages
 
-pub use registry::Registry;
+pub use registry::{Registry, Revision};
 
 mod registry;
 
 use anyhow::Context;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-// TODO: cache expiration, checksum, etc
-/// Stores cached problem information
-struct ProblemCache {
-    /// Maps problem name to problem cache.
-    items: HashMap<String, ProblemCacheItem>,
+/// Cached problem. Lock is held while problem is loaded or revalidated, so
+/// that concurrent requests for the same problem wait for it.
+type CacheEntry = Arc<tokio::sync::Mutex<Option<ProblemCacheItem>>>;
+
+struct ProblemCacheItem {
+    /// Index of registry which provided this problem
+    registry: usize,
+    revision: Revision,
+    assets: Arc<AssetsDir>,
+    manifest: pom::Problem,
+    /// When revision was last compared with registry
+    validated_at: Instant,
 }
 
-impl ProblemCache {
-    fn new() -> ProblemCache {
-        ProblemCache {
-            items: HashMap::new(),
+impl ProblemCacheItem {
+    fn handle(&self) -> ProblemHandle {
+        ProblemHandle {
+            manifest: self.manifest.clone(),
+            revision: self.revision.clone(),
+            assets: self.assets.clone(),
         }
     }
 }
-struct ProblemCacheItem {
-    assets: PathBuf,
-    manifest: pom::Problem,
+
+/// Directory with assets of one problem revision.
+/// It is removed when revision is neither cached nor used.
+#[derive(Debug)]
+struct AssetsDir(PathBuf);
+
+impl Drop for AssetsDir {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_dir_all(&self.0) {
+            tracing::warn!(path=%self.0.display(), error=%err, "failed to remove problem assets");
+        }
+    }
+}
+
+/// Loaded problem revision. Its assets are kept until all handles are
+/// dropped, even if problem was updated or invalidated since.
+#[derive(Debug, Clone)]
+pub struct ProblemHandle {
+    pub manifest: pom::Problem,
+    pub revision: Revision,
+    assets: Arc<AssetsDir>,
+}
+
+impl ProblemHandle {
+    /// Returns path to problem directory, containing `assets` dir
+    pub fn problem_dir(&self) -> &Path {
+        &self.assets.0
+    }
+}
+
+/// Number of cache hits and misses in [`find`](Loader::find)
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
 }
 
 pub struct Loader {
     registries: Vec<Box<dyn Registry>>,
-    cache: tokio::sync::Mutex<ProblemCache>,
-    /// Each problem will be represented by ${cache_dir}/${problem_name}
+    /// Maps problem name to problem cache. This lock is only held for map
+    /// access, and never across await points.
+    cache: std::sync::Mutex<HashMap<String, CacheEntry>>,
+    /// Each problem revision will be represented by
+    /// ${cache_dir}/${problem_name}/${generation}
     cache_dir: PathBuf,
+    next_generation: AtomicU64,
+    revalidate_after: Option<Duration>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
 }
 
 impl Loader {
@@ -38,7 +94,11 @@ impl Loader {
         let mut loader = Loader {
             registries: vec![],
             cache_dir: cache_dir.to_path_buf(),
-            cache: tokio::sync::Mutex::new(ProblemCache::new()),
+            cache: std::sync::Mutex::new(HashMap::new()),
+            next_generation: AtomicU64::new(0),
+            revalidate_after: conf.revalidate_after.map(Duration::from_secs),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
         };
         if let Some(fs) = &conf.fs {
             let fs_reg = registry::FsRegistry::new(fs.clone());
@@ -53,23 +113,66 @@ impl Loader {
         Ok(loader)
     }
 
+    /// Checks that cached problem was not updated in registry, if
+    /// revalidation policy requires it. If registry fails, cached revision
+    /// is used.
+    async fn is_up_to_date(&self, problem_name: &str, item: &mut ProblemCacheItem) -> bool {
+        let revalidate_after = match self.revalidate_after {
+            Some(period) => period,
+            None => return true,
+        };
+        if item.validated_at.elapsed() < revalidate_after {
+            return true;
+        }
+        let registry = &self.registries[item.registry];
+        match registry.get_revision(problem_name).await {
+            Ok(current) if current.as_ref() == Some(&item.revision) => {
+                item.validated_at = Instant::now();
+                true
+            }
+            Ok(_) => {
+                tracing::info!(problem_name=%problem_name, old_revision=%item.revision.0, "problem was updated");
+                false
+            }
+            Err(err) => {
+                tracing::warn!(problem_name=%problem_name, registry=%registry.name(), error=%format_args!("{:#}", err), "failed to revalidate problem, using cached revision");
+                true
+            }
+        }
+    }
+
     /// Tries to resolve problem named `problem_name` in all configured
-    /// registries. On success, returns problem manifest to path to assets dir.
-    pub async fn find(
-        &self,
-        problem_name: &str,
-    ) -> anyhow::Result<Option<(pom::Problem, PathBuf)>> {
-        let mut cache = self.cache.lock().await;
-        if let Some(cached_info) = cache.items.get(problem_name) {
-            return Ok(Some((
-                cached_info.manifest.clone(),
-                cached_info.assets.clone(),
-            )));
+    /// registries. On success, returns handle to the current problem revision.
+    pub async fn find(&self, problem_name: &str) -> anyhow::Result<Option<ProblemHandle>> {
+        let entry = self
+            .cache
+            .lock()
+            .unwrap()
+            .entry(problem_name.to_string())
+            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(None)))
+            .clone();
+        let mut entry = entry.lock().await;
+        if let Some(item) = &mut *entry {
+            if self.is_up_to_date(problem_name, item).await {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(Some(item.handle()));
+            }
+            // outdated revision is only kept while someone uses it
+            *entry = None;
         }
         // cache for this problem not found, let's load it.
-        let assets_path = self.cache_dir.join(problem_name);
-        for registry in &self.registries {
-            if let Some(manifest) = registry
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let assets_path = self
+            .cache_dir
+            .join(problem_name)
+            .join(generation.to_string());
+        tokio::fs::create_dir_all(&assets_path)
+            .await
+            .context("failed to create problem cache dir")?;
+        let assets = Arc::new(AssetsDir(assets_path.clone()));
+        for (registry_idx, registry) in self.registries.iter().enumerate() {
+            if let Some((manifest, revision)) = registry
                 .get_problem(problem_name, &assets_path)
                 .await
                 .with_context(|| {
@@ -80,19 +183,46 @@ impl Loader {
                     )
                 })?
             {
-                cache.items.insert(
-                    problem_name.to_string(),
-                    ProblemCacheItem {
-                        manifest: manifest.clone(),
-                        assets: assets_path.clone(),
-                    },
-                );
-                return Ok(Some((manifest, assets_path)));
+                let item = ProblemCacheItem {
+                    registry: registry_idx,
+                    revision,
+                    assets,
+                    manifest,
+                    validated_at: Instant::now(),
+                };
+                let handle = item.handle();
+                *entry = Some(item);
+                return Ok(Some(handle));
             }
         }
         // no registry knows about this problem
         Ok(None)
     }
+
+    /// Removes problem from cache, so that it will be reloaded on next
+    /// [`find`](Loader::find). If `problem_name` is None, all problems are
+    /// removed. Requests which already use the problem are not affected.
+    ///
+    /// Returns count of removed problems.
+    pub fn invalidate(&self, problem_name: Option<&str>) -> usize {
+        let mut cache = self.cache.lock().unwrap();
+        let removed: Vec<CacheEntry> = match problem_name {
+            Some(problem_name) => cache.remove(problem_name).into_iter().collect(),
+            None => cache.drain().map(|(_, entry)| entry).collect(),
+        };
+        // entry which is being loaded right now is counted too
+        removed
+            .iter()
+            .filter(|entry| entry.try_lock().map_or(true, |item| item.is_some()))
+            .count()
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.cache_hits.load(Ordering::Relaxed),
+            misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
 }
 
 /// Used in [`from_config`](Loader::from_config) constructor
@@ -102,4 +232,9 @@ pub struct LoaderConfig {
     fs: Option<std::path::PathBuf>,
     #[serde(default)]
     mongodb: Option<String>,
+    /// If set, cached problem is compared with registry when it was last
+    /// checked more than given count of seconds ago (0 means on each use).
+    /// Otherwise, cached problems are only reloaded after invalidation.
+    #[serde(default)]
+    revalidate_after: Option<u64>,
 }
@@ -2,9 +2,20 @@
 
 use anyhow::Context as _;
 use async_trait::async_trait;
-use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 use tracing::instrument;
 
+/// Identifies version of problem in registry.
+/// Registry must return new revision whenever problem is changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision(pub String);
+
 /// Single problem source.
 /// `problem-loader` itself is just abstraction for group of
 /// registries.
@@ -19,10 +30,44 @@ pub trait Registry: Send + Sync {
         &self,
         problem_name: &str,
         assets_path: &Path,
-    ) -> anyhow::Result<Option<pom::Problem>>;
+    ) -> anyhow::Result<Option<(pom::Problem, Revision)>>;
+
+    /// Returns current revision of problem, without fetching it.
+    /// Returns None if problem was not found.
+    async fn get_revision(&self, problem_name: &str) -> anyhow::Result<Option<Revision>>;
+}
+
+/// Computes checksum of problem directory. Manifest is hashed completely,
+/// and for assets only paths, sizes and modification times are hashed.
+fn checksum_dir(problem_dir: &Path) -> anyhow::Result<Revision> {
+    let mut hasher = DefaultHasher::new();
+    std::fs::read(problem_dir.join("manifest.json"))
+        .context("failed to read manifest")?
+        .hash(&mut hasher);
+    let assets_dir = problem_dir.join("assets");
+    let mut dirs = vec![assets_dir.clone()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("failed to list {}", dir.display()))?
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            path.strip_prefix(&assets_dir)?.hash(&mut hasher);
+            if metadata.is_dir() {
+                dirs.push(path);
+            } else {
+                metadata.len().hash(&mut hasher);
+                metadata.modified()?.hash(&mut hasher);
+            }
+        }
+    }
+    Ok(Revision(format!("{:016x}", hasher.finish())))
 }
 
-/// Resolves problems from filesystem
+/// Resolves problems from filesystem.
+/// Revision is checksum of problem directory.
 #[derive(Debug)]
 pub struct FsRegistry {
     /// Directory containing all problems
@@ -42,12 +87,20 @@ impl Registry for FsRegistry {
         &self,
         problem_name: &str,
         dest_path: &Path,
-    ) -> anyhow::Result<Option<pom::Problem>> {
+    ) -> anyhow::Result<Option<(pom::Problem, Revision)>> {
         let problem_dir = self.problems_dir.join(problem_name);
         let manifest_path = problem_dir.join("manifest.json");
         if !manifest_path.exists() {
             return Ok(None);
         }
+        // checksum is calculated before copying, so concurrent modification
+        // will be detected on next revalidation
+        let revision = {
+            let problem_dir = problem_dir.clone();
+            tokio::task::spawn_blocking(move || checksum_dir(&problem_dir))
+                .await
+                .unwrap()?
+        };
         let manifest = tokio::fs::read(manifest_path).await?;
         let manifest = serde_json::from_slice(&manifest).context("invalid problem manifest")?;
         let assets_dir = problem_dir.join("assets");
@@ -58,13 +111,43 @@ impl Registry for FsRegistry {
         })
         .await
         .unwrap()?;
-        Ok(Some(manifest))
+        Ok(Some((manifest, revision)))
     }
+
+    #[instrument]
+    async fn get_revision(&self, problem_name: &str) -> anyhow::Result<Option<Revision>> {
+        let problem_dir = self.problems_dir.join(problem_name);
+        if !problem_dir.join("manifest.json").exists() {
+            return Ok(None);
+        }
+        let revision = tokio::task::spawn_blocking(move || checksum_dir(&problem_dir))
+            .await
+            .unwrap()?;
+        Ok(Some(revision))
+    }
+}
+
+/// Computes revision of problem document without `revision` field (e.g.
+/// created by older apiserver), the same way apiserver does it on upload.
+fn document_checksum(doc: &bson::Document) -> anyhow::Result<Revision> {
+    let mut hasher = Sha256::new();
+    for field in &["manifest", "assets"] {
+        let data = doc
+            .get_binary_generic(field)
+            .with_context(|| format!("storage schema violation for field `{}`", field))?;
+        hasher.update(data);
+    }
+    Ok(Revision(hex::encode(hasher.finalize())))
 }
 
 /// Resolves problems via MongoDB
 pub struct MongoRegistry {
     collection: mongodb::Collection,
+    /// Checksums of problem documents without `revision` field, so that
+    /// revalidation does not have to fetch whole document each time. They
+    /// can not become outdated, because apiserver sets `revision` whenever
+    /// it writes problem.
+    checksums: Mutex<HashMap<String, Revision>>,
 }
 
 impl std::fmt::Debug for MongoRegistry {
@@ -83,7 +166,28 @@ impl MongoRegistry {
             .context("database is not available")?;
         let database = client.database("jjs");
         let collection = database.collection("problems");
-        Ok(MongoRegistry { collection })
+        Ok(MongoRegistry {
+            collection,
+            checksums: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns revision of problem document, computing it if document has
+    /// no `revision` field
+    fn document_revision(
+        &self,
+        problem_name: &str,
+        doc: &bson::Document,
+    ) -> anyhow::Result<Revision> {
+        if let Ok(revision) = doc.get_str("revision") {
+            return Ok(Revision(revision.to_string()));
+        }
+        let revision = document_checksum(doc)?;
+        self.checksums
+            .lock()
+            .unwrap()
+            .insert(problem_name.to_string(), revision.clone());
+        Ok(revision)
     }
 }
 
@@ -94,16 +198,11 @@ impl Registry for MongoRegistry {
         &self,
         problem_name: &str,
         target_path: &Path,
-    ) -> anyhow::Result<Option<pom::Problem>> {
+    ) -> anyhow::Result<Option<(pom::Problem, Revision)>> {
         // at first, let's find document about this problem
-        let filter = {
-            let mut filter = bson::Document::new();
-            filter.insert("problem-name", problem_name);
-            filter
-        };
         let doc = self
             .collection
-            .find_one(filter, None)
+            .find_one(problem_filter(problem_name), None)
             .await
             .context("problem document lookup failure")?;
         let doc = match doc {
@@ -112,6 +211,7 @@ impl Registry for MongoRegistry {
             None => return Ok(None),
         };
         tracing::info!("problem found");
+        let revision = self.document_revision(problem_name, &doc)?;
         let manifest = doc
             .get_binary_generic("manifest")
             .context("storage schema violation for field `manifest`")?;
@@ -137,6 +237,50 @@ impl Registry for MongoRegistry {
         .unwrap()
         .context("failed to unpack")?;
 
-        Ok(Some(manifest))
+        Ok(Some((manifest, revision)))
     }
+
+    #[instrument]
+    async fn get_revision(&self, problem_name: &str) -> anyhow::Result<Option<Revision>> {
+        // assets can be large, so we only fetch revision
+        let projection = {
+            let mut projection = bson::Document::new();
+            projection.insert("revision", 1);
+            projection
+        };
+        let options = mongodb::options::FindOneOptions::builder()
+            .projection(projection)
+            .build();
+        let doc = self
+            .collection
+            .find_one(problem_filter(problem_name), options)
+            .await
+            .context("problem document lookup failure")?;
+        let doc = match doc {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+        if let Ok(revision) = doc.get_str("revision") {
+            return Ok(Some(Revision(revision.to_string())));
+        }
+        if let Some(revision) = self.checksums.lock().unwrap().get(problem_name) {
+            return Ok(Some(revision.clone()));
+        }
+        // document has no revision, so we have to checksum it
+        let doc = self
+            .collection
+            .find_one(problem_filter(problem_name), None)
+            .await
+            .context("problem document lookup failure")?;
+        match doc {
+            Some(doc) => self.document_revision(problem_name, &doc).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn problem_filter(problem_name: &str) -> bson::Document {
+    let mut filter = bson::Document::new();
+    filter.insert("problem-name", problem_name);
+    filter
 }
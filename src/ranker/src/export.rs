@@ -0,0 +1,55 @@
+//! Exporting monitor to external formats
+mod cds;
+mod csv;
+mod html;
+
+pub use self::{
+    cds::{cds_judgements, cds_scoreboard, Judgement, JudgementEvent, Scoreboard},
+    csv::to_csv,
+    html::to_html,
+};
+
+use crate::{Cell, Config, PartyId, ProblemConfig, ProblemId, Ranking};
+use std::collections::BTreeMap;
+
+/// Information about contest, which is not stored in `Monitor`, but is
+/// needed by exporters
+pub struct ExportContext<'a> {
+    /// Contest name
+    pub title: &'a str,
+    /// Same problems as were used to build monitor
+    pub problems: &'a [(ProblemId, ProblemConfig)],
+    /// Display names of parties. Parties without name are shown by id.
+    pub party_names: &'a BTreeMap<PartyId, String>,
+    /// Same config as was used to build monitor
+    pub config: &'a Config,
+}
+
+impl ExportContext<'_> {
+    fn party_name(&self, party: PartyId) -> String {
+        match self.party_names.get(&party) {
+            Some(name) => name.clone(),
+            None => party.0.to_string(),
+        }
+    }
+}
+
+/// Returns short cell description, as usually seen in monitors.
+///
+/// In ICPC contests it is `+` or `+2` for solved problem, `-3` for unsolved
+/// problem and `?4` for problem with pending attempts (all attempts are
+/// counted). In other contests it is score.
+fn cell_text(cell: &Cell, config: &Config) -> String {
+    if cell.empty {
+        return String::new();
+    }
+    if cell.pending > 0 {
+        return format!("?{}", cell.attempts + cell.pending);
+    }
+    match config.ranking {
+        Ranking::Score => cell.score.to_string(),
+        Ranking::Icpc if cell.ok && cell.attempts == 0 => "+".to_string(),
+        Ranking::Icpc if cell.ok => format!("+{}", cell.attempts),
+        Ranking::Icpc => format!("-{}", cell.attempts),
+    }
+}
@@ -0,0 +1,185 @@
+//! ICPC Contest Data Server formats, as described in Contest API
+//! specification (version 2020-03).
+//!
+//! Team ids and problem ids are string representations of `PartyId`
+//! and `ProblemId`.
+use super::ExportContext;
+use crate::{run_score, Monitor, Ranking, Run, RunId, Score, Timeline, Visibility};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Contents of `/contests/<id>/scoreboard` endpoint
+#[derive(Debug, Serialize)]
+pub struct Scoreboard {
+    pub rows: Vec<ScoreboardRow>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreboardRow {
+    pub rank: u32,
+    pub team_id: String,
+    pub score: ScoreboardScore,
+    pub problems: Vec<ScoreboardProblem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreboardScore {
+    pub num_solved: u32,
+    /// Penalty in minutes
+    pub total_time: u32,
+    /// Present in score-based contests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreboardProblem {
+    pub problem_id: String,
+    pub num_judged: u32,
+    pub num_pending: u32,
+    pub solved: bool,
+    /// Minute of first full solution
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<u32>,
+    pub first_to_solve: bool,
+    /// Present in score-based contests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+}
+
+/// Exports monitor as CDS scoreboard
+pub fn cds_scoreboard(monitor: &Monitor, ctx: &ExportContext) -> Scoreboard {
+    let is_score_based = match ctx.config.ranking {
+        Ranking::Score => true,
+        Ranking::Icpc => false,
+    };
+    let rows = monitor
+        .ranking
+        .iter()
+        .map(|party| {
+            let row = &monitor.parties[party];
+            let problems = ctx
+                .problems
+                .iter()
+                .map(|(problem, _)| {
+                    let cell = &row.problems[problem];
+                    ScoreboardProblem {
+                        problem_id: problem.0.to_string(),
+                        num_judged: cell.attempts + u32::from(cell.ok),
+                        num_pending: cell.pending,
+                        solved: cell.ok,
+                        time: cell.accepted_at,
+                        first_to_solve: cell.marked,
+                        score: Some(cell.score).filter(|_| is_score_based),
+                    }
+                })
+                .collect();
+            ScoreboardRow {
+                rank: row.stats.place,
+                team_id: party.0.to_string(),
+                score: ScoreboardScore {
+                    num_solved: row.stats.solved,
+                    total_time: row.stats.penalty,
+                    score: Some(row.stats.score).filter(|_| is_score_based),
+                },
+                problems,
+            }
+        })
+        .collect();
+    Scoreboard { rows }
+}
+
+/// Judgement object
+#[derive(Debug, Serialize)]
+pub struct Judgement {
+    pub id: String,
+    pub submission_id: String,
+    /// `AC` or `RJ`
+    pub judgement_type_id: &'static str,
+    /// Absolute time, present only if contest start time is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<String>,
+    pub start_contest_time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<String>,
+    pub end_contest_time: String,
+}
+
+/// Event feed entry, describing judgement
+#[derive(Debug, Serialize)]
+pub struct JudgementEvent {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: String,
+    pub op: &'static str,
+    pub data: Judgement,
+}
+
+/// Formats duration as RELTIME, e.g. `1:02:03.004`
+fn format_reltime(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_millis()
+    )
+}
+
+/// Returns event feed entries for runs, ordered by time.
+///
+/// Runs are judged instantly, so judgements start and end at submission
+/// time. Judgements of runs for unknown problems are skipped.
+/// Judgements of runs which are ignored or hidden in monitor with given
+/// visibility are skipped too.
+pub fn cds_judgements(
+    runs: &[(RunId, Run)],
+    ctx: &ExportContext,
+    visibility: Visibility,
+    contest_start: Option<DateTime<Utc>>,
+) -> Vec<JudgementEvent> {
+    let mut runs: Vec<_> = runs
+        .iter()
+        .filter(|(_, run)| {
+            let timeline = Timeline::new(ctx.config, visibility, run.party);
+            !timeline.is_hidden(run) && !timeline.is_ignored(run)
+        })
+        .filter_map(|(run_id, run)| {
+            ctx.problems
+                .iter()
+                .find(|(problem, _)| *problem == run.problem)
+                .map(|(_, problem)| (run_id, run, problem))
+        })
+        .collect();
+    runs.sort_by_key(|(run_id, run, _)| (run.time, **run_id));
+    runs.into_iter()
+        .map(|(run_id, run, problem)| {
+            let judgement_type_id = if run_score(run) == problem.accepted_score {
+                "AC"
+            } else {
+                "RJ"
+            };
+            let absolute_time = contest_start.map(|start| {
+                let offset = chrono::Duration::from_std(run.time).expect("run time is too large");
+                (start + offset).to_rfc3339()
+            });
+            let contest_time = format_reltime(run.time);
+            JudgementEvent {
+                kind: "judgements",
+                id: run_id.0.to_string(),
+                op: "create",
+                data: Judgement {
+                    id: run_id.0.to_string(),
+                    submission_id: run_id.0.to_string(),
+                    judgement_type_id,
+                    start_time: absolute_time.clone(),
+                    start_contest_time: contest_time.clone(),
+                    end_time: absolute_time,
+                    end_contest_time: contest_time,
+                },
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,62 @@
+use super::{cell_text, ExportContext};
+use crate::{Monitor, Ranking};
+
+/// Quotes field if it contains special characters (RFC 4180).
+///
+/// Fields which spreadsheets would interpret as formulas are prefixed with
+/// `'`, because party names are provided by users.
+fn escape(field: &str) -> String {
+    let field = if field.starts_with(&['=', '+', '-', '@'][..]) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn write_record(out: &mut String, record: &[String]) {
+    let record: Vec<_> = record.iter().map(|field| escape(field)).collect();
+    out.push_str(&record.join(","));
+    out.push_str("\r\n");
+}
+
+/// Exports monitor as CSV table, suitable for spreadsheets.
+///
+/// Table has header, and then one record per party, ordered by place.
+/// Cells are formatted as in usual monitor (e.g. `+2`).
+pub fn to_csv(monitor: &Monitor, ctx: &ExportContext) -> String {
+    let mut out = String::new();
+    let mut header = vec!["Place".to_string(), "Party".to_string()];
+    header.extend(ctx.problems.iter().map(|(_, problem)| problem.name.clone()));
+    match ctx.config.ranking {
+        Ranking::Score => header.push("Score".to_string()),
+        Ranking::Icpc => {
+            header.push("Solved".to_string());
+            header.push("Penalty".to_string());
+        }
+    }
+    write_record(&mut out, &header);
+
+    for party in &monitor.ranking {
+        let row = &monitor.parties[party];
+        let mut record = vec![row.stats.place.to_string(), ctx.party_name(*party)];
+        record.extend(
+            ctx.problems
+                .iter()
+                .map(|(problem, _)| cell_text(&row.problems[problem], ctx.config)),
+        );
+        match ctx.config.ranking {
+            Ranking::Score => record.push(row.stats.score.to_string()),
+            Ranking::Icpc => {
+                record.push(row.stats.solved.to_string());
+                record.push(row.stats.penalty.to_string());
+            }
+        }
+        write_record(&mut out, &record);
+    }
+    out
+}
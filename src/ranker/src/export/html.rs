@@ -0,0 +1,147 @@
+use super::{cell_text, ExportContext};
+use crate::{Cell, Coloring, Monitor, PartyStats, ProblemStats, Ranking};
+use std::fmt::Write;
+
+const STYLE: &str = "
+body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999; padding: 2px 6px; text-align: center; }
+td.party { text-align: left; }
+tr.stripe-1 { background: #eee; }
+tr.gold { background: #ffe680; }
+tr.silver { background: #ddd; }
+tr.bronze { background: #e8b88a; }
+td.ok { color: #080; }
+td.fail { color: #c00; }
+td.pending { color: #00c; }
+td.first { background: #9e9; }
+tfoot td { color: #555; }
+";
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn row_class(stats: &PartyStats, coloring: &Coloring) -> String {
+    match coloring {
+        Coloring::Medals(_) => match stats.color {
+            0 => "gold".to_string(),
+            1 => "silver".to_string(),
+            2 => "bronze".to_string(),
+            _ => String::new(),
+        },
+        _ => format!("stripe-{}", stats.color % 2),
+    }
+}
+
+fn cell_class(cell: &Cell) -> &'static str {
+    if cell.marked {
+        "first"
+    } else if cell.pending > 0 {
+        "pending"
+    } else if cell.ok {
+        "ok"
+    } else if !cell.empty {
+        "fail"
+    } else {
+        ""
+    }
+}
+
+fn write_stats_row(
+    out: &mut String,
+    monitor: &Monitor,
+    ctx: &ExportContext,
+    caption: &str,
+    value: impl Fn(&ProblemStats) -> String,
+) {
+    writeln!(out, "<tr>\n<td></td><td class=\"party\">{}</td>", caption).unwrap();
+    for (problem, _) in ctx.problems {
+        writeln!(out, "<td>{}</td>", value(&monitor.stats.problems[problem])).unwrap();
+    }
+    match ctx.config.ranking {
+        Ranking::Score => writeln!(out, "<td></td>").unwrap(),
+        Ranking::Icpc => writeln!(out, "<td></td><td></td>").unwrap(),
+    }
+    writeln!(out, "</tr>").unwrap();
+}
+
+/// Exports monitor as self-contained HTML page.
+///
+/// Rows are highlighted according to party colors, and footer contains
+/// problem stats.
+pub fn to_html(monitor: &Monitor, ctx: &ExportContext) -> String {
+    // writing to String never fails
+    let mut out = String::new();
+    let title = escape(ctx.title);
+    writeln!(out, "<!DOCTYPE html>").unwrap();
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">").unwrap();
+    writeln!(out, "<title>{}</title>", title).unwrap();
+    writeln!(out, "<style>{}</style>\n</head>\n<body>", STYLE).unwrap();
+    writeln!(out, "<h1>{}</h1>\n<table>\n<thead>\n<tr>", title).unwrap();
+    writeln!(out, "<th>Place</th><th>Party</th>").unwrap();
+    for (_, problem) in ctx.problems {
+        writeln!(out, "<th>{}</th>", escape(&problem.name)).unwrap();
+    }
+    match ctx.config.ranking {
+        Ranking::Score => writeln!(out, "<th>Score</th>").unwrap(),
+        Ranking::Icpc => writeln!(out, "<th>Solved</th><th>Penalty</th>").unwrap(),
+    }
+    writeln!(out, "</tr>\n</thead>\n<tbody>").unwrap();
+
+    for party in &monitor.ranking {
+        let row = &monitor.parties[party];
+        writeln!(
+            out,
+            "<tr class=\"{}\">\n<td>{}</td><td class=\"party\">{}</td>",
+            row_class(&row.stats, &ctx.config.coloring),
+            row.stats.place,
+            escape(&ctx.party_name(*party))
+        )
+        .unwrap();
+        for (problem, _) in ctx.problems {
+            let cell = &row.problems[problem];
+            writeln!(
+                out,
+                "<td class=\"{}\">{}</td>",
+                cell_class(cell),
+                cell_text(cell, ctx.config)
+            )
+            .unwrap();
+        }
+        match ctx.config.ranking {
+            Ranking::Score => writeln!(out, "<td>{}</td>", row.stats.score).unwrap(),
+            Ranking::Icpc => writeln!(
+                out,
+                "<td>{}</td><td>{}</td>",
+                row.stats.solved, row.stats.penalty
+            )
+            .unwrap(),
+        }
+        writeln!(out, "</tr>").unwrap();
+    }
+
+    writeln!(out, "</tbody>\n<tfoot>").unwrap();
+    write_stats_row(&mut out, monitor, ctx, "Runs", |stats| {
+        stats.total_runs.to_string()
+    });
+    write_stats_row(&mut out, monitor, ctx, "Accepted", |stats| {
+        stats.accepted_runs.to_string()
+    });
+    write_stats_row(&mut out, monitor, ctx, "Max score", |stats| {
+        stats.max_score.to_string()
+    });
+    writeln!(out, "</tfoot>\n</table>\n</body>\n</html>").unwrap();
+    out
+}
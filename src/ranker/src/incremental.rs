@@ -0,0 +1,227 @@
+//! Incremental monitor building
+use crate::{
+    build_cell, build_monitor_for, build_party_totals, build_places_and_colors,
+    mark_first_solutions, Config, Monitor, PartyId, PartyRow, PartyStats, ProblemConfig, ProblemId,
+    ProblemStats, Run, RunId, Timeline, Visibility,
+};
+use serde::Serialize;
+use std::{
+    cmp,
+    collections::{BTreeMap, BTreeSet},
+};
+
+/// Change of contest runs
+#[derive(Debug)]
+pub enum RunChange {
+    /// New run was submitted
+    Insert(RunId, Run),
+    /// Run was rejudged. Unknown run is inserted.
+    Update(RunId, Run),
+    /// Run was deleted (e.g. ignored by jury). Unknown run is ignored.
+    Delete(RunId),
+}
+
+/// Changes of monitor, caused by some `RunChange`s
+#[derive(Debug, Default, Serialize, Eq, PartialEq)]
+pub struct MonitorDiff {
+    /// New versions of changed rows. Row is changed if some cell or party stats
+    /// (including place and color) is changed.
+    pub parties: BTreeMap<PartyId, PartyRow>,
+    /// New stats of changed problems
+    pub problems: BTreeMap<ProblemId, ProblemStats>,
+    /// New ranking, if it is changed
+    pub ranking: Option<Vec<PartyId>>,
+}
+
+impl MonitorDiff {
+    pub fn is_empty(&self) -> bool {
+        self.parties.is_empty() && self.problems.is_empty() && self.ranking.is_none()
+    }
+}
+
+/// Maintains `Monitor` for changing set of runs.
+///
+/// Only cells of changed runs are rebuilt, and only stats of their parties and
+/// problems are recalculated. Places and colors depend on all parties, so they
+/// are recalculated on every change.
+pub struct MonitorBuilder {
+    problems: Vec<(ProblemId, ProblemConfig)>,
+    parties: Vec<PartyId>,
+    config: Config,
+    visibility: Visibility,
+    /// Cells in which all results are shown, regardless of visibility
+    revealed_cells: BTreeSet<(PartyId, ProblemId)>,
+    runs: BTreeMap<RunId, Run>,
+    runs_by_party_and_problem: BTreeMap<(PartyId, ProblemId), BTreeSet<RunId>>,
+    /// Contribution of each cell to `ProblemStats`
+    cell_stats: BTreeMap<(PartyId, ProblemId), ProblemStats>,
+    monitor: Monitor,
+}
+
+impl MonitorBuilder {
+    /// Creates builder of jury monitor with empty run set
+    pub fn new(
+        problems: Vec<(ProblemId, ProblemConfig)>,
+        parties: Vec<PartyId>,
+        config: Config,
+    ) -> MonitorBuilder {
+        MonitorBuilder::with_visibility(problems, parties, config, Visibility::Jury)
+    }
+
+    /// Creates builder of monitor with given visibility and empty run set
+    pub fn with_visibility(
+        problems: Vec<(ProblemId, ProblemConfig)>,
+        parties: Vec<PartyId>,
+        config: Config,
+        visibility: Visibility,
+    ) -> MonitorBuilder {
+        let monitor = build_monitor_for(&[], &problems, &parties, &config, visibility);
+        MonitorBuilder {
+            problems,
+            parties,
+            config,
+            visibility,
+            revealed_cells: BTreeSet::new(),
+            runs: BTreeMap::new(),
+            runs_by_party_and_problem: BTreeMap::new(),
+            cell_stats: BTreeMap::new(),
+            monitor,
+        }
+    }
+
+    /// Returns up-to-date monitor
+    pub fn monitor(&self) -> &Monitor {
+        &self.monitor
+    }
+
+    /// Applies single change
+    pub fn apply(&mut self, change: RunChange) -> MonitorDiff {
+        self.apply_all(std::iter::once(change))
+    }
+
+    /// Applies several changes at once. This is faster than applying them
+    /// one by one, because places and colors are only recalculated once.
+    pub fn apply_all(&mut self, changes: impl IntoIterator<Item = RunChange>) -> MonitorDiff {
+        let mut changed_cells = BTreeSet::new();
+        for change in changes {
+            let (run_id, new_run) = match change {
+                RunChange::Insert(run_id, run) | RunChange::Update(run_id, run) => {
+                    (run_id, Some(run))
+                }
+                RunChange::Delete(run_id) => (run_id, None),
+            };
+            if let Some(old_run) = self.runs.remove(&run_id) {
+                let key = (old_run.party, old_run.problem);
+                if let Some(run_ids) = self.runs_by_party_and_problem.get_mut(&key) {
+                    run_ids.remove(&run_id);
+                }
+                changed_cells.insert(key);
+            }
+            if let Some(run) = new_run {
+                let key = (run.party, run.problem);
+                self.runs_by_party_and_problem
+                    .entry(key)
+                    .or_default()
+                    .insert(run_id);
+                self.runs.insert(run_id, run);
+                changed_cells.insert(key);
+            }
+        }
+        self.rebuild(changed_cells)
+    }
+
+    /// Shows hidden results in the given cell, as if monitor was not frozen
+    /// for it. Later changes of runs in this cell will be shown too.
+    pub fn reveal(&mut self, party: PartyId, problem: ProblemId) -> MonitorDiff {
+        self.revealed_cells.insert((party, problem));
+        self.rebuild(std::iter::once((party, problem)).collect())
+    }
+
+    fn rebuild(&mut self, changed_cells: BTreeSet<(PartyId, ProblemId)>) -> MonitorDiff {
+        let old_stats: BTreeMap<PartyId, PartyStats> = self
+            .monitor
+            .parties
+            .iter()
+            .map(|(&party, row)| (party, row.stats.clone()))
+            .collect();
+        let mut changed_parties = BTreeSet::new();
+        let mut changed_problems = BTreeSet::new();
+        for (party, problem) in changed_cells {
+            let problem_config = match self.problems.iter().find(|p| p.0 == problem) {
+                Some(p) => &p.1,
+                // runs for unknown problems and parties are ignored, as in `build_monitor`
+                None => continue,
+            };
+            let row = match self.monitor.parties.get_mut(&party) {
+                Some(row) => row,
+                None => continue,
+            };
+            let runs = &self.runs;
+            let cell_runs = self
+                .runs_by_party_and_problem
+                .get(&(party, problem))
+                .into_iter()
+                .flatten()
+                .map(|run_id| &runs[run_id]);
+            let mut timeline = Timeline::new(&self.config, self.visibility, party);
+            if self.revealed_cells.contains(&(party, problem)) {
+                timeline.hidden_since = None;
+            }
+            let mut cell_stats = ProblemStats::default();
+            let mut cell = build_cell(
+                cell_runs,
+                problem_config,
+                &self.config,
+                timeline,
+                &mut cell_stats,
+            );
+            self.cell_stats.insert((party, problem), cell_stats);
+            changed_problems.insert(problem);
+            // marks are updated below
+            cell.marked = row.problems[&problem].marked;
+            if row.problems[&problem] != cell {
+                row.problems.insert(problem, cell);
+                changed_parties.insert(party);
+            }
+        }
+
+        let mut diff = MonitorDiff::default();
+        for &problem in &changed_problems {
+            changed_parties.extend(mark_first_solutions(
+                &mut self.monitor,
+                &self.parties,
+                problem,
+            ));
+        }
+        for problem in changed_problems {
+            let mut stats = ProblemStats::default();
+            for party in &self.parties {
+                if let Some(cell_stats) = self.cell_stats.get(&(*party, problem)) {
+                    stats.total_runs += cell_stats.total_runs;
+                    stats.accepted_runs += cell_stats.accepted_runs;
+                    stats.max_score = cmp::max(stats.max_score, cell_stats.max_score);
+                }
+            }
+            let current = self.monitor.stats.problems.get_mut(&problem).unwrap();
+            if *current != stats {
+                *current = stats.clone();
+                diff.problems.insert(problem, stats);
+            }
+        }
+
+        for party in &changed_parties {
+            build_party_totals(self.monitor.parties.get_mut(party).unwrap(), &self.config);
+        }
+        let old_ranking = std::mem::take(&mut self.monitor.ranking);
+        build_places_and_colors(&mut self.monitor, &self.parties, &self.config);
+        if self.monitor.ranking != old_ranking {
+            diff.ranking = Some(self.monitor.ranking.clone());
+        }
+        for (party, row) in &self.monitor.parties {
+            if changed_parties.contains(party) || old_stats[party] != row.stats {
+                diff.parties.insert(*party, row.clone());
+            }
+        }
+        diff
+    }
+}
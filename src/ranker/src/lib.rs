@@ -1,11 +1,19 @@
 //! Ranker is library, responsible for generating monitor
 //! Is is used in both apiserver and invoker
 
+pub mod export;
+mod incremental;
+mod resolver;
+mod series;
 #[cfg(test)]
 mod tests;
 
+pub use incremental::{MonitorBuilder, MonitorDiff, RunChange};
+pub use resolver::{Resolver, ResolverStep};
+pub use series::{build_series_monitor, Round, SeriesAggregation, SeriesMonitor, SeriesRow};
+
 use serde::Serialize;
-use std::{cmp, cmp::Ordering, collections::BTreeMap, num::NonZeroU32};
+use std::{cmp, cmp::Ordering, collections::BTreeMap, num::NonZeroU32, time::Duration};
 
 #[derive(Hash, Ord, PartialOrd, Eq, PartialEq, Debug, Serialize, Copy, Clone)]
 pub struct SubtaskId(pub NonZeroU32);
@@ -26,10 +34,17 @@ pub struct Run {
     pub subtasks: BTreeMap<SubtaskId, Score>,
     pub party: PartyId,
     pub problem: ProblemId,
+    /// Submission time, relative to contest start
+    pub time: Duration,
+}
+
+/// Converts time relative to contest start to minutes, as used in penalty
+fn minutes(time: Duration) -> u32 {
+    (time.as_secs() / 60) as u32
 }
 
 /// Represents one cell in monitor
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct Cell {
     /// True if party haven't attempted to solve problem
     pub empty: bool,
@@ -38,18 +53,31 @@ pub struct Cell {
     /// Score gained
     pub score: Score,
     /// True if cell should be highlighted
-    /// For example, in ICPC contest run will be `marked` probably if it is first full solution for problem
+    ///
+    /// Cell is `marked` if it contains first full solution for problem
+    /// (several cells are marked if they were solved at the same time)
     pub marked: bool,
     /// Count of non-ignored attempts which should be displayed
     ///
     /// E.g. not accounts for runs after full solution
     pub attempts: u32,
+    /// Minute of first full solution, if problem is solved
+    pub accepted_at: Option<u32>,
+    /// Penalty in minutes: `accepted_at` plus penalty for each attempt.
+    /// Zero if problem is not solved.
+    pub penalty: u32,
+    /// Count of attempts with hidden results, i.e. submitted after freeze.
+    /// Not included in `attempts`.
+    pub pending: u32,
+    /// Exact time of first full solution, used to mark cells
+    #[serde(skip)]
+    accepted_time: Option<Duration>,
 }
 
 /// Represents some properties of row, describing party
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct PartyStats {
-    /// This is used to distinguish groups of parties
+    /// This is used to distinguish groups of parties, see [`Coloring`](Coloring)
     ///
     /// E.g.: participants who solved 8 problems, get `color == 0`; participants who solved 7
     /// problems get `color == 1`, and so on.
@@ -58,16 +86,22 @@ pub struct PartyStats {
     pub color: u32,
     /// Total score gained by party in contest
     pub score: Score,
+    /// Count of solved problems
+    pub solved: u32,
+    /// Total penalty in minutes, aggregated according to `Config::penalty_aggregation`
+    pub penalty: u32,
+    /// Place of party, starting from 1. Parties which are tied share the same place.
+    pub place: u32,
 }
 
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct PartyRow {
     stats: PartyStats,
     problems: BTreeMap<ProblemId, Cell>,
 }
 
 /// Represents some statistics of problem
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Eq, PartialEq)]
 pub struct ProblemStats {
     pub total_runs: u32,
     /// How many runs are accepted. If one party made two accepted runs, both are counted.
@@ -79,7 +113,7 @@ pub struct ProblemStats {
     pub max_score: Score,
 }
 
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct StatsRow {
     pub problems: BTreeMap<ProblemId, ProblemStats>,
 }
@@ -91,10 +125,11 @@ pub struct StatsRow {
 pub enum RunScoreAggregationTarget {
     /// All runs will be used
     All,
-    /// `k` latest (later = id is greater) will be used
+    /// `k` latest (later = submitted later; runs with same time are ordered
+    /// as passed to `build_monitor`, or by id in `MonitorBuilder`) will be used
     Latest(u32),
     /// Run with max score will be used
-    /// If there are several runs with max score, run with greatest id will be used
+    /// If there are several runs with max score, latest run will be used
     Best,
 }
 
@@ -116,40 +151,197 @@ pub struct ProblemConfig {
     pub aggregation: RunScoreAggregation,
 }
 
+/// Determines how party penalty is calculated from penalties of solved problems
 #[derive(Debug)]
 pub enum PenaltyAggregation {
     Sum,
     Max,
 }
 
+/// Determines order of parties in monitor
+#[derive(Debug)]
+pub enum Ranking {
+    /// Parties are ordered by total score
+    Score,
+    /// ICPC rules: parties are ordered by count of solved problems, then
+    /// by penalty, then by time of last solved problem (earlier is better)
+    Icpc,
+}
+
+/// Determines which problems will be used to calculate total score of party
 #[derive(Debug)]
 pub enum ProblemScoreAggregationTarget {
+    /// Scores for all problems are summed
     All,
+    /// Only `k` problems with max scores are summed
     Best(u32),
 }
 
 #[derive(Debug)]
 pub struct Config {
     pub penalty_aggregation: PenaltyAggregation,
+    /// Penalty in minutes for each rejected run before first full solution
+    /// (20 in ICPC)
+    pub attempt_penalty: u32,
     pub score_problems: ProblemScoreAggregationTarget,
+    pub ranking: Ranking,
+    /// Results of runs submitted at this time since party start or later
+    /// are hidden in public monitor
+    pub freeze_time: Option<Duration>,
+    /// Start times of parties relative to contest start, for virtual
+    /// participations. Parties which are not listed started with contest.
+    ///
+    /// All times in monitor (e.g. penalty or freeze) are counted since
+    /// party start, so virtual parties are compared with original ones
+    /// as if they participated simultaneously.
+    pub start_offsets: BTreeMap<PartyId, Duration>,
+    /// If set, monitor is built as of this time since party start, i.e. runs
+    /// submitted later are ignored.
+    ///
+    /// Together with `start_offsets`, this gives "ghost" view: virtual party,
+    /// which started `elapsed` ago, is compared with original parties
+    /// at the same point of contest.
+    pub elapsed: Option<Duration>,
+    pub coloring: Coloring,
+}
+
+/// Determines how `PartyStats::color` is calculated
+#[derive(Debug)]
+pub enum Coloring {
+    /// Parties with equal count of solved problems get same color.
+    /// Parties with more problems solved get lesser color.
+    Solved,
+    /// Parties with equal `score / bucket_size` get same color.
+    /// Parties with greater score get lesser color.
+    /// `bucket_size` must be positive.
+    ScoreBuckets(Score),
+    /// Colors are 0 for gold medal, 1 for silver, 2 for bronze and 3 for
+    /// parties without medal
+    Medals(MedalCutoffs),
+}
+
+/// Last places which get medals. Cutoffs are cumulative, e.g. with
+/// `gold: Rank(4), silver: Rank(8)` places 5 to 8 get silver.
+/// Parties which are tied share place, so they always get the same medal.
+#[derive(Debug)]
+pub struct MedalCutoffs {
+    pub gold: Cutoff,
+    pub silver: Cutoff,
+    pub bronze: Cutoff,
+}
+
+#[derive(Debug)]
+pub enum Cutoff {
+    /// Parties with place not greater than given get medal
+    Rank(u32),
+    /// Given percent of parties (rounded down) get medal
+    Percent(u32),
+}
+
+impl Cutoff {
+    /// Returns last place which gets medal
+    fn last_place(&self, party_count: usize) -> u32 {
+        match *self {
+            Cutoff::Rank(place) => place,
+            Cutoff::Percent(percent) => (party_count as u64 * u64::from(percent) / 100) as u32,
+        }
+    }
+}
+
+/// Determines who will see monitor
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Visibility {
+    /// Complete monitor, as seen by jury
+    Jury,
+    /// Results of runs submitted after [`Config::freeze_time`](Config::freeze_time)
+    /// are hidden
+    Public,
+}
+
+/// Determines how run times of one party are interpreted
+#[derive(Debug, Copy, Clone)]
+struct Timeline {
+    /// Party start, relative to contest start
+    start_offset: Duration,
+    /// Results of runs submitted at this time since party start or later
+    /// are hidden
+    hidden_since: Option<Duration>,
+    /// Runs submitted at this time since party start or later are ignored
+    until: Option<Duration>,
 }
 
-#[derive(Debug, Serialize, Eq, PartialEq)]
+impl Timeline {
+    fn new(config: &Config, visibility: Visibility, party: PartyId) -> Timeline {
+        let start_offset = config
+            .start_offsets
+            .get(&party)
+            .copied()
+            .unwrap_or_default();
+        Timeline {
+            start_offset,
+            hidden_since: match visibility {
+                Visibility::Jury => None,
+                Visibility::Public => config.freeze_time,
+            },
+            until: config.elapsed,
+        }
+    }
+
+    /// Returns submission time of run, relative to party start
+    fn time_since_start(&self, run: &Run) -> Duration {
+        run.time.checked_sub(self.start_offset).unwrap_or_default()
+    }
+
+    fn is_ignored(&self, run: &Run) -> bool {
+        match self.until {
+            Some(until) => self.time_since_start(run) >= until,
+            None => false,
+        }
+    }
+
+    fn is_hidden(&self, run: &Run) -> bool {
+        match self.hidden_since {
+            Some(since) => self.time_since_start(run) >= since,
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
 pub struct Monitor {
     pub parties: BTreeMap<PartyId, PartyRow>,
     pub stats: StatsRow,
+    /// All parties, from first place to last
+    pub ranking: Vec<PartyId>,
 }
 
-/// Builds a `Monitor`, given list of all runs
+/// Builds a jury `Monitor`, given list of all runs
+///
+/// See [`MonitorBuilder`](MonitorBuilder) for updating monitor incrementally.
 /// # Panics
 /// Panics if provided arguments are invalid
-// Probably, later some means to build this incrementally will be implemented
 pub fn build_monitor(
     runs: &[Run],
     problems: &[(ProblemId, ProblemConfig)],
     parties: &[PartyId],
-    _config: &Config,
+    config: &Config,
 ) -> Monitor {
+    build_monitor_for(runs, problems, parties, config, Visibility::Jury)
+}
+
+/// Builds a `Monitor` with given visibility, given list of all runs
+/// # Panics
+/// Panics if provided arguments are invalid
+pub fn build_monitor_for(
+    runs: &[Run],
+    problems: &[(ProblemId, ProblemConfig)],
+    parties: &[PartyId],
+    config: &Config,
+    visibility: Visibility,
+) -> Monitor {
+    if let Coloring::ScoreBuckets(bucket_size) = config.coloring {
+        assert!(bucket_size > 0, "score bucket size must be positive");
+    }
     let mut party_info = BTreeMap::new();
     let mut runs_by_party_and_problem = BTreeMap::new();
     for (i, run) in runs.iter().enumerate() {
@@ -185,13 +377,20 @@ pub fn build_monitor(
             let runs = run_ids.iter().map(|&run_id| &runs[run_id]);
             let problem_stats = stats.problems.get_mut(&problem.0).unwrap();
 
-            let cell = build_cell(runs, &problem.1, problem_stats);
+            let timeline = Timeline::new(config, visibility, party);
+            let cell = build_cell(runs, &problem.1, config, timeline, problem_stats);
 
             cell_by_party_and_problem.insert((party, problem.0), cell);
         }
     }
     for &party in parties {
-        let stats = PartyStats { color: 0, score: 0 };
+        let stats = PartyStats {
+            color: 0,
+            score: 0,
+            solved: 0,
+            penalty: 0,
+            place: 0,
+        };
         let mut row = PartyRow {
             stats,
             problems: BTreeMap::new(),
@@ -209,50 +408,101 @@ pub fn build_monitor(
     let mut mon = Monitor {
         parties: party_info,
         stats,
+        ranking: Vec::new(),
     };
-    build_party_stats(&mut mon, parties);
+    for problem in problems {
+        mark_first_solutions(&mut mon, parties, problem.0);
+    }
+    build_party_stats(&mut mon, parties, config);
     mon
 }
 
+fn run_score(run: &Run) -> Score {
+    run.subtasks.values().sum()
+}
+
+/// Builds cell from runs of one party on one problem
 fn build_cell<'a>(
     runs: impl Iterator<Item = &'a Run>,
     problem: &ProblemConfig,
+    config: &Config,
+    timeline: Timeline,
     problem_stats: &mut ProblemStats,
 ) -> Cell {
     let mut cell = Cell {
         empty: true,
         ok: false,
         score: 0,
-        // TODO
+        // will be set in `mark_first_solutions`
         marked: false,
         attempts: 0,
+        accepted_at: None,
+        penalty: 0,
+        pending: 0,
+        accepted_time: None,
     };
-    let mut max_based_score = 0;
-    let mut merge_subtask_based_score = BTreeMap::new();
-
+    // attempts are counted before first full solution, so order matters
+    let mut runs: Vec<_> = runs.filter(|run| !timeline.is_ignored(run)).collect();
+    runs.sort_by_key(|run| run.time);
+    let visible_count = runs
+        .iter()
+        .take_while(|run| !timeline.is_hidden(run))
+        .count();
+    let (runs, hidden_runs) = runs.split_at(visible_count);
     for run in runs {
         // cell is not empty, because there are attempts for this problem
         cell.empty = false;
         problem_stats.total_runs += 1;
-        let mut run_score = 0;
-        for (&st_id, &st_score) in run.subtasks.iter() {
-            run_score += st_score;
-            let subtask_opt = merge_subtask_based_score.entry(st_id).or_insert(0);
-            *subtask_opt = cmp::max(*subtask_opt, st_score);
-        }
-        max_based_score = cmp::max(max_based_score, run_score);
-
-        match run_score.cmp(&problem.accepted_score) {
+        match run_score(run).cmp(&problem.accepted_score) {
             Ordering::Less => {
-                cell.attempts += 1;
+                if !cell.ok {
+                    cell.attempts += 1;
+                }
             }
             Ordering::Equal => {
-                cell.ok = true;
+                if !cell.ok {
+                    let accepted_time = timeline.time_since_start(run);
+                    let accepted_at = minutes(accepted_time);
+                    cell.ok = true;
+                    cell.accepted_at = Some(accepted_at);
+                    cell.accepted_time = Some(accepted_time);
+                    cell.penalty = accepted_at + cell.attempts * config.attempt_penalty;
+                }
                 problem_stats.accepted_runs += 1;
             }
             Ordering::Greater => panic!("run's score is more than total possible"),
         }
     }
+
+    if !hidden_runs.is_empty() {
+        cell.empty = false;
+        problem_stats.total_runs += hidden_runs.len() as u32;
+        // runs after full solution are not interesting anyway
+        if !cell.ok {
+            cell.pending = hidden_runs.len() as u32;
+        }
+    }
+
+    let scored_runs = match problem.score_runs {
+        RunScoreAggregationTarget::All => runs,
+        RunScoreAggregationTarget::Latest(k) => &runs[runs.len().saturating_sub(k as usize)..],
+        RunScoreAggregationTarget::Best => {
+            // on ties, `max_by_key` returns last element, i.e. latest run
+            match (0..runs.len()).max_by_key(|&i| run_score(runs[i])) {
+                Some(i) => &runs[i..=i],
+                None => &[],
+            }
+        }
+    };
+    let mut max_based_score = 0;
+    let mut merge_subtask_based_score = BTreeMap::new();
+    for run in scored_runs {
+        for (&st_id, &st_score) in run.subtasks.iter() {
+            let subtask_opt = merge_subtask_based_score.entry(st_id).or_insert(0);
+            *subtask_opt = cmp::max(*subtask_opt, st_score);
+        }
+        max_based_score = cmp::max(max_based_score, run_score(run));
+    }
     cell.score = match problem.aggregation {
         RunScoreAggregation::Max => max_based_score,
         RunScoreAggregation::MergeSubtasks => {
@@ -263,21 +513,97 @@ fn build_cell<'a>(
     cell
 }
 
-fn build_party_stats(mon: &mut Monitor, parties: &[PartyId]) {
-    // step 1: calculate PartyStats.score
+/// Sets `Cell::marked` for all cells of the given problem.
+/// Returns parties whose cells were changed.
+fn mark_first_solutions(
+    mon: &mut Monitor,
+    parties: &[PartyId],
+    problem: ProblemId,
+) -> Vec<PartyId> {
+    let first_accepted_time = parties
+        .iter()
+        .filter_map(|party| mon.parties[party].problems[&problem].accepted_time)
+        .min();
+    let mut changed = Vec::new();
+    for &party in parties {
+        let cell = mon
+            .parties
+            .get_mut(&party)
+            .unwrap()
+            .problems
+            .get_mut(&problem)
+            .unwrap();
+        let marked = cell.accepted_time.is_some() && cell.accepted_time == first_accepted_time;
+        if cell.marked != marked {
+            cell.marked = marked;
+            changed.push(party);
+        }
+    }
+    changed
+}
+
+fn build_party_stats(mon: &mut Monitor, parties: &[PartyId], config: &Config) {
+    // step 1: calculate PartyStats.score, solved and penalty
     for party in parties {
-        let mut score = 0;
-        for cell in mon.parties[party].problems.values() {
-            score += cell.score;
+        build_party_totals(mon.parties.get_mut(party).unwrap(), config);
+    }
+    build_places_and_colors(mon, parties, config);
+}
+
+/// Calculates PartyStats.score, solved and penalty from cells
+fn build_party_totals(row: &mut PartyRow, config: &Config) {
+    let mut scores: Vec<_> = row.problems.values().map(|cell| cell.score).collect();
+    if let ProblemScoreAggregationTarget::Best(k) = config.score_problems {
+        scores.sort_unstable_by(|a, b| b.cmp(a));
+        scores.truncate(k as usize);
+    }
+    let score = scores.into_iter().sum();
+    let mut solved = 0;
+    let mut penalty = 0;
+    for cell in row.problems.values() {
+        if cell.ok {
+            solved += 1;
+            penalty = match config.penalty_aggregation {
+                PenaltyAggregation::Sum => penalty + cell.penalty,
+                PenaltyAggregation::Max => cmp::max(penalty, cell.penalty),
+            };
         }
-        mon.parties.get_mut(party).unwrap().stats.score = score;
     }
-    // step 2: calculate PartyStats.color
+    row.stats.score = score;
+    row.stats.solved = solved;
+    row.stats.penalty = penalty;
+}
+
+/// Calculates PartyStats.place and color. These depend on all parties.
+fn build_places_and_colors(mon: &mut Monitor, parties: &[PartyId], config: &Config) {
+    // step 2: sort parties and assign places
+    rank_parties(mon, parties, config);
+    // step 3: calculate PartyStats.color
+    if let Coloring::Medals(cutoffs) = &config.coloring {
+        let last_places = [
+            cutoffs.gold.last_place(parties.len()),
+            cutoffs.silver.last_place(parties.len()),
+            cutoffs.bronze.last_place(parties.len()),
+        ];
+        for party in parties {
+            let stats = &mut mon.parties.get_mut(party).unwrap().stats;
+            stats.color = last_places
+                .iter()
+                .position(|&last_place| stats.place <= last_place)
+                .unwrap_or(last_places.len()) as u32;
+        }
+        return;
+    }
     // at first, we want to calculate coloring key
-    // TODO: it is hardcoded as score / 100
     let mut coloring_key = BTreeMap::new();
     for &party in parties {
-        coloring_key.insert(party, mon.parties[&party].stats.score / 100);
+        let stats = &mon.parties[&party].stats;
+        let key = match config.coloring {
+            Coloring::Solved => stats.solved as Score,
+            Coloring::ScoreBuckets(bucket_size) => stats.score / bucket_size,
+            Coloring::Medals(_) => unreachable!(),
+        };
+        coloring_key.insert(party, key);
     }
     let mut distinct_color_keys: Vec<_> = coloring_key
         .values()
@@ -294,3 +620,47 @@ fn build_party_stats(mon: &mut Monitor, parties: &[PartyId]) {
         mon.parties.get_mut(&party).unwrap().stats.color = color as u32;
     }
 }
+
+/// Key used to order parties. Lesser key is better, and parties with equal
+/// keys share the same place.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum RankingKey {
+    Score(cmp::Reverse<Score>),
+    Icpc {
+        solved: cmp::Reverse<u32>,
+        penalty: u32,
+        last_accepted_at: u32,
+    },
+}
+
+fn ranking_key(row: &PartyRow, config: &Config) -> RankingKey {
+    match config.ranking {
+        Ranking::Score => RankingKey::Score(cmp::Reverse(row.stats.score)),
+        Ranking::Icpc => RankingKey::Icpc {
+            solved: cmp::Reverse(row.stats.solved),
+            penalty: row.stats.penalty,
+            last_accepted_at: row
+                .problems
+                .values()
+                .filter_map(|cell| cell.accepted_at)
+                .max()
+                .unwrap_or(0),
+        },
+    }
+}
+
+fn rank_parties(mon: &mut Monitor, parties: &[PartyId], config: &Config) {
+    let mut keyed: Vec<_> = parties
+        .iter()
+        .map(|&party| (ranking_key(&mon.parties[&party], config), party))
+        .collect();
+    keyed.sort();
+    let mut place = 0;
+    for (i, (key, party)) in keyed.iter().enumerate() {
+        if i == 0 || keyed[i - 1].0 != *key {
+            place = i as u32 + 1;
+        }
+        mon.parties.get_mut(party).unwrap().stats.place = place;
+    }
+    mon.ranking = keyed.into_iter().map(|(_key, party)| party).collect();
+}
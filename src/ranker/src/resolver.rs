@@ -0,0 +1,69 @@
+//! Unfreezing monitor, e.g. during awards ceremony
+use crate::{
+    Config, Monitor, MonitorBuilder, MonitorDiff, PartyId, ProblemConfig, ProblemId, Run,
+    RunChange, RunId, Visibility,
+};
+
+/// One revealed cell
+#[derive(Debug)]
+pub struct ResolverStep {
+    pub party: PartyId,
+    pub problem: ProblemId,
+    /// Changes of public monitor, caused by revealing this cell
+    pub diff: MonitorDiff,
+}
+
+/// Reveals pending cells of frozen monitor one by one.
+///
+/// Standard order is used: on each step, lowest-ranked party with pending
+/// cells is selected, and its first pending cell is revealed. After all
+/// cells are revealed, monitor is equal to jury monitor.
+pub struct Resolver {
+    builder: MonitorBuilder,
+}
+
+impl Resolver {
+    /// Creates resolver, starting from public monitor built from `runs`
+    pub fn new(
+        runs: impl IntoIterator<Item = (RunId, Run)>,
+        problems: Vec<(ProblemId, ProblemConfig)>,
+        parties: Vec<PartyId>,
+        config: Config,
+    ) -> Resolver {
+        let mut builder =
+            MonitorBuilder::with_visibility(problems, parties, config, Visibility::Public);
+        builder.apply_all(
+            runs.into_iter()
+                .map(|(run_id, run)| RunChange::Insert(run_id, run)),
+        );
+        Resolver { builder }
+    }
+
+    /// Returns current state of monitor
+    pub fn monitor(&self) -> &Monitor {
+        self.builder.monitor()
+    }
+
+    /// Returns cell which will be revealed on next step
+    pub fn next_cell(&self) -> Option<(PartyId, ProblemId)> {
+        let monitor = self.builder.monitor();
+        monitor.ranking.iter().rev().find_map(|&party| {
+            monitor.parties[&party]
+                .problems
+                .iter()
+                .find(|(_, cell)| cell.pending > 0)
+                .map(|(&problem, _)| (party, problem))
+        })
+    }
+
+    /// Reveals next cell. Returns `None` if there are no pending cells.
+    pub fn step(&mut self) -> Option<ResolverStep> {
+        let (party, problem) = self.next_cell()?;
+        let diff = self.builder.reveal(party, problem);
+        Some(ResolverStep {
+            party,
+            problem,
+            diff,
+        })
+    }
+}
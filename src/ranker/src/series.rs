@@ -0,0 +1,196 @@
+//! Combined standings for series of contests (e.g. qualification rounds
+//! and final)
+use crate::{build_monitor, Config, Monitor, PartyId, ProblemConfig, ProblemId, Run, Score};
+use serde::Serialize;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+};
+
+/// One contest of series
+#[derive(Debug, Clone)]
+pub struct Round {
+    pub monitor: Monitor,
+    /// Round scores are multiplied by weight. Must be finite.
+    pub weight: f64,
+    /// Maps problems of this round to problems of series.
+    ///
+    /// If set, only mapped problems are counted in round score, and their
+    /// scores are shown in `SeriesRow::problems`. Otherwise, round score
+    /// is party score in round monitor.
+    /// Scores of problems mapped to the same series problem are summed.
+    pub problem_mapping: Option<Vec<(ProblemId, ProblemId)>>,
+}
+
+impl Round {
+    /// Creates round from its runs. See [`build_monitor`](crate::build_monitor).
+    pub fn from_runs(
+        runs: &[Run],
+        problems: &[(ProblemId, ProblemConfig)],
+        parties: &[PartyId],
+        config: &Config,
+        weight: f64,
+    ) -> Round {
+        Round {
+            monitor: build_monitor(runs, problems, parties, config),
+            weight,
+            problem_mapping: None,
+        }
+    }
+
+    /// Returns party score in this round, and its distribution across series
+    /// problems, or `None` if party skipped this round.
+    fn raw_score(&self, party: PartyId) -> Option<(Score, BTreeMap<ProblemId, Score>)> {
+        let row = self.monitor.parties.get(&party)?;
+        // round monitor contains all parties, even those which did not
+        // submit anything
+        if row.problems.values().all(|cell| cell.empty) {
+            return None;
+        }
+        let mapping = match &self.problem_mapping {
+            Some(mapping) => mapping,
+            None => return Some((row.stats.score, BTreeMap::new())),
+        };
+        let mut problems = BTreeMap::new();
+        for (round_problem, series_problem) in mapping {
+            if let Some(cell) = row.problems.get(round_problem) {
+                *problems.entry(*series_problem).or_insert(0) += cell.score;
+            }
+        }
+        Some((problems.values().sum(), problems))
+    }
+}
+
+/// Determines how round scores are combined
+#[derive(Debug)]
+pub enum SeriesAggregation {
+    /// Weighted round scores are summed
+    Sum,
+    /// Only `n` best weighted round scores are summed
+    BestRounds(u32),
+    /// Round scores are scaled, so that best party in each round gets
+    /// 100 points (before applying weight), and then summed
+    Normalized,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SeriesRow {
+    pub party: PartyId,
+    /// Place of party, starting from 1. Parties which are tied share the same place.
+    pub place: u32,
+    pub score: f64,
+    /// Score gained in each round, or `None` if party skipped round
+    pub rounds: Vec<Option<f64>>,
+    /// Which rounds are counted in `score`. Skipped rounds are never counted.
+    pub counted: Vec<bool>,
+    /// Scores for series problems, gained in counted rounds
+    pub problems: BTreeMap<ProblemId, f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SeriesMonitor {
+    /// All parties which participated in at least one round, from first
+    /// place to last
+    pub rows: Vec<SeriesRow>,
+}
+
+/// Builds combined standings of several rounds.
+///
+/// Parties which skipped round (i.e. submitted nothing) get nothing for it.
+/// # Panics
+/// Panics if weight of some round is NaN or infinite
+pub fn build_series_monitor(rounds: &[Round], aggregation: &SeriesAggregation) -> SeriesMonitor {
+    for round in rounds {
+        assert!(round.weight.is_finite(), "round weight must be finite");
+    }
+    let parties: BTreeSet<PartyId> = rounds
+        .iter()
+        .flat_map(|round| round.monitor.parties.keys().copied())
+        .collect();
+    // parties which skipped all rounds are not listed
+    let raw_scores: BTreeMap<PartyId, Vec<_>> = parties
+        .iter()
+        .map(|&party| {
+            let scores: Vec<_> = rounds.iter().map(|round| round.raw_score(party)).collect();
+            (party, scores)
+        })
+        .filter(|(_, scores)| scores.iter().any(Option::is_some))
+        .collect();
+    // factor which converts raw score into score
+    let factors: Vec<f64> = rounds
+        .iter()
+        .enumerate()
+        .map(|(i, round)| match aggregation {
+            SeriesAggregation::Normalized => {
+                let max_score = raw_scores
+                    .values()
+                    .filter_map(|scores| scores[i].as_ref())
+                    .map(|(score, _)| *score)
+                    .max()
+                    .unwrap_or(0);
+                if max_score > 0 {
+                    round.weight * 100.0 / f64::from(max_score)
+                } else {
+                    0.0
+                }
+            }
+            _ => round.weight,
+        })
+        .collect();
+
+    let mut rows: Vec<SeriesRow> = raw_scores
+        .into_iter()
+        .map(|(party, raw_scores)| {
+            let round_scores: Vec<_> = raw_scores
+                .iter()
+                .zip(&factors)
+                .map(|(raw, factor)| raw.as_ref().map(|(score, _)| f64::from(*score) * factor))
+                .collect();
+            let mut counted: Vec<_> = round_scores.iter().map(Option::is_some).collect();
+            if let SeriesAggregation::BestRounds(n) = aggregation {
+                let mut participated: Vec<_> = (0..rounds.len()).filter(|&i| counted[i]).collect();
+                // stable sort: on ties, earlier rounds are preferred
+                participated.sort_by(|&a, &b| cmp_scores(round_scores[b], round_scores[a]));
+                for &i in participated.iter().skip(*n as usize) {
+                    counted[i] = false;
+                }
+            }
+            let mut score = 0.0;
+            let mut problems = BTreeMap::new();
+            for (i, raw) in raw_scores.iter().enumerate() {
+                let (raw_score, raw_problems) = match raw {
+                    Some(raw) if counted[i] => raw,
+                    _ => continue,
+                };
+                score += f64::from(*raw_score) * factors[i];
+                for (problem, problem_score) in raw_problems {
+                    *problems.entry(*problem).or_insert(0.0) +=
+                        f64::from(*problem_score) * factors[i];
+                }
+            }
+            SeriesRow {
+                party,
+                place: 0,
+                score,
+                rounds: round_scores,
+                counted,
+                problems,
+            }
+        })
+        .collect();
+
+    // stable sort: tied parties are ordered by id
+    rows.sort_by(|a, b| cmp_scores(Some(b.score), Some(a.score)));
+    for i in 0..rows.len() {
+        rows[i].place = if i > 0 && rows[i - 1].score == rows[i].score {
+            rows[i - 1].place
+        } else {
+            i as u32 + 1
+        };
+    }
+    SeriesMonitor { rows }
+}
+
+fn cmp_scores(a: Option<f64>, b: Option<f64>) -> Ordering {
+    a.partial_cmp(&b).expect("score is NaN")
+}
@@ -1,10 +1,31 @@
 use super::*;
 use maplit::btreemap as map;
 
-const SIMPLE_CONFIG: Config = Config {
-    penalty_aggregation: PenaltyAggregation::Sum,
-    score_problems: ProblemScoreAggregationTarget::All,
-};
+fn simple_config() -> Config {
+    Config {
+        penalty_aggregation: PenaltyAggregation::Sum,
+        attempt_penalty: 20,
+        score_problems: ProblemScoreAggregationTarget::All,
+        ranking: Ranking::Score,
+        freeze_time: None,
+        coloring: Coloring::ScoreBuckets(100),
+        start_offsets: BTreeMap::new(),
+        elapsed: None,
+    }
+}
+
+fn icpc_config() -> Config {
+    Config {
+        penalty_aggregation: PenaltyAggregation::Sum,
+        attempt_penalty: 20,
+        score_problems: ProblemScoreAggregationTarget::All,
+        ranking: Ranking::Icpc,
+        freeze_time: None,
+        coloring: Coloring::ScoreBuckets(100),
+        start_offsets: BTreeMap::new(),
+        elapsed: None,
+    }
+}
 
 const EMPTY_CELL: Cell = Cell {
     empty: true,
@@ -12,6 +33,10 @@ const EMPTY_CELL: Cell = Cell {
     score: 0,
     marked: false,
     attempts: 0,
+    accepted_at: None,
+    penalty: 0,
+    pending: 0,
+    accepted_time: None,
 };
 
 fn simple_problem_config(name: &str) -> ProblemConfig {
@@ -35,6 +60,10 @@ fn subtask_id(n: u32) -> SubtaskId {
     SubtaskId(NonZeroU32::new(n).unwrap())
 }
 
+fn at(minute: u64) -> Duration {
+    Duration::from_secs(minute * 60)
+}
+
 fn check_same(expected: Monitor, actual: Monitor) {
     pretty_assertions::assert_eq!(expected, actual);
 }
@@ -60,6 +89,7 @@ fn test_simple() {
             },
             party: user1,
             problem: prob_easy,
+            time: at(10),
         },
         // user2 also got good score on easy
         Run {
@@ -69,6 +99,7 @@ fn test_simple() {
             },
             party: user2,
             problem: prob_easy,
+            time: at(12),
         },
         // user2 attempted hard, but unsuccessfully
         Run {
@@ -77,6 +108,7 @@ fn test_simple() {
             },
             party: user2,
             problem: prob_hard,
+            time: at(30),
         },
         // well, easy is easy
         Run {
@@ -86,6 +118,7 @@ fn test_simple() {
             },
             party: korotkevich,
             problem: prob_easy,
+            time: at(5),
         },
         // hard is hard even for G. Korotkevich
         Run {
@@ -95,6 +128,7 @@ fn test_simple() {
             },
             party: korotkevich,
             problem: prob_hard,
+            time: at(40),
         },
         Run {
             subtasks: map! {
@@ -104,16 +138,20 @@ fn test_simple() {
             },
             party: korotkevich,
             problem: prob_hard,
+            time: at(50),
         },
     ];
     let parties = [user1, user2, korotkevich];
-    let monitor = build_monitor(&runs, &problems, &parties, &SIMPLE_CONFIG);
+    let monitor = build_monitor(&runs, &problems, &parties, &simple_config());
     let expected = Monitor {
         parties: map! {
             user1 => PartyRow {
                 stats: PartyStats {
                    color: 0,
-                   score: 100
+                   score: 100,
+                   solved: 1,
+                   penalty: 10,
+                   place: 2,
                 },
                 problems: map! {
                     prob_easy => Cell {
@@ -121,7 +159,11 @@ fn test_simple() {
                          ok: true,
                          score: 100,
                          marked: false,
-                         attempts: 0
+                         attempts: 0,
+                         accepted_at: Some(10),
+                         penalty: 10,
+                         pending: 0,
+                         accepted_time: Some(at(10)),
                     },
                     prob_hard => EMPTY_CELL,
                 }
@@ -129,7 +171,10 @@ fn test_simple() {
             user2 => PartyRow {
                 stats: PartyStats {
                     color: 1,
-                    score: 85
+                    score: 85,
+                    solved: 0,
+                    penalty: 0,
+                    place: 3,
                 },
                 problems: map! {
                     prob_easy => Cell {
@@ -137,29 +182,44 @@ fn test_simple() {
                         ok: false,
                         score: 85,
                         marked: false,
-                        attempts: 1
+                        attempts: 1,
+                        accepted_at: None,
+                        penalty: 0,
+                        pending: 0,
+                        accepted_time: None,
                     },
                     prob_hard => Cell {
                         empty: false,
                         ok: false,
                         score: 0,
                         marked: false,
-                        attempts: 1
+                        attempts: 1,
+                        accepted_at: None,
+                        penalty: 0,
+                        pending: 0,
+                        accepted_time: None,
                     }
                 }
             },
             korotkevich => PartyRow {
                 stats: PartyStats {
                     color: 0,
-                    score: 191
+                    score: 191,
+                    solved: 1,
+                    penalty: 5,
+                    place: 1,
                 },
                  problems: map! {
                      prob_easy => Cell {
                          empty: false,
                          ok: true,
                          score: 100,
-                         marked: false,
-                         attempts: 0
+                         marked: true,
+                         attempts: 0,
+                         accepted_at: Some(5),
+                         penalty: 5,
+                         pending: 0,
+                         accepted_time: Some(at(5)),
                      },
                      prob_hard => Cell {
                          empty: false,
@@ -167,6 +227,10 @@ fn test_simple() {
                          score: 91,
                          marked: false,
                          attempts: 2,
+                         accepted_at: None,
+                         penalty: 0,
+                         pending: 0,
+                         accepted_time: None,
                      }
                  }
             }
@@ -185,6 +249,666 @@ fn test_simple() {
                 }
             },
         },
+        ranking: vec![korotkevich, user1, user2],
     };
     check_same(expected, monitor);
 }
+
+fn icpc_run(party: PartyId, problem: ProblemId, minute: u64, ok: bool) -> Run {
+    Run {
+        subtasks: map! {
+            subtask_id(1) => if ok { 100 } else { 0 }
+        },
+        party,
+        problem,
+        time: at(minute),
+    }
+}
+
+#[test]
+fn test_icpc() {
+    let prob_a = problem_id(1);
+    let prob_b = problem_id(2);
+    let problems = [
+        (prob_a, simple_problem_config("A")),
+        (prob_b, simple_problem_config("B")),
+    ];
+    let team1 = party_id(1);
+    let team2 = party_id(2);
+    let team3 = party_id(3);
+    let team4 = party_id(4);
+    let runs = [
+        // team1: A with one rejected attempt (15 + 20), B at 100
+        icpc_run(team1, prob_a, 15, true),
+        icpc_run(team1, prob_a, 10, false),
+        icpc_run(team1, prob_b, 100, true),
+        // team2: A at 20, B with one rejected attempt (95 + 20)
+        icpc_run(team2, prob_a, 20, true),
+        icpc_run(team2, prob_b, 90, false),
+        icpc_run(team2, prob_b, 95, true),
+        // team3: runs after first full solution do not add penalty
+        icpc_run(team3, prob_a, 30, true),
+        icpc_run(team3, prob_a, 40, false),
+    ];
+    let parties = [team1, team2, team3, team4];
+
+    let monitor = build_monitor(&runs, &problems, &parties, &icpc_config());
+    let solved_and_penalty = |party| {
+        let stats = &monitor.parties[&party].stats;
+        (stats.solved, stats.penalty, stats.place)
+    };
+    assert_eq!(solved_and_penalty(team1), (2, 135, 2));
+    assert_eq!(solved_and_penalty(team2), (2, 135, 1));
+    assert_eq!(solved_and_penalty(team3), (1, 30, 3));
+    assert_eq!(solved_and_penalty(team4), (0, 0, 4));
+    // team2 solved last problem earlier
+    assert_eq!(monitor.ranking, vec![team2, team1, team3, team4]);
+    let cell = &monitor.parties[&team1].problems[&prob_a];
+    assert_eq!(
+        (cell.attempts, cell.accepted_at, cell.penalty),
+        (1, Some(15), 35)
+    );
+    let cell = &monitor.parties[&team3].problems[&prob_a];
+    assert_eq!((cell.attempts, cell.penalty), (0, 30));
+
+    let max_config = Config {
+        penalty_aggregation: PenaltyAggregation::Max,
+        ..icpc_config()
+    };
+    let monitor = build_monitor(&runs, &problems, &parties, &max_config);
+    assert_eq!(monitor.parties[&team1].stats.penalty, 100);
+    assert_eq!(monitor.parties[&team2].stats.penalty, 115);
+    assert_eq!(monitor.ranking, vec![team1, team2, team3, team4]);
+}
+
+#[test]
+fn test_icpc_ties_share_place() {
+    let prob = problem_id(1);
+    let problems = [(prob, simple_problem_config("A"))];
+    let team1 = party_id(1);
+    let team2 = party_id(2);
+    let team3 = party_id(3);
+    let runs = [
+        icpc_run(team1, prob, 50, true),
+        icpc_run(team2, prob, 50, true),
+    ];
+    let parties = [team1, team2, team3];
+    let monitor = build_monitor(&runs, &problems, &parties, &icpc_config());
+    assert_eq!(monitor.parties[&team1].stats.place, 1);
+    assert_eq!(monitor.parties[&team2].stats.place, 1);
+    assert_eq!(monitor.parties[&team3].stats.place, 3);
+}
+
+fn scored_run(party: PartyId, problem: ProblemId, minute: u64, subtasks: &[Score]) -> Run {
+    Run {
+        subtasks: subtasks
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| (subtask_id(i as u32 + 1), score))
+            .collect(),
+        party,
+        problem,
+        time: at(minute),
+    }
+}
+
+fn cell_score(
+    runs: &[Run],
+    score_runs: RunScoreAggregationTarget,
+    aggregation: RunScoreAggregation,
+) -> Score {
+    let problem = problem_id(1);
+    let problems = [(
+        problem,
+        ProblemConfig {
+            name: "A".to_string(),
+            accepted_score: 100,
+            score_runs,
+            aggregation,
+        },
+    )];
+    let monitor = build_monitor(runs, &problems, &[party_id(1)], &simple_config());
+    monitor.parties[&party_id(1)].problems[&problem].score
+}
+
+#[test]
+fn test_score_runs() {
+    let party = party_id(1);
+    let problem = problem_id(1);
+    // passed out of order to check that runs are ordered by time
+    let runs = [
+        scored_run(party, problem, 20, &[10, 30]),
+        scored_run(party, problem, 10, &[40, 0]),
+        scored_run(party, problem, 30, &[0, 20]),
+    ];
+    use RunScoreAggregation::{Max, MergeSubtasks};
+    use RunScoreAggregationTarget::{All, Best, Latest};
+    assert_eq!(cell_score(&runs, All, Max), 40);
+    assert_eq!(cell_score(&runs, All, MergeSubtasks), 70);
+    assert_eq!(cell_score(&runs, Latest(1), Max), 20);
+    assert_eq!(cell_score(&runs, Latest(1), MergeSubtasks), 20);
+    assert_eq!(cell_score(&runs, Latest(2), Max), 40);
+    assert_eq!(cell_score(&runs, Latest(2), MergeSubtasks), 40);
+    assert_eq!(cell_score(&runs, Latest(5), MergeSubtasks), 70);
+    assert_eq!(cell_score(&runs, Best, Max), 40);
+    assert_eq!(cell_score(&runs, Best, MergeSubtasks), 40);
+    assert_eq!(cell_score(&[], Best, Max), 0);
+}
+
+#[test]
+fn test_score_runs_best_prefers_latest() {
+    let party = party_id(1);
+    let problem = problem_id(1);
+    let runs = [
+        scored_run(party, problem, 10, &[40, 0]),
+        scored_run(party, problem, 20, &[0, 40]),
+    ];
+    // both runs have score 40, but only latest one is used
+    assert_eq!(
+        cell_score(
+            &runs,
+            RunScoreAggregationTarget::Best,
+            RunScoreAggregation::MergeSubtasks
+        ),
+        40
+    );
+    let runs = [
+        scored_run(party, problem, 10, &[40, 0]),
+        scored_run(party, problem, 20, &[0, 40]),
+        scored_run(party, problem, 30, &[30, 0]),
+    ];
+    let cell = cell_score(
+        &runs,
+        RunScoreAggregationTarget::Latest(2),
+        RunScoreAggregation::MergeSubtasks,
+    );
+    assert_eq!(cell, 70);
+}
+
+#[test]
+fn test_score_problems() {
+    let party = party_id(1);
+    let problems: Vec<_> = (1..=4)
+        .map(|i| (problem_id(i), simple_problem_config(&i.to_string())))
+        .collect();
+    let runs: Vec<_> = [30, 100, 70, 50]
+        .iter()
+        .enumerate()
+        .map(|(i, &score)| scored_run(party, problem_id(i as u32 + 1), 10, &[score]))
+        .collect();
+    let party_score = |score_problems| {
+        let config = Config {
+            score_problems,
+            ..simple_config()
+        };
+        build_monitor(&runs, &problems, &[party], &config).parties[&party]
+            .stats
+            .score
+    };
+    assert_eq!(party_score(ProblemScoreAggregationTarget::All), 250);
+    assert_eq!(party_score(ProblemScoreAggregationTarget::Best(2)), 170);
+    assert_eq!(party_score(ProblemScoreAggregationTarget::Best(3)), 220);
+    assert_eq!(party_score(ProblemScoreAggregationTarget::Best(10)), 250);
+    assert_eq!(party_score(ProblemScoreAggregationTarget::Best(0)), 0);
+}
+
+fn run_id(n: u32) -> RunId {
+    RunId(NonZeroU32::new(n).unwrap())
+}
+
+#[test]
+fn test_monitor_builder() {
+    let prob_a = problem_id(1);
+    let prob_b = problem_id(2);
+    let problems = || {
+        vec![
+            (prob_a, simple_problem_config("A")),
+            (prob_b, simple_problem_config("B")),
+        ]
+    };
+    let team1 = party_id(1);
+    let team2 = party_id(2);
+    let team3 = party_id(3);
+    let parties = vec![team1, team2, team3];
+    let changed_parties = |diff: &MonitorDiff| diff.parties.keys().copied().collect::<Vec<_>>();
+    let mut builder = MonitorBuilder::new(problems(), parties.clone(), icpc_config());
+
+    let diff = builder.apply_all(vec![
+        RunChange::Insert(run_id(1), icpc_run(team1, prob_a, 10, false)),
+        RunChange::Insert(run_id(2), icpc_run(team1, prob_a, 15, true)),
+        RunChange::Insert(run_id(3), icpc_run(team2, prob_b, 20, true)),
+    ]);
+    // team3 has no runs, but its place and color changed
+    assert_eq!(changed_parties(&diff), vec![team1, team2, team3]);
+    assert_eq!(diff.problems.len(), 2);
+    assert_eq!(diff.ranking, Some(vec![team2, team1, team3]));
+
+    // rejudge: first attempt of team1 is now accepted
+    let diff = builder.apply(RunChange::Update(
+        run_id(1),
+        icpc_run(team1, prob_a, 10, true),
+    ));
+    assert_eq!(changed_parties(&diff), vec![team1, team2]);
+    assert_eq!(diff.parties[&team1].stats.penalty, 10);
+    assert_eq!(diff.ranking, Some(vec![team1, team2, team3]));
+    assert_eq!(diff.problems[&prob_a].accepted_runs, 2);
+
+    // run after full solution changes problem stats, but not rows
+    let diff = builder.apply(RunChange::Insert(
+        run_id(4),
+        icpc_run(team1, prob_a, 30, false),
+    ));
+    assert!(diff.parties.is_empty());
+    assert_eq!(diff.ranking, None);
+    assert_eq!(diff.problems[&prob_a].total_runs, 3);
+
+    let diff = builder.apply(RunChange::Insert(
+        run_id(5),
+        icpc_run(team3, prob_b, 40, false),
+    ));
+    assert_eq!(changed_parties(&diff), vec![team3]);
+    assert_eq!(diff.parties[&team3].problems[&prob_b].attempts, 1);
+
+    // team2 and team3 are now tied
+    let diff = builder.apply(RunChange::Delete(run_id(3)));
+    assert_eq!(changed_parties(&diff), vec![team2, team3]);
+    assert_eq!(diff.parties[&team3].stats.place, 2);
+    assert_eq!(diff.ranking, None);
+    // deleting unknown run is no-op
+    assert!(builder.apply(RunChange::Delete(run_id(3))).is_empty());
+
+    let expected = build_monitor(
+        &[
+            icpc_run(team1, prob_a, 10, true),
+            icpc_run(team1, prob_a, 15, true),
+            icpc_run(team1, prob_a, 30, false),
+            icpc_run(team3, prob_b, 40, false),
+        ],
+        &problems(),
+        &parties,
+        &icpc_config(),
+    );
+    pretty_assertions::assert_eq!(&expected, builder.monitor());
+}
+
+fn frozen_contest() -> (Vec<Run>, Vec<PartyId>, Config) {
+    let prob_a = problem_id(1);
+    let prob_b = problem_id(2);
+    let team1 = party_id(1);
+    let team2 = party_id(2);
+    let team3 = party_id(3);
+    let runs = vec![
+        icpc_run(team1, prob_a, 10, true),
+        icpc_run(team1, prob_b, 62, true),
+        icpc_run(team2, prob_a, 20, true),
+        icpc_run(team2, prob_b, 55, true),
+        icpc_run(team3, prob_a, 65, false),
+        icpc_run(team3, prob_a, 80, true),
+    ];
+    let config = Config {
+        freeze_time: Some(at(60)),
+        ..icpc_config()
+    };
+    (runs, vec![team1, team2, team3], config)
+}
+
+fn two_problems() -> Vec<(ProblemId, ProblemConfig)> {
+    vec![
+        (problem_id(1), simple_problem_config("A")),
+        (problem_id(2), simple_problem_config("B")),
+    ]
+}
+
+#[test]
+fn test_freeze() {
+    let (runs, parties, config) = frozen_contest();
+    let (team1, team2, team3) = (parties[0], parties[1], parties[2]);
+    let problems = two_problems();
+
+    let public = build_monitor_for(&runs, &problems, &parties, &config, Visibility::Public);
+    assert_eq!(public.ranking, vec![team2, team1, team3]);
+    let cell = &public.parties[&team1].problems[&problem_id(2)];
+    assert_eq!((cell.empty, cell.ok, cell.pending), (false, false, 1));
+    let cell = &public.parties[&team3].problems[&problem_id(1)];
+    assert_eq!((cell.attempts, cell.pending, cell.penalty), (0, 2, 0));
+    let stats = &public.stats.problems[&problem_id(1)];
+    assert_eq!((stats.total_runs, stats.accepted_runs), (4, 2));
+
+    let jury = build_monitor(&runs, &problems, &parties, &config);
+    assert_eq!(jury.ranking, vec![team1, team2, team3]);
+    assert_eq!(jury.parties[&team3].stats.penalty, 100);
+    assert!(jury
+        .parties
+        .values()
+        .flat_map(|row| row.problems.values())
+        .all(|cell| cell.pending == 0));
+}
+
+#[test]
+fn test_resolver() {
+    let (runs, parties, config) = frozen_contest();
+    let (team1, team2, team3) = (parties[0], parties[1], parties[2]);
+    let jury = build_monitor(&runs, &two_problems(), &parties, &config);
+    let runs = runs
+        .into_iter()
+        .enumerate()
+        .map(|(i, run)| (run_id(i as u32 + 1), run));
+    let mut resolver = Resolver::new(runs, two_problems(), parties.clone(), config);
+    assert_eq!(resolver.monitor().ranking, vec![team2, team1, team3]);
+
+    // team3 is the last one
+    let step = resolver.step().unwrap();
+    assert_eq!((step.party, step.problem), (team3, problem_id(1)));
+    assert_eq!(step.diff.parties[&team3].stats.solved, 1);
+    assert_eq!(step.diff.ranking, None);
+
+    // now team1 is the last party with pending cells
+    let step = resolver.step().unwrap();
+    assert_eq!((step.party, step.problem), (team1, problem_id(2)));
+    assert_eq!(step.diff.ranking, Some(vec![team1, team2, team3]));
+
+    assert!(resolver.step().is_none());
+    pretty_assertions::assert_eq!(&jury, resolver.monitor());
+}
+
+#[test]
+fn test_first_solutions_marked() {
+    let prob_a = problem_id(1);
+    let prob_b = problem_id(2);
+    let team1 = party_id(1);
+    let team2 = party_id(2);
+    let team3 = party_id(3);
+    let parties = vec![team1, team2, team3];
+    let mut builder = MonitorBuilder::new(two_problems(), parties.clone(), icpc_config());
+    builder.apply_all(vec![
+        RunChange::Insert(run_id(1), icpc_run(team1, prob_a, 30, true)),
+        RunChange::Insert(run_id(2), icpc_run(team2, prob_a, 20, true)),
+        RunChange::Insert(run_id(3), icpc_run(team3, prob_a, 20, true)),
+        RunChange::Insert(run_id(4), icpc_run(team1, prob_b, 10, false)),
+    ]);
+    let marked =
+        |monitor: &Monitor, party, problem| monitor.parties[&party].problems[&problem].marked;
+    let monitor = builder.monitor();
+    // both first solutions are marked
+    assert!(!marked(monitor, team1, prob_a));
+    assert!(marked(monitor, team2, prob_a));
+    assert!(marked(monitor, team3, prob_a));
+    assert!(!marked(monitor, team1, prob_b));
+
+    let diff = builder.apply_all(vec![
+        RunChange::Update(run_id(2), icpc_run(team2, prob_a, 20, false)),
+        RunChange::Delete(run_id(3)),
+    ]);
+    assert!(marked(builder.monitor(), team1, prob_a));
+    assert!(diff.parties[&team1].problems[&prob_a].marked);
+    assert!(!diff.parties[&team2].problems[&prob_a].marked);
+
+    // pending cells are not marked in public monitor
+    let (runs, parties, config) = frozen_contest();
+    let public = build_monitor_for(
+        &runs,
+        &two_problems(),
+        &parties,
+        &config,
+        Visibility::Public,
+    );
+    assert!(marked(&public, team2, prob_b));
+    assert!(!marked(&public, team3, prob_a));
+}
+
+#[test]
+fn test_coloring() {
+    let prob_count = 3;
+    let problems: Vec<_> = (1..=prob_count)
+        .map(|i| (problem_id(i), simple_problem_config(&i.to_string())))
+        .collect();
+    let parties: Vec<_> = (1..=5).map(party_id).collect();
+    // parties solved 3, 2, 2, 1 and 0 problems
+    let mut runs = Vec::new();
+    for (&party, &solved) in parties.iter().zip(&[3, 2, 2, 1, 0]) {
+        for problem in 1..=prob_count {
+            runs.push(icpc_run(party, problem_id(problem), 10, problem <= solved));
+        }
+    }
+    let colors = |coloring| {
+        let config = Config {
+            coloring,
+            ..icpc_config()
+        };
+        let monitor = build_monitor(&runs, &problems, &parties, &config);
+        parties
+            .iter()
+            .map(|party| monitor.parties[party].stats.color)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(colors(Coloring::Solved), vec![0, 1, 1, 2, 3]);
+    assert_eq!(colors(Coloring::ScoreBuckets(200)), vec![0, 0, 0, 1, 1]);
+    let medals = |bronze| {
+        Coloring::Medals(MedalCutoffs {
+            gold: Cutoff::Rank(1),
+            silver: Cutoff::Rank(2),
+            bronze,
+        })
+    };
+    // tied parties get the same medal
+    assert_eq!(colors(medals(Cutoff::Percent(80))), vec![0, 1, 1, 2, 3]);
+    assert_eq!(colors(medals(Cutoff::Percent(60))), vec![0, 1, 1, 3, 3]);
+    assert_eq!(colors(medals(Cutoff::Rank(5))), vec![0, 1, 1, 2, 2]);
+}
+
+#[test]
+#[should_panic(expected = "score bucket size must be positive")]
+fn test_empty_score_bucket() {
+    let config = Config {
+        coloring: Coloring::ScoreBuckets(0),
+        ..icpc_config()
+    };
+    build_monitor(&[], &two_problems(), &[party_id(1)], &config);
+}
+
+#[test]
+fn test_export() {
+    let (runs, parties, config) = frozen_contest();
+    let (team1, team2) = (parties[0], parties[1]);
+    let problems = two_problems();
+    let party_names = map! {
+        team1 => "Team \"One\", <b>".to_string(),
+        team2 => "=Team Two".to_string(),
+    };
+    let ctx = export::ExportContext {
+        title: "Final",
+        problems: &problems,
+        party_names: &party_names,
+        config: &config,
+    };
+    let public = build_monitor_for(&runs, &problems, &parties, &config, Visibility::Public);
+
+    assert_eq!(
+        export::to_csv(&public, &ctx),
+        "Place,Party,A,B,Solved,Penalty\r\n\
+         1,'=Team Two,'+,'+,2,75\r\n\
+         2,\"Team \"\"One\"\", <b>\",'+,?1,1,10\r\n\
+         3,3,?2,,0,0\r\n"
+    );
+
+    let html = export::to_html(&public, &ctx);
+    assert!(html.contains("<td class=\"party\">Team &quot;One&quot;, &lt;b&gt;</td>"));
+    assert!(html.contains("<td class=\"pending\">?2</td>"));
+    assert!(html.contains("<tr class=\"stripe-0\">"));
+
+    let scoreboard = serde_json::to_value(export::cds_scoreboard(&public, &ctx)).unwrap();
+    assert_eq!(
+        scoreboard["rows"][1],
+        serde_json::json!({
+            "rank": 2,
+            "team_id": "1",
+            "score": {"num_solved": 1, "total_time": 10},
+            "problems": [
+                {
+                    "problem_id": "1",
+                    "num_judged": 1,
+                    "num_pending": 0,
+                    "solved": true,
+                    "time": 10,
+                    "first_to_solve": true
+                },
+                {
+                    "problem_id": "2",
+                    "num_judged": 0,
+                    "num_pending": 1,
+                    "solved": false,
+                    "first_to_solve": false
+                }
+            ]
+        })
+    );
+
+    let runs: Vec<_> = runs
+        .into_iter()
+        .enumerate()
+        .map(|(i, run)| (run_id(i as u32 + 1), run))
+        .collect();
+    let start = "2020-10-01T10:00:00Z".parse().unwrap();
+    let events = export::cds_judgements(&runs, &ctx, Visibility::Jury, Some(start));
+    assert_eq!(events.len(), 6);
+    assert_eq!(
+        serde_json::to_value(&events[5]).unwrap(),
+        serde_json::json!({
+            "type": "judgements",
+            "id": "6",
+            "op": "create",
+            "data": {
+                "id": "6",
+                "submission_id": "6",
+                "judgement_type_id": "AC",
+                "start_time": "2020-10-01T11:20:00+00:00",
+                "start_contest_time": "1:20:00.000",
+                "end_time": "2020-10-01T11:20:00+00:00",
+                "end_contest_time": "1:20:00.000"
+            }
+        })
+    );
+    let events = export::cds_judgements(&runs, &ctx, Visibility::Public, None);
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|event| event.data.start_time.is_none()));
+}
+
+#[test]
+fn test_virtual_participation() {
+    let prob_a = problem_id(1);
+    let prob_b = problem_id(2);
+    let original = party_id(1);
+    let virtual_party = party_id(2);
+    let parties = [original, virtual_party];
+    // virtual party started one day later
+    let offset = at(24 * 60);
+    let later = |minute| offset + at(minute);
+    let runs = [
+        icpc_run(original, prob_a, 10, true),
+        icpc_run(original, prob_b, 50, true),
+        Run {
+            time: later(5),
+            ..icpc_run(virtual_party, prob_a, 0, true)
+        },
+        Run {
+            time: later(70),
+            ..icpc_run(virtual_party, prob_b, 0, true)
+        },
+    ];
+    let config = Config {
+        start_offsets: map! { virtual_party => offset },
+        ..icpc_config()
+    };
+    let monitor = build_monitor(&runs, &two_problems(), &parties, &config);
+    let cell = &monitor.parties[&virtual_party].problems[&prob_a];
+    assert_eq!((cell.accepted_at, cell.marked), (Some(5), true));
+    assert_eq!(monitor.parties[&virtual_party].stats.penalty, 75);
+    assert_eq!(monitor.ranking, vec![original, virtual_party]);
+
+    // ghost view: virtual party is at 30th minute of contest
+    let ghost_config = Config {
+        elapsed: Some(at(30)),
+        ..config
+    };
+    let monitor = build_monitor(&runs, &two_problems(), &parties, &ghost_config);
+    assert!(monitor.parties[&original].problems[&prob_b].empty);
+    assert!(monitor.parties[&virtual_party].problems[&prob_b].empty);
+    assert_eq!(monitor.ranking, vec![virtual_party, original]);
+    assert_eq!(monitor.stats.problems[&prob_b].total_runs, 0);
+}
+
+#[test]
+fn test_series() {
+    let problems: Vec<_> = (1..=2)
+        .map(|i| (problem_id(i), simple_problem_config(&i.to_string())))
+        .collect();
+    // fourth party skipped both rounds, so it is not listed
+    let parties: Vec<_> = (1..=4).map(party_id).collect();
+    let (alice, bob, carol) = (parties[0], parties[1], parties[2]);
+    let round = |scores: &[(PartyId, Score, Score)], weight| {
+        let mut runs = Vec::new();
+        for &(party, a, b) in scores {
+            runs.push(scored_run(party, problem_id(1), 10, &[a]));
+            runs.push(scored_run(party, problem_id(2), 10, &[b]));
+        }
+        Round::from_runs(&runs, &problems, &parties, &simple_config(), weight)
+    };
+    // carol skipped first round
+    let first = round(&[(alice, 100, 100), (bob, 50, 0)], 1.0);
+    let second = round(&[(alice, 0, 0), (bob, 50, 50), (carol, 50, 0)], 2.0);
+    let rounds = vec![first, second];
+    let scores = |aggregation| {
+        build_series_monitor(&rounds, &aggregation)
+            .rows
+            .into_iter()
+            .map(|row| (row.party, row.score, row.place))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        scores(SeriesAggregation::Sum),
+        vec![(bob, 250.0, 1), (alice, 200.0, 2), (carol, 100.0, 3)]
+    );
+    assert_eq!(
+        scores(SeriesAggregation::BestRounds(1)),
+        vec![(alice, 200.0, 1), (bob, 200.0, 1), (carol, 100.0, 3)]
+    );
+    // first round: alice 100, bob 25; second: alice 0, bob 200, carol 100
+    assert_eq!(
+        scores(SeriesAggregation::Normalized),
+        vec![(bob, 225.0, 1), (alice, 100.0, 2), (carol, 100.0, 2)]
+    );
+
+    let series = build_series_monitor(&rounds, &SeriesAggregation::BestRounds(1));
+    let carol_row = series.rows.iter().find(|row| row.party == carol).unwrap();
+    assert_eq!(carol_row.rounds, vec![None, Some(100.0)]);
+    assert_eq!(carol_row.counted, vec![false, true]);
+
+    // both rounds share the same problem set, but only second problem counts
+    let mapping = vec![(problem_id(2), problem_id(1))];
+    let rounds: Vec<_> = rounds
+        .into_iter()
+        .map(|round| Round {
+            problem_mapping: Some(mapping.clone()),
+            ..round
+        })
+        .collect();
+    let series = build_series_monitor(&rounds, &SeriesAggregation::Sum);
+    let row = &series.rows[0];
+    assert_eq!((row.party, row.score), (alice, 100.0));
+    assert_eq!(row.problems, map! { problem_id(1) => 100.0 });
+    assert_eq!(series.rows[1].problems, map! { problem_id(1) => 100.0 });
+}
+
+#[test]
+#[should_panic(expected = "round weight must be finite")]
+fn test_series_nan_weight() {
+    let round = Round::from_runs(
+        &[],
+        &two_problems(),
+        &[party_id(1)],
+        &simple_config(),
+        f64::NAN,
+    );
+    build_series_monitor(&[round], &SeriesAggregation::Sum);
+}